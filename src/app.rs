@@ -0,0 +1,2783 @@
+use eframe::egui;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::backend::{EventSink, InputBackend, WindowsBackend};
+use crate::emergency::GlobalHotkey;
+use crate::events::{self, EventType, MacroEvent};
+use crate::export::ExportFormat;
+use crate::calibration::{self, InputProbe, SelfTest};
+use crate::preflight::{ScreenBounds, Warning};
+use crate::library::{self, FileSummary, LibraryEntry};
+use crate::hooks::{self, GlobalHooks};
+use crate::humanize::HumanizeSettings;
+use crate::recorder::{self, CaptureMode, IdleChange, MacroRecorder, RecordingLimit};
+use crate::player::{CursorMethod, FaultAction, MacroPlayer, MissingFieldPolicy, PlaybackEnd, PlaybackFault, TimingMode, DEFAULT_FAILSAFE_THRESHOLD_PX};
+use crate::recovery;
+use crate::stats::{self, MacroStats};
+use crate::hotkeys::{self, Hotkey, HotkeyAction, HotkeyConfig};
+use crate::settings::{self, Settings};
+use crate::target::{self, RecordTarget, WindowInfo};
+use crate::tray::{TrayCommand, TrayIcon};
+
+// First fresh key press this frame as a bindable chord
+fn pressed_hotkey(input: &egui::InputState) -> Option<Hotkey> {
+    input.events.iter().find_map(|event| match event {
+        egui::Event::Key { key, pressed: true, repeat: false, modifiers } => {
+            hotkeys::vk_from_name(key.name()).map(|vk| Hotkey {
+                ctrl: modifiers.ctrl,
+                shift: modifiers.shift,
+                alt: modifiers.alt,
+                vk,
+            })
+        }
+        _ => None,
+    })
+}
+
+// Also how the tray finds the window again after hiding it
+const APP_TITLE: &str = "Macro Recorder - Rust Edition";
+
+pub fn run() -> Result<(), eframe::Error> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([900.0, 600.0])
+            .with_min_inner_size([800.0, 500.0]),
+        ..Default::default()
+    };
+    
+    eframe::run_native(
+        APP_TITLE,
+        options,
+        Box::new(|cc| {
+            // This gives us image support:
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            Box::new(MacroApp::new(cc))
+        }),
+    )
+}
+
+#[derive(Debug, Clone)]
+enum AppState {
+    Idle,
+    Recording,
+    RecordingPaused,
+    // Play was pressed; the macro starts once playback_countdown runs out
+    Countdown,
+    Playing,
+    PlayingPaused,
+}
+
+// Fields of one event being edited from the table; None where the event has no such field
+#[derive(Debug, Clone)]
+struct EventEdit {
+    index: usize,
+    timestamp: f64,
+    position: Option<(i64, i64)>,
+    key_name: Option<String>,
+}
+
+impl EventEdit {
+    fn from_event(index: usize, event: &MacroEvent) -> Self {
+        let position = match (event.data.get("x").and_then(|x| x.as_i64()), event.data.get("y").and_then(|y| y.as_i64())) {
+            (Some(x), Some(y)) => Some((x, y)),
+            _ => None,
+        };
+        Self {
+            index,
+            timestamp: event.timestamp,
+            position,
+            key_name: event.data.get("key_name").and_then(|key| key.as_str()).map(|key| key.to_string()),
+        }
+    }
+    
+    fn apply_to(&self, event: &MacroEvent) -> MacroEvent {
+        let mut edited = event.clone();
+        edited.timestamp = self.timestamp;
+        if let Some((x, y)) = self.position {
+            edited.data["x"] = serde_json::json!(x);
+            edited.data["y"] = serde_json::json!(y);
+        }
+        if let Some(key_name) = &self.key_name {
+            edited.data["key_name"] = serde_json::json!(key_name);
+        }
+        edited
+    }
+}
+
+// A recording counts as unsaved if it has events and changed since it was last written out
+fn has_unsaved_work(event_count: usize, revision: u64, saved_revision: Option<u64>) -> bool {
+    event_count > 0 && saved_revision != Some(revision)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ToggleAction {
+    StartRecording,
+    StopRecording,
+    Ignore,
+}
+
+// What the record toggle hotkey does from each state; it never interrupts playback
+fn toggle_transition(state: &AppState) -> ToggleAction {
+    match state {
+        AppState::Idle => ToggleAction::StartRecording,
+        AppState::Recording | AppState::RecordingPaused => ToggleAction::StopRecording,
+        AppState::Countdown | AppState::Playing | AppState::PlayingPaused => ToggleAction::Ignore,
+    }
+}
+
+const FUNCTION_KEYS: [egui::Key; 12] = [
+    egui::Key::F1, egui::Key::F2, egui::Key::F3, egui::Key::F4,
+    egui::Key::F5, egui::Key::F6, egui::Key::F7, egui::Key::F8,
+    egui::Key::F9, egui::Key::F10, egui::Key::F11, egui::Key::F12,
+];
+
+// VK_F1 is 0x70 and the rest follow in order
+fn function_key_vk(number: u8) -> u32 {
+    0x6F + number as u32
+}
+
+// How often the UI should refresh on its own; None means repaint only on input
+fn repaint_interval(state: &AppState) -> Option<Duration> {
+    match state {
+        AppState::Idle => None,
+        AppState::Recording | AppState::Playing => Some(Duration::from_millis(50)),
+        AppState::RecordingPaused | AppState::PlayingPaused => Some(Duration::from_millis(250)),
+        AppState::Countdown => Some(Duration::from_millis(100)),
+    }
+}
+
+// Tooltip and menu header of the tray icon
+fn tray_status(state: &AppState) -> &'static str {
+    match state {
+        AppState::Idle => "Macro Recorder: idle",
+        AppState::Recording => "Macro Recorder: recording",
+        AppState::RecordingPaused => "Macro Recorder: recording paused",
+        AppState::Countdown => "Macro Recorder: starting playback",
+        AppState::Playing => "Macro Recorder: playing",
+        AppState::PlayingPaused => "Macro Recorder: playback paused",
+    }
+}
+
+// Whole seconds left before `deadline`, rounded up so the display reads 3, 2, 1
+fn countdown_secs_left(deadline: Instant, now: Instant) -> u64 {
+    (deadline.saturating_duration_since(now).as_millis() as u64).div_ceil(1000)
+}
+
+fn format_overlay_text(elapsed: Duration, paused: bool) -> String {
+    let total = elapsed.as_secs();
+    let marker = if paused { "⏸" } else { "●" };
+    format!("{} REC {:02}:{:02}", marker, total / 60, total % 60)
+}
+
+// Event kinds the log can hide; app messages have no category and always show
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LogCategory {
+    Keys,
+    Clicks,
+    Scroll,
+    Moves,
+}
+
+impl LogCategory {
+    fn of(event: &MacroEvent) -> Option<Self> {
+        match event.event_type {
+            EventType::KeyDown | EventType::KeyUp | EventType::TextInput => Some(LogCategory::Keys),
+            EventType::MouseDown | EventType::MouseUp | EventType::Click => Some(LogCategory::Clicks),
+            EventType::MouseScroll | EventType::MouseHScroll => Some(LogCategory::Scroll),
+            EventType::MouseMove | EventType::Hover => Some(LogCategory::Moves),
+            EventType::FocusChange | EventType::Delay => None,
+        }
+    }
+}
+
+struct LogEntry {
+    text: String,
+    category: Option<LogCategory>,
+}
+
+// Mouse moves arrive in bursts; log at most one per interval
+const MOVE_LOG_INTERVAL: Duration = Duration::from_millis(100);
+
+// Live events waiting for the UI thread; the oldest are dropped if it falls this far behind
+const LIVE_LOG_BACKLOG: usize = 256;
+
+fn should_log_move(last_logged: Option<Instant>, now: Instant) -> bool {
+    match last_logged {
+        Some(last) => now.duration_since(last) >= MOVE_LOG_INTERVAL,
+        None => true,
+    }
+}
+
+// Files load_recording understands, judged by extension
+fn is_macro_path(path: &str) -> bool {
+    let is_mcr = std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("mcr"))
+        .unwrap_or(false);
+    is_mcr || events::is_gzip_path(path) || events::is_json_path(path)
+}
+
+// Gives the user time to move the cursor to the target before it's captured
+const CURSOR_PICK_DELAY: Duration = Duration::from_secs(3);
+
+struct MacroApp {
+    state: AppState,
+    recorder: Arc<Mutex<MacroRecorder>>,
+    player: Arc<Mutex<MacroPlayer>>,
+    hooks: Arc<Mutex<GlobalHooks>>,
+    // Captures through `hooks`, so their settings apply while recording
+    capture: WindowsBackend,
+    input_probe: Arc<InputProbe>,
+    self_test: Option<SelfTest>,
+    
+    // UI state
+    current_file: Option<String>,
+    saved_revision: Option<u64>,
+    // Recorder revision when current_file was last saved or loaded; past it, the file is stale
+    file_revision: Option<u64>,
+    confirm_exit: bool,
+    // Cut point typed into the split dialog, shown while Some
+    split_at_secs: Option<f64>,
+    exit_confirmed: bool,
+    preflight_warnings: Vec<Warning>,
+    playback_fault: Option<PlaybackFault>,
+    // Limit that ended the last recording, shown until dismissed
+    limit_notice: Option<RecordingLimit>,
+    show_library: bool,
+    show_histogram: bool,
+    // Statistics of the recording at the given revision, recomputed when it changes
+    stats_cache: Option<(u64, MacroStats)>,
+    show_event_table: bool,
+    editing_event: Option<EventEdit>,
+    histogram_bucket_ms: u64,
+    library_dir: Option<std::path::PathBuf>,
+    library_entries: Vec<LibraryEntry>,
+    selected_event: Option<usize>,
+    // Event the next playback begins at; kept through the pre-flight prompt
+    play_from_index: usize,
+    retime_factor: f64,
+    retime_offset: f64,
+    // Pause between the current recording and a file appended to it
+    append_gap_ms: u64,
+    scroll_merge_ms: u64,
+    insert_delay_ms: u64,
+    cursor_pick_deadline: Option<Instant>,
+    log_messages: VecDeque<LogEntry>,
+    // Filled by the hook callback with each event the recorder kept, drained into the log
+    live_events: Arc<Mutex<VecDeque<MacroEvent>>>,
+    max_log_lines: usize,
+    last_move_log: Option<Instant>,
+    last_autosave: Option<Instant>,
+    pending_recovery: Option<FileSummary>,
+    
+    // Statistics
+    events_recorded: usize,
+    // Position within the current loop; it restarts at 0 on every iteration
+    events_played: usize,
+    current_loop: usize,
+    events_skipped: usize,
+    recording_time: f32,
+    
+    // Settings
+    show_mouse_moves: bool,
+    show_keys: bool,
+    show_clicks: bool,
+    show_scrolls: bool,
+    min_move_interval_ms: u64,
+    min_move_distance_px: u32,
+    playback_speed: f32,
+    abort_on_malformed: bool,
+    pause_on_error: bool,
+    click_hold_ms: u64,
+    activation_settle_ms: u64,
+    double_clicks: bool,
+    fixed_interval: bool,
+    fixed_interval_ms: u64,
+    repeat_count: usize,
+    infinite_loop: bool,
+    loop_delay_ms: u64,
+    playback_countdown_secs: u64,
+    playback_countdown: Option<Instant>,
+    block_input_during_playback: bool,
+    // Release keys a loaded file presses but never lets go of
+    repair_on_load: bool,
+    rebase_on_load: bool,
+    failsafe_enabled: bool,
+    absolute_mouse_input: bool,
+    smooth_motion: bool,
+    smooth_motion_hz: u32,
+    humanize: bool,
+    humanize_timing_pct: u32,
+    humanize_scatter_px: u32,
+    humanize_seed: u64,
+    show_recording_overlay: bool,
+    close_to_tray: bool,
+    hotkeys: HotkeyConfig,
+    // Action waiting for its new key combo from the Settings menu
+    rebinding_hotkey: Option<HotkeyAction>,
+    // The play hotkey works from any window, so it's registered with the OS rather than read
+    // from egui input; the flag is set on the hotkey's own thread
+    play_hotkey: Option<GlobalHotkey>,
+    play_hotkey_pressed: Arc<AtomicBool>,
+    tray: Option<TrayIcon>,
+    // For waking the UI from the tray and hotkey threads while it's idle or hidden
+    egui_ctx: egui::Context,
+    // F-key number (1-12) that toggles recording, if any
+    record_toggle_key: Option<u8>,
+    recognize_shortcuts: bool,
+    capture_clicks_only: bool,
+    detect_hovers: bool,
+    hover_threshold_ms: u64,
+    post_to_window: bool,
+    target_window_title: String,
+    // Recording restricted to this window (or its whole process); refreshed from the Settings menu
+    record_target: Option<WindowInfo>,
+    record_target_by_process: bool,
+    record_target_choices: Vec<WindowInfo>,
+    autosave_enabled: bool,
+    autosave_secs: u64,
+    max_events: usize,
+    max_duration_secs: u64,
+    idle_pause_enabled: bool,
+    idle_pause_secs: u64,
+    compact_mouse_paths: bool,
+    recent_files: Vec<String>,
+    // What's on disk, to notice when the settings above change
+    saved_settings: Settings,
+}
+
+impl MacroApp {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let hooks = Arc::new(Mutex::new(GlobalHooks::new()));
+        let recorder = Arc::new(Mutex::new(MacroRecorder::new()));
+        let player = Arc::new(Mutex::new(MacroPlayer::new()));
+        
+        let input_probe = Arc::new(InputProbe::new());
+        
+        let hotkey_config = HotkeyConfig::load(&hotkeys::hotkey_config_path());
+        let settings = Settings::load(&settings::settings_path());
+        
+        let live_events = Arc::new(Mutex::new(VecDeque::new()));
+        hooks.lock().unwrap().set_hotkeys(&hotkey_config);
+        let capture = WindowsBackend::with_hooks(hooks.clone());
+        
+        let mut app = Self {
+            state: AppState::Idle,
+            recorder,
+            player,
+            hooks,
+            capture,
+            input_probe,
+            self_test: None,
+            current_file: None,
+            saved_revision: None,
+            file_revision: None,
+            confirm_exit: false,
+            split_at_secs: None,
+            exit_confirmed: false,
+            preflight_warnings: Vec::new(),
+            playback_fault: None,
+            limit_notice: None,
+            show_library: settings.show_library,
+            show_histogram: false,
+            stats_cache: None,
+            show_event_table: settings.show_event_table,
+            editing_event: None,
+            histogram_bucket_ms: 100,
+            library_dir: settings.library_dir.clone(),
+            library_entries: Vec::new(),
+            selected_event: None,
+            play_from_index: 0,
+            retime_factor: 1.0,
+            retime_offset: 0.0,
+            append_gap_ms: 0,
+            scroll_merge_ms: 150,
+            insert_delay_ms: 1000,
+            cursor_pick_deadline: None,
+            log_messages: VecDeque::new(),
+            live_events,
+            max_log_lines: settings.max_log_lines,
+            last_move_log: None,
+            last_autosave: None,
+            pending_recovery: recovery::find_recovery(&recovery::recovery_path()),
+            events_recorded: 0,
+            events_played: 0,
+            current_loop: 0,
+            events_skipped: 0,
+            recording_time: 0.0,
+            show_mouse_moves: settings.show_mouse_moves,
+            show_keys: settings.show_keys,
+            show_clicks: settings.show_clicks,
+            show_scrolls: settings.show_scrolls,
+            min_move_interval_ms: settings.min_move_interval_ms,
+            min_move_distance_px: settings.min_move_distance_px,
+            playback_speed: settings.playback_speed,
+            abort_on_malformed: settings.abort_on_malformed,
+            pause_on_error: settings.pause_on_error,
+            click_hold_ms: settings.click_hold_ms,
+            activation_settle_ms: settings.activation_settle_ms,
+            double_clicks: settings.double_clicks,
+            fixed_interval: settings.fixed_interval,
+            fixed_interval_ms: settings.fixed_interval_ms,
+            repeat_count: settings.repeat_count,
+            infinite_loop: settings.infinite_loop,
+            loop_delay_ms: settings.loop_delay_ms,
+            playback_countdown_secs: settings.playback_countdown_secs,
+            playback_countdown: None,
+            block_input_during_playback: settings.block_input_during_playback,
+            repair_on_load: settings.repair_on_load,
+            rebase_on_load: settings.rebase_on_load,
+            failsafe_enabled: settings.failsafe_enabled,
+            absolute_mouse_input: settings.absolute_mouse_input,
+            smooth_motion: settings.smooth_motion,
+            smooth_motion_hz: settings.smooth_motion_hz,
+            humanize: settings.humanize,
+            humanize_timing_pct: settings.humanize_timing_pct,
+            humanize_scatter_px: settings.humanize_scatter_px,
+            humanize_seed: settings.humanize_seed,
+            show_recording_overlay: settings.show_recording_overlay,
+            close_to_tray: settings.close_to_tray,
+            hotkeys: hotkey_config,
+            rebinding_hotkey: None,
+            play_hotkey: None,
+            play_hotkey_pressed: Arc::new(AtomicBool::new(false)),
+            tray: None,
+            egui_ctx: cc.egui_ctx.clone(),
+            record_toggle_key: settings.record_toggle_key,
+            recognize_shortcuts: settings.recognize_shortcuts,
+            capture_clicks_only: settings.capture_clicks_only,
+            detect_hovers: settings.detect_hovers,
+            hover_threshold_ms: settings.hover_threshold_ms,
+            post_to_window: false,
+            target_window_title: String::new(),
+            record_target: None,
+            record_target_by_process: false,
+            record_target_choices: Vec::new(),
+            autosave_enabled: settings.autosave_enabled,
+            autosave_secs: settings.autosave_secs,
+            max_events: settings.max_events,
+            max_duration_secs: settings.max_duration_secs,
+            idle_pause_enabled: settings.idle_pause_enabled,
+            idle_pause_secs: settings.idle_pause_secs,
+            compact_mouse_paths: settings.compact_mouse_paths,
+            // Files deleted or moved since the last run aren't worth offering
+            recent_files: settings.recent_files.iter()
+                .filter(|path| std::path::Path::new(path).is_file())
+                .cloned()
+                .collect(),
+            saved_settings: settings.clone(),
+        };
+        
+        // The hook only learns the toggle key through the setter
+        app.set_record_toggle_key(settings.record_toggle_key);
+        app.refresh_library();
+        app.register_play_hotkey();
+        app.create_tray();
+        app
+    }
+    
+    // Drops any earlier registration first, so this also applies a new binding
+    fn register_play_hotkey(&mut self) {
+        self.play_hotkey = None;
+        let pressed = self.play_hotkey_pressed.clone();
+        let ctx = self.egui_ctx.clone();
+        match GlobalHotkey::register(self.hotkeys.play, move || {
+            pressed.store(true, Ordering::SeqCst);
+            ctx.request_repaint();
+        }) {
+            Ok(hotkey) => self.play_hotkey = Some(hotkey),
+            Err(e) => self.add_log(format!("⚠️ Global play hotkey unavailable: {}", e)),
+        }
+    }
+    
+    fn create_tray(&mut self) {
+        let ctx = self.egui_ctx.clone();
+        match TrayIcon::create(tray_status(&self.state), APP_TITLE, move || ctx.request_repaint()) {
+            Ok(tray) => self.tray = Some(tray),
+            Err(e) => self.add_log(format!("⚠️ Tray icon unavailable: {}", e)),
+        }
+    }
+    
+    // Tray menu picks and the global play hotkey, which arrive without focus
+    fn poll_background_triggers(&mut self) {
+        if self.play_hotkey_pressed.swap(false, Ordering::SeqCst) {
+            self.start_playback(0);
+        }
+        
+        let commands = self.tray.as_ref().map(|tray| tray.take_commands()).unwrap_or_default();
+        for command in commands {
+            match command {
+                TrayCommand::Play => self.start_playback(0),
+                TrayCommand::Stop => self.stop_current_action(),
+                TrayCommand::Quit => {
+                    let ctx = self.egui_ctx.clone();
+                    self.request_exit(&ctx);
+                }
+            }
+        }
+        
+        if let Some(tray) = &self.tray {
+            tray.set_status(tray_status(&self.state));
+        }
+    }
+    
+    fn add_log(&mut self, message: String) {
+        self.push_log(message, None);
+    }
+    
+    fn current_settings(&self) -> Settings {
+        Settings {
+            show_mouse_moves: self.show_mouse_moves,
+            show_keys: self.show_keys,
+            show_clicks: self.show_clicks,
+            show_scrolls: self.show_scrolls,
+            max_log_lines: self.max_log_lines,
+            show_library: self.show_library,
+            show_event_table: self.show_event_table,
+            library_dir: self.library_dir.clone(),
+            min_move_interval_ms: self.min_move_interval_ms,
+            min_move_distance_px: self.min_move_distance_px,
+            playback_speed: self.playback_speed,
+            abort_on_malformed: self.abort_on_malformed,
+            pause_on_error: self.pause_on_error,
+            click_hold_ms: self.click_hold_ms,
+            activation_settle_ms: self.activation_settle_ms,
+            double_clicks: self.double_clicks,
+            fixed_interval: self.fixed_interval,
+            fixed_interval_ms: self.fixed_interval_ms,
+            repeat_count: self.repeat_count,
+            infinite_loop: self.infinite_loop,
+            loop_delay_ms: self.loop_delay_ms,
+            playback_countdown_secs: self.playback_countdown_secs,
+            block_input_during_playback: self.block_input_during_playback,
+            repair_on_load: self.repair_on_load,
+            rebase_on_load: self.rebase_on_load,
+            failsafe_enabled: self.failsafe_enabled,
+            absolute_mouse_input: self.absolute_mouse_input,
+            smooth_motion: self.smooth_motion,
+            smooth_motion_hz: self.smooth_motion_hz,
+            humanize: self.humanize,
+            humanize_timing_pct: self.humanize_timing_pct,
+            humanize_scatter_px: self.humanize_scatter_px,
+            humanize_seed: self.humanize_seed,
+            show_recording_overlay: self.show_recording_overlay,
+            close_to_tray: self.close_to_tray,
+            record_toggle_key: self.record_toggle_key,
+            recognize_shortcuts: self.recognize_shortcuts,
+            capture_clicks_only: self.capture_clicks_only,
+            detect_hovers: self.detect_hovers,
+            hover_threshold_ms: self.hover_threshold_ms,
+            autosave_enabled: self.autosave_enabled,
+            autosave_secs: self.autosave_secs,
+            max_events: self.max_events,
+            max_duration_secs: self.max_duration_secs,
+            idle_pause_enabled: self.idle_pause_enabled,
+            idle_pause_secs: self.idle_pause_secs,
+            compact_mouse_paths: self.compact_mouse_paths,
+            recent_files: self.recent_files.clone(),
+        }
+    }
+    
+    // Written whenever something changed, but not mid-drag so a slider doesn't rewrite the file every frame
+    fn persist_settings(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.pointer.any_down()) {
+            return;
+        }
+        let settings = self.current_settings();
+        if settings == self.saved_settings {
+            return;
+        }
+        
+        match settings.save(&settings::settings_path()) {
+            Ok(()) => self.saved_settings = settings,
+            Err(e) => {
+                log::warn!("Failed to save settings: {}", e);
+                // Don't retry every frame; the next change tries again
+                self.saved_settings = settings;
+            }
+        }
+    }
+    
+    fn push_log(&mut self, message: String, category: Option<LogCategory>) {
+        let timestamp = chrono::Local::now().format("%H:%M:%S%.3f");
+        self.log_messages.push_back(LogEntry {
+            text: format!("[{}] {}", timestamp, message),
+            category,
+        });
+        
+        // Keep only recent messages
+        while self.log_messages.len() > self.max_log_lines {
+            self.log_messages.pop_front();
+        }
+    }
+    
+    // Hidden categories are still logged, so toggling a filter back on reveals them
+    fn log_event(&mut self, event: &MacroEvent) {
+        if event.is_mouse_move() {
+            let now = Instant::now();
+            if !should_log_move(self.last_move_log, now) {
+                return;
+            }
+            self.last_move_log = Some(now);
+        }
+        
+        self.push_log(event.get_description(), LogCategory::of(event));
+    }
+    
+    fn log_shows(&self, category: Option<LogCategory>) -> bool {
+        match category {
+            None => true,
+            Some(LogCategory::Keys) => self.show_keys,
+            Some(LogCategory::Clicks) => self.show_clicks,
+            Some(LogCategory::Scroll) => self.show_scrolls,
+            Some(LogCategory::Moves) => self.show_mouse_moves,
+        }
+    }
+    
+    fn start_recording(&mut self) {
+        if matches!(self.state, AppState::Idle) {
+            self.state = AppState::Recording;
+            self.events_recorded = 0;
+            self.recording_time = 0.0;
+            self.last_autosave = Some(Instant::now());
+            
+            if let Ok(mut recorder) = self.recorder.lock() {
+                recorder.clear();
+                recorder.set_recognize_shortcuts(self.recognize_shortcuts);
+                recorder.set_capture_mode(if self.capture_clicks_only {
+                    CaptureMode::ClicksOnly
+                } else {
+                    CaptureMode::Full
+                });
+                recorder.set_hover_threshold(if self.detect_hovers {
+                    Some(self.hover_threshold_ms as f64 / 1000.0)
+                } else {
+                    None
+                });
+                recorder.set_limits(self.max_events, self.max_duration_secs);
+                recorder.set_idle_pause(if self.idle_pause_enabled {
+                    Some(Duration::from_secs(self.idle_pause_secs.max(1)))
+                } else {
+                    None
+                });
+                recorder.start();
+            }
+            
+            if let Ok(mut hooks) = self.hooks.lock() {
+                hooks.set_min_move_interval_ms(self.min_move_interval_ms);
+                hooks.set_min_move_distance_px(self.min_move_distance_px);
+                hooks.set_record_target(self.record_target_spec());
+            }
+            let install_result = self.capture.install_hooks(self.capture_sink());
+            
+            match install_result {
+                Ok(_) => {
+                    self.add_log("🔴 Recording started - Global hooks active".to_string());
+                    self.add_log(format!("📝 Hotkeys: {} (pause), {} (stop)",
+                        self.hotkeys.pause.label(), self.hotkeys.stop.label()));
+                    match self.record_target_spec() {
+                        Some(RecordTarget::Process(process)) => self.add_log(format!("🎯 Recording only windows of {}", process)),
+                        Some(RecordTarget::Window(_)) => self.add_log(format!("🎯 Recording only '{}'",
+                            self.record_target.as_ref().map(|window| window.title.as_str()).unwrap_or_default())),
+                        None => {}
+                    }
+                }
+                Err(e) => {
+                    self.add_log(format!("❌ Failed to install hooks: {}", e));
+                    self.state = AppState::Idle;
+                }
+            }
+        }
+    }
+    
+    // What the hooks hand captured input to: the self-test's probe keys are held back, the rest
+    // goes to the recorder and, once kept, to the live log
+    fn capture_sink(&self) -> EventSink {
+        let live_events = self.live_events.clone();
+        let record = MacroRecorder::capture_sink(self.recorder.clone(), move |added| {
+            if let Ok(mut live) = live_events.lock() {
+                live.push_back(added.clone());
+                while live.len() > LIVE_LOG_BACKLOG {
+                    live.pop_front();
+                }
+            }
+        });
+        let probe = self.input_probe.clone();
+        Box::new(move |event| {
+            if !probe.observe(&event) {
+                record(event);
+            }
+        })
+    }
+    
+    fn record_target_spec(&self) -> Option<RecordTarget> {
+        let window = self.record_target.as_ref()?;
+        match (&window.process, self.record_target_by_process) {
+            (Some(process), true) => Some(RecordTarget::Process(process.clone())),
+            _ => Some(RecordTarget::Window(window.hwnd)),
+        }
+    }
+    
+    // The recorder paused or resumed itself over idle time; follow it
+    fn apply_idle_change(&mut self, change: IdleChange) {
+        match (change, &self.state) {
+            (IdleChange::Paused, AppState::Recording) => {
+                self.state = AppState::RecordingPaused;
+                self.add_log(format!("💤 No input for {}s - recording paused until the next input", self.idle_pause_secs));
+            }
+            (IdleChange::Resumed, AppState::RecordingPaused) => {
+                self.state = AppState::Recording;
+                self.add_log("▶️ Input detected - recording resumed".to_string());
+            }
+            _ => {}
+        }
+    }
+    
+    fn pause_resume_recording(&mut self) {
+        match self.state {
+            AppState::Recording => {
+                self.state = AppState::RecordingPaused;
+                if let Ok(mut recorder) = self.recorder.lock() {
+                    recorder.pause();
+                }
+                self.add_log("⏸️ Recording paused".to_string());
+            }
+            AppState::RecordingPaused => {
+                self.state = AppState::Recording;
+                if let Ok(mut recorder) = self.recorder.lock() {
+                    recorder.resume();
+                }
+                self.add_log("▶️ Recording resumed".to_string());
+            }
+            AppState::Playing => {
+                self.state = AppState::PlayingPaused;
+                if let Ok(mut player) = self.player.lock() {
+                    player.pause();
+                }
+                self.add_log("⏸️ Playback paused".to_string());
+            }
+            AppState::PlayingPaused => {
+                self.state = AppState::Playing;
+                if let Ok(mut player) = self.player.lock() {
+                    player.resume();
+                }
+                self.add_log("▶️ Playback resumed".to_string());
+            }
+            _ => {}
+        }
+    }
+    
+    fn stop_current_action(&mut self) {
+        match self.state {
+            AppState::Recording | AppState::RecordingPaused => {
+                self.capture.uninstall_hooks();
+                
+                if let Ok(mut recorder) = self.recorder.lock() {
+                    recorder.stop();
+                    self.events_recorded = recorder.get_events().len();
+                }
+                
+                self.state = AppState::Idle;
+                recovery::discard_recovery(&recovery::recovery_path());
+                self.add_log(format!("🛑 Recording stopped - {} events captured", self.events_recorded));
+                
+                self.add_log("💾 Use 'Save As' to save your recording".to_string());
+            }
+            AppState::Playing | AppState::PlayingPaused => {
+                if let Ok(mut player) = self.player.lock() {
+                    player.stop();
+                }
+                self.release_input_block();
+                self.state = AppState::Idle;
+                self.add_log("🛑 Playback stopped".to_string());
+            }
+            AppState::Countdown => {
+                self.playback_countdown = None;
+                self.state = AppState::Idle;
+                self.add_log("🛑 Playback cancelled before it started".to_string());
+            }
+            _ => {}
+        }
+    }
+    
+    // The playback thread ended by itself; stop_current_action covers the user stopping it
+    fn finish_playback(&mut self, end: PlaybackEnd) {
+        if matches!(self.state, AppState::Playing | AppState::PlayingPaused) {
+            self.release_input_block();
+            self.state = AppState::Idle;
+        }
+        match end {
+            PlaybackEnd::Completed => self.add_log("✅ Playback complete".to_string()),
+            PlaybackEnd::Aborted => self.add_log("⚠️ Playback aborted early (see the log for why)".to_string()),
+        }
+    }
+    
+    fn save_recording(&mut self, path: &str) {
+        let save_result = if let Ok(mut recorder) = self.recorder.lock() {
+            recorder.set_compact_paths(self.compact_mouse_paths);
+            let saved = if events::is_json_path(path) {
+                recorder.save_to_json(path)
+            } else {
+                recorder.save_to_file(path)
+            };
+            saved.map(|_| recorder.revision())
+        } else {
+            Err("Failed to lock recorder".into())
+        };
+        
+        match save_result {
+            Ok(revision) => {
+                self.current_file = Some(path.to_string());
+                self.saved_revision = Some(revision);
+                self.file_revision = Some(revision);
+                settings::push_recent(&mut self.recent_files, path);
+                recovery::discard_recovery(&recovery::recovery_path());
+                self.add_log(format!("💾 Saved to: {}", path));
+            }
+            Err(e) => {
+                self.add_log(format!("❌ Save failed: {}", e));
+            }
+        }
+    }
+    
+    fn load_recording(&mut self, path: &str) {
+        let repair = self.repair_on_load;
+        let rebase = self.rebase_on_load;
+        let load_result = if let Ok(mut player) = self.player.lock() {
+            if events::is_json_path(path) {
+                player.load_from_json(path)
+            } else {
+                player.load_from_file(path)
+            }.map(|count| {
+                let report = player.validate();
+                let repaired = if repair { player.repair() } else { 0 };
+                let rebased = if rebase { player.rebase() } else { None };
+                (count, player.format_version(), report, repaired, rebased)
+            })
+        } else {
+            Err("Failed to lock player".into())
+        };
+        
+        match load_result {
+            Ok((event_count, version, report, repaired, rebased)) => {
+                self.current_file = Some(path.to_string());
+                self.file_revision = self.recorder.lock().ok().map(|recorder| recorder.revision());
+                settings::push_recent(&mut self.recent_files, path);
+                self.add_log(format!("📁 Loaded {} events from: {}", event_count, path));
+                if version < events::MCR_BASE_VERSION {
+                    self.add_log(format!("ℹ️ Old .mcr v{} file; saving it writes v{} or newer", version, events::MCR_BASE_VERSION));
+                }
+                for input in &report.unreleased {
+                    self.add_log(format!("⚠️ #{} {} is never released", input.index, input.description));
+                }
+                for input in &report.unmatched_releases {
+                    self.add_log(format!("⚠️ #{} {} has no matching press", input.index, input.description));
+                }
+                if repaired > 0 {
+                    self.add_log(format!("🔧 Added {} release(s) at the end so nothing stays held", repaired));
+                }
+                if let Some(offset) = rebased {
+                    self.add_log(format!("⏩ Skipped {:.2}s of waiting before the first event", offset));
+                }
+            }
+            Err(e) => {
+                self.add_log(format!("❌ Load failed: {}", e));
+            }
+        }
+    }
+    
+    fn open_recent(&mut self, path: &str) {
+        if std::path::Path::new(path).is_file() {
+            self.load_recording(path);
+        } else {
+            self.recent_files.retain(|recent| recent != path);
+            self.add_log(format!("❌ {} no longer exists; removed it from recent files", path));
+        }
+    }
+    
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (hovering, dropped) = ctx.input(|i| (!i.raw.hovered_files.is_empty(), i.raw.dropped_files.clone()));
+        
+        if hovering {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("file_drop")));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(180));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "📂 Drop a .mcr or .json file to load it",
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+        
+        // Only one macro can be loaded, so the last good file wins
+        for file in dropped {
+            let path = match file.path {
+                Some(path) => path.display().to_string(),
+                None => continue,
+            };
+            if !is_macro_path(&path) {
+                self.add_log(format!("⚠️ Ignored dropped file {}: not a .mcr or .json macro", path));
+                continue;
+            }
+            if !matches!(self.state, AppState::Idle) {
+                self.add_log(format!("⚠️ Ignored dropped file {}: stop first", path));
+                continue;
+            }
+            self.add_log(format!("📂 Dropped {}", path));
+            self.load_recording(&path);
+        }
+    }
+    
+    fn export_macro(&mut self, format: ExportFormat, path: &str) {
+        let export_result = if let Ok(player) = self.player.lock() {
+            player.export(format, path)
+        } else {
+            Err("Failed to lock player".into())
+        };
+        
+        match export_result {
+            Ok(_) => self.add_log(format!("📤 Exported {} to: {}", format.name(), path)),
+            Err(e) => self.add_log(format!("❌ Export failed: {}", e)),
+        }
+    }
+    
+    // Periodically snapshot the in-progress recording so a crash doesn't lose it
+    fn autosave_recording(&mut self, now: Instant) {
+        let recording = matches!(self.state, AppState::Recording | AppState::RecordingPaused);
+        let interval = Duration::from_secs(self.autosave_secs);
+        if !recording || !self.autosave_enabled || !recovery::autosave_due(self.last_autosave, now, interval) {
+            return;
+        }
+        self.last_autosave = Some(now);
+        
+        let path = recovery::recovery_path();
+        let save_result = if let Ok(mut recorder) = self.recorder.lock() {
+            recorder.set_compact_paths(self.compact_mouse_paths);
+            recorder.save_to_file(&path.display().to_string())
+        } else {
+            Err("Failed to lock recorder".into())
+        };
+        
+        if let Err(e) = save_result {
+            self.add_log(format!("❌ Auto-save failed: {}", e));
+        }
+    }
+    
+    fn recover_recording(&mut self) {
+        self.pending_recovery = None;
+        let path = recovery::recovery_path();
+        
+        let load_result = if let Ok(mut recorder) = self.recorder.lock() {
+            recorder.load_from_file(&path.display().to_string())
+                .map(|count| (count, recorder.get_events().clone()))
+        } else {
+            Err("Failed to lock recorder".into())
+        };
+        
+        match load_result {
+            Ok((count, events)) => {
+                if let Ok(mut player) = self.player.lock() {
+                    player.load_events(events);
+                }
+                self.add_log(format!("♻️ Recovered {} events from an unfinished recording", count));
+                self.add_log("💾 Use 'Save As' to keep it".to_string());
+            }
+            Err(e) => self.add_log(format!("❌ Recovery failed: {}", e)),
+        }
+    }
+    
+    fn stop_and_test(&mut self) {
+        if matches!(self.state, AppState::Recording | AppState::RecordingPaused) {
+            self.stop_current_action();
+            
+            let events = match self.recorder.lock() {
+                Ok(recorder) => recorder.get_events().clone(),
+                Err(_) => return,
+            };
+            
+            let loaded = if let Ok(mut player) = self.player.lock() {
+                player.load_events(events)
+            } else {
+                0
+            };
+            
+            self.add_log(format!("🧪 {} recorded events loaded for testing - press Play to replay", loaded));
+        }
+    }
+    
+    fn start_input_self_test(&mut self) {
+        if !matches!(self.state, AppState::Idle) || self.self_test.is_some() {
+            return;
+        }
+        
+        if let Err(e) = self.capture.install_hooks(self.capture_sink()) {
+            self.add_log(format!("❌ Input test failed to install hooks: {}", e));
+            return;
+        }
+        
+        match SelfTest::start(&self.input_probe, calibration::send_probe_key) {
+            Ok(test) => {
+                self.self_test = Some(test);
+                self.add_log("🩺 Testing input injection...".to_string());
+            }
+            Err(result) => self.finish_input_self_test(result),
+        }
+    }
+    
+    fn finish_input_self_test(&mut self, result: calibration::SelfTestResult) {
+        self.self_test = None;
+        self.capture.uninstall_hooks();
+        
+        let icon = if result == calibration::SelfTestResult::Working { "✅" } else { "❌" };
+        self.add_log(format!("{} {}", icon, result.describe()));
+    }
+    
+    fn start_cursor_pick(&mut self) {
+        if matches!(self.state, AppState::Idle) && self.cursor_pick_deadline.is_none() {
+            self.cursor_pick_deadline = Some(Instant::now() + CURSOR_PICK_DELAY);
+            self.add_log(format!("📍 Move the cursor to the target - capturing in {}s", CURSOR_PICK_DELAY.as_secs()));
+        }
+    }
+    
+    fn poll_cursor_pick(&mut self, now: Instant) {
+        match self.cursor_pick_deadline {
+            Some(deadline) if now >= deadline => self.cursor_pick_deadline = None,
+            _ => return,
+        }
+        
+        let (x, y) = match hooks::cursor_position() {
+            Some(position) => position,
+            None => {
+                self.add_log("❌ Could not read the cursor position".to_string());
+                return;
+            }
+        };
+        
+        let inserted_at = if let Ok(mut recorder) = self.recorder.lock() {
+            let index = self.selected_event.map(|i| i + 1).unwrap_or(recorder.get_events().len());
+            recorder.insert_mouse_move(index, x, y)
+        } else {
+            return;
+        };
+        
+        self.selected_event = Some(inserted_at);
+        self.add_log(format!("📍 Inserted Mouse Move: ({}, {}) at #{}", x, y, inserted_at));
+    }
+    
+    fn engage_input_block(&mut self) {
+        if let Ok(mut hooks) = self.hooks.lock() {
+            hooks.set_block_user_input(true);
+        }
+        let install_result = self.capture.install_hooks(self.capture_sink());
+        
+        match install_result {
+            Ok(_) => self.add_log(format!("🔒 User input blocked during playback - {} to stop", self.hotkeys.stop.label())),
+            Err(e) => {
+                self.release_input_block();
+                self.add_log(format!("❌ Failed to block input: {}", e));
+            }
+        }
+    }
+    
+    fn release_input_block(&mut self) {
+        if let Ok(mut hooks) = self.hooks.lock() {
+            hooks.set_block_user_input(false);
+        }
+        self.capture.uninstall_hooks();
+    }
+    
+    fn refresh_library(&mut self) {
+        let dir = match &self.library_dir {
+            Some(dir) => dir.clone(),
+            None => return,
+        };
+        
+        match library::scan_folder(&dir) {
+            Ok(entries) => {
+                self.add_log(format!("📚 Library: {} macros in {}", entries.len(), dir.display()));
+                self.library_entries = entries;
+            }
+            Err(e) => self.add_log(format!("❌ Library scan failed: {}", e)),
+        }
+    }
+    
+    fn undo_edit(&mut self) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let undone = self.recorder.lock().map(|mut recorder| recorder.undo()).unwrap_or(false);
+        if undone {
+            self.add_log("↩️ Undo".to_string());
+        }
+    }
+    
+    fn redo_edit(&mut self) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let redone = self.recorder.lock().map(|mut recorder| recorder.redo()).unwrap_or(false);
+        if redone {
+            self.add_log("↪️ Redo".to_string());
+        }
+    }
+    
+    // Only runs while the Statistics section is expanded, and reuses the last result until the recording changes
+    fn stats_ui(&mut self, ui: &mut egui::Ui) {
+        let stats = match self.recorder.lock() {
+            Ok(recorder) => match &self.stats_cache {
+                Some((revision, stats)) if *revision == recorder.revision() => stats.clone(),
+                _ => {
+                    let stats = recorder.stats();
+                    self.stats_cache = Some((recorder.revision(), stats.clone()));
+                    stats
+                }
+            },
+            Err(_) => return,
+        };
+        
+        if stats.total_events == 0 {
+            ui.colored_label(egui::Color32::GRAY, "Record something to see statistics");
+            return;
+        }
+        
+        ui.label(format!("Total events: {}", stats.total_events));
+        let per_type: Vec<String> = stats.per_type.iter()
+            .map(|(event_type, count)| format!("{} {}", event_type, count))
+            .collect();
+        ui.label(format!("By type: {}", per_type.join(" | ")));
+        ui.label(format!("Mouse travel: {:.0} px", stats.mouse_travel_px));
+        if !stats.clicks_per_button.is_empty() {
+            let clicks: Vec<String> = stats.clicks_per_button.iter()
+                .map(|(button, count)| {
+                    let name = match button {
+                        1 => "Left",
+                        2 => "Right",
+                        3 => "Middle",
+                        4 => "X1",
+                        5 => "X2",
+                        _ => "Other",
+                    };
+                    format!("{} {}", name, count)
+                })
+                .collect();
+            ui.label(format!("Clicks: {}", clicks.join(" | ")));
+        }
+        if let Some(average) = stats.average_interval_ms {
+            ui.label(format!("Average gap between events: {:.1} ms", average));
+        }
+    }
+    
+    fn delete_selected_event(&mut self) {
+        let index = match self.selected_event {
+            Some(index) if matches!(self.state, AppState::Idle) => index,
+            _ => return,
+        };
+        let removed = self.recorder.lock().ok().and_then(|mut recorder| {
+            let removed = recorder.remove_event(index)?;
+            Some((removed, recorder.get_events().len()))
+        });
+        
+        if let Some((event, remaining)) = removed {
+            self.selected_event = if remaining == 0 { None } else { Some(index.min(remaining - 1)) };
+            self.add_log(format!("🗑️ Deleted #{}: {}", index, event.get_description()));
+        }
+    }
+    
+    // Swap the selected row with its neighbour; the selection follows the row
+    fn move_selected_event(&mut self, up: bool) {
+        let index = match self.selected_event {
+            Some(index) if matches!(self.state, AppState::Idle) => index,
+            _ => return,
+        };
+        let target = if up {
+            match index.checked_sub(1) {
+                Some(target) => target,
+                None => return,
+            }
+        } else {
+            index + 1
+        };
+        
+        let moved = self.recorder.lock().map(|mut recorder| recorder.swap_events(index, target)).unwrap_or(false);
+        if moved {
+            self.selected_event = Some(target);
+        }
+    }
+    
+    fn event_table_ui(&mut self, ui: &mut egui::Ui) {
+        let idle = matches!(self.state, AppState::Idle);
+        let has_selection = self.selected_event.is_some();
+        
+        ui.horizontal(|ui| {
+            ui.heading("📋 Events");
+            if ui.add_enabled(idle && has_selection, egui::Button::new("⬆ Up")).clicked() {
+                self.move_selected_event(true);
+            }
+            if ui.add_enabled(idle && has_selection, egui::Button::new("⬇ Down")).clicked() {
+                self.move_selected_event(false);
+            }
+            if ui.add_enabled(idle && has_selection, egui::Button::new("🗑️ Delete")).clicked() {
+                self.delete_selected_event();
+            }
+        });
+        ui.separator();
+        
+        let recorder = self.recorder.clone();
+        let recorder = match recorder.lock() {
+            Ok(recorder) => recorder,
+            Err(_) => return,
+        };
+        let events = recorder.get_events();
+        if self.selected_event.is_some_and(|index| index >= events.len()) {
+            self.selected_event = None;
+        }
+        
+        if events.is_empty() {
+            ui.colored_label(egui::Color32::GRAY, "No recorded events yet");
+            return;
+        }
+        
+        ui.monospace(format!("{:>5}  {:>9}  {:<36}  {}", "#", "Time", "Event", "Data"));
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical()
+            .id_source("event_table")
+            .max_height(ui.available_height() * 0.5)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, events.len(), |ui, rows| {
+                for index in rows {
+                    let event = &events[index];
+                    let row = format!(
+                        "{:>5}  {:>8.3}s  {:<36}  {}",
+                        index,
+                        event.timestamp,
+                        event.get_description(),
+                        event.data
+                    );
+                    let selected = self.selected_event == Some(index);
+                    let response = ui.selectable_label(selected, egui::RichText::new(row).monospace());
+                    if response.double_clicked() {
+                        self.selected_event = Some(index);
+                        if idle {
+                            self.editing_event = Some(EventEdit::from_event(index, event));
+                        }
+                    } else if response.clicked() {
+                        self.selected_event = if selected { None } else { Some(index) };
+                    }
+                }
+            });
+    }
+    
+    fn apply_event_edit(&mut self, edit: &EventEdit) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        if let Some((x, y)) = edit.position {
+            if !ScreenBounds::virtual_screen().contains(x, y) {
+                self.add_log(format!("⚠️ ({}, {}) is outside the screen; event #{} may miss its target", x, y, edit.index));
+            }
+        }
+        
+        let updated = self.recorder.lock().ok().and_then(|mut recorder| {
+            let event = edit.apply_to(recorder.get_events().get(edit.index)?);
+            recorder.update_event(edit.index, event)
+        });
+        
+        match updated {
+            Some(index) if index != edit.index => {
+                self.selected_event = Some(index);
+                self.add_log(format!("✏️ Edited event #{}, now #{} by time", edit.index, index));
+            }
+            Some(index) => {
+                self.selected_event = Some(index);
+                self.add_log(format!("✏️ Edited event #{}", index));
+            }
+            None => self.add_log(format!("❌ Event #{} no longer exists", edit.index)),
+        }
+    }
+    
+    fn rescale_recording(&mut self, factor: f64) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let changed = self.recorder.lock().map(|mut recorder| recorder.rescale_time(factor)).unwrap_or(false);
+        if changed {
+            self.add_log(format!("⏱️ Rescaled all timestamps by {}x", factor));
+        }
+    }
+    
+    fn append_recording(&mut self, path: &str) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let gap = self.append_gap_ms as f64 / 1000.0;
+        let append_result = match self.recorder.lock() {
+            Ok(mut recorder) => recorder.append_from_file(path, gap),
+            Err(_) => Err("Failed to lock recorder".into()),
+        };
+        
+        match append_result {
+            Ok(count) => self.add_log(format!("➕ Appended {} events from: {}", count, path)),
+            Err(e) => self.add_log(format!("❌ Append failed: {}", e)),
+        }
+    }
+    
+    fn split_recording(&mut self, at: f64, path: &str) {
+        let (first_path, second_path) = recorder::split_paths(path);
+        let split_result = match self.recorder.lock() {
+            Ok(mut recorder) => {
+                recorder.set_compact_paths(self.compact_mouse_paths);
+                recorder.save_split(at, &first_path, &second_path)
+            }
+            Err(_) => Err("Failed to lock recorder".into()),
+        };
+        
+        match split_result {
+            Ok((first, second)) => {
+                self.add_log(format!("✂️ Split at {:.2}s into {} ({} events) and {} ({} events)",
+                    at, first_path, first, second_path, second));
+            }
+            Err(e) => self.add_log(format!("❌ Split failed: {}", e)),
+        }
+    }
+    
+    fn shift_recording(&mut self, offset: f64) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let changed = self.recorder.lock().map(|mut recorder| recorder.shift_time(offset)).unwrap_or(false);
+        if changed {
+            self.add_log(format!("⏱️ Shifted all timestamps by {:+.3}s", offset));
+        }
+    }
+    
+    fn trim_recording(&mut self) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        // Event count and time of the last event
+        let extent = |recorder: &MacroRecorder| {
+            let events = recorder.get_events();
+            (events.len(), events.last().map_or(0.0, |event| event.timestamp))
+        };
+        let (before, changed, after) = match self.recorder.lock() {
+            Ok(mut recorder) => {
+                let before = extent(&recorder);
+                let changed = recorder.trim();
+                (before, changed, extent(&recorder))
+            }
+            Err(_) => return,
+        };
+        if changed {
+            self.add_log(format!("✂️ Trimmed dead time: {:.2}s -> {:.2}s, {} trailing event(s) dropped",
+                before.1, after.1, before.0 - after.0));
+        } else {
+            self.add_log("✂️ No dead time to trim".to_string());
+        }
+    }
+    
+    fn poll_playback_fault(&mut self) {
+        let fault = self.player.lock().ok().and_then(|player| player.get_fault());
+        
+        match (&self.playback_fault, fault) {
+            (None, Some(fault)) => {
+                if matches!(self.state, AppState::Playing) {
+                    self.state = AppState::PlayingPaused;
+                    if let Ok(mut player) = self.player.lock() {
+                        player.pause();
+                    }
+                }
+                self.add_log(format!("❌ Playback paused at event #{} ({}): {}", fault.index, fault.description, fault.error));
+                self.playback_fault = Some(fault);
+            }
+            (Some(_), None) => self.playback_fault = None,
+            _ => {}
+        }
+    }
+    
+    fn resolve_playback_fault(&mut self, action: FaultAction) {
+        if let Ok(mut player) = self.player.lock() {
+            player.resolve_fault(action);
+        }
+        if matches!(self.state, AppState::PlayingPaused) {
+            self.state = AppState::Playing;
+        }
+        self.playback_fault = None;
+        
+        let verb = if action == FaultAction::Retry { "Retrying" } else { "Skipping" };
+        self.add_log(format!("▶️ {} failed event", verb));
+    }
+    
+    fn coalesce_scrolls(&mut self) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let window = self.scroll_merge_ms as f64 / 1000.0;
+        let removed = self.recorder.lock().map(|mut recorder| recorder.coalesce_scrolls(window)).unwrap_or(0);
+        self.add_log(format!("🎡 Merged scroll bursts - {} events removed", removed));
+    }
+    
+    // Goes after the selected event, or at the end when nothing is selected
+    fn insert_delay(&mut self) {
+        if !matches!(self.state, AppState::Idle) {
+            return;
+        }
+        let ms = self.insert_delay_ms;
+        let inserted_at = if let Ok(mut recorder) = self.recorder.lock() {
+            let index = self.selected_event.map(|i| i + 1).unwrap_or(recorder.get_events().len());
+            recorder.insert_delay(index, ms)
+        } else {
+            return;
+        };
+        
+        self.selected_event = Some(inserted_at);
+        self.add_log(format!("⏳ Inserted Wait {}ms at #{}", ms, inserted_at));
+    }
+    
+    fn toggle_recording(&mut self) {
+        match toggle_transition(&self.state) {
+            ToggleAction::StartRecording => self.start_recording(),
+            ToggleAction::StopRecording => self.stop_current_action(),
+            ToggleAction::Ignore => {}
+        }
+    }
+    
+    fn set_record_toggle_key(&mut self, key: Option<u8>) {
+        self.record_toggle_key = key;
+        if let Ok(mut hooks) = self.hooks.lock() {
+            hooks.set_record_toggle_key(key.map(function_key_vk));
+        }
+    }
+    
+    fn run_hotkey_action(&mut self, action: HotkeyAction) {
+        match action {
+            HotkeyAction::Record => self.start_recording(),
+            HotkeyAction::Pause => self.pause_resume_recording(),
+            HotkeyAction::Stop | HotkeyAction::EmergencyStop => self.stop_current_action(),
+            HotkeyAction::Play => self.start_playback(0),
+        }
+    }
+    
+    fn rebind_hotkey(&mut self, action: HotkeyAction, hotkey: Hotkey) {
+        self.hotkeys.set(action, hotkey);
+        if let Ok(mut hooks) = self.hooks.lock() {
+            hooks.set_hotkeys(&self.hotkeys);
+        }
+        self.add_log(format!("⌨️ {} hotkey set to {}", action.label(), hotkey.label()));
+        if action == HotkeyAction::Play {
+            self.register_play_hotkey();
+        }
+        
+        for (first, second) in self.hotkeys.conflicts() {
+            self.add_log(format!("⚠️ {} and {} are both bound to {}",
+                first.label(), second.label(), self.hotkeys.get(first).label()));
+        }
+        
+        if let Err(e) = self.hotkeys.save(&hotkeys::hotkey_config_path()) {
+            self.add_log(format!("❌ Failed to save hotkeys: {}", e));
+        }
+    }
+    
+    fn has_unsaved_recording(&self) -> bool {
+        self.recorder.lock()
+            .map(|recorder| has_unsaved_work(recorder.get_events().len(), recorder.revision(), self.saved_revision))
+            .unwrap_or(false)
+    }
+    
+    // Whether the recording changed since current_file was saved or loaded, so the file no longer
+    // matches what's in memory
+    fn is_dirty(&self) -> bool {
+        self.current_file.is_some() && self.recorder.lock()
+            .map(|recorder| self.file_revision != Some(recorder.revision()))
+            .unwrap_or(false)
+    }
+    
+    // Closes right away, or asks about unsaved work first. Whatever is running is only stopped
+    // once the exit goes ahead, so cancelling the prompt leaves it running.
+    fn request_exit(&mut self, ctx: &egui::Context) {
+        if self.exit_confirmed || !self.has_unsaved_recording() {
+            self.stop_current_action();
+            self.exit_confirmed = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        } else {
+            self.confirm_exit = true;
+        }
+    }
+    
+    fn has_loaded_macro(&self) -> bool {
+        self.player.lock()
+            .map(|player| player.get_total_events() > 0)
+            .unwrap_or(false)
+    }
+    
+    fn start_playback(&mut self, from_index: usize) {
+        if matches!(self.state, AppState::Idle) && self.has_loaded_macro() {
+            self.play_from_index = from_index;
+            let warnings = match self.player.lock() {
+                Ok(player) => player.preflight(),
+                Err(_) => Vec::new(),
+            };
+            
+            if warnings.is_empty() {
+                self.begin_playback();
+            } else {
+                self.add_log(format!("⚠️ Pre-flight check found {} issue(s)", warnings.len()));
+                self.preflight_warnings = warnings;
+            }
+        }
+    }
+    
+    // The window to post events to when replaying in the background; Err (already logged)
+    // when that window can't be found
+    fn resolve_target_window(&mut self) -> Result<Option<isize>, ()> {
+        if !self.post_to_window {
+            return Ok(None);
+        }
+        match hooks::find_window(&self.target_window_title) {
+            Some(hwnd) => Ok(Some(hwnd)),
+            None => {
+                self.add_log(format!("❌ No window titled '{}'", self.target_window_title));
+                Err(())
+            }
+        }
+    }
+    
+    fn apply_playback_settings(&self, player: &mut MacroPlayer, target_window: Option<isize>) {
+        player.set_speed(self.playback_speed);
+        player.set_missing_field_policy(if self.abort_on_malformed {
+            MissingFieldPolicy::Abort
+        } else {
+            MissingFieldPolicy::Skip
+        });
+        player.set_pause_on_error(self.pause_on_error);
+        player.set_click_hold_ms(self.click_hold_ms);
+        player.set_activation_settle(Duration::from_millis(self.activation_settle_ms));
+        player.set_double_clicks(self.double_clicks);
+        player.set_target_window(target_window);
+        player.set_repeat_count(if self.infinite_loop { 0 } else { self.repeat_count });
+        player.set_loop_delay(Duration::from_millis(self.loop_delay_ms));
+        player.set_failsafe(self.failsafe_enabled.then_some(DEFAULT_FAILSAFE_THRESHOLD_PX));
+        player.set_emergency_hotkey(Some(self.hotkeys.emergency));
+        player.set_smooth_motion(self.smooth_motion.then_some(self.smooth_motion_hz));
+        player.set_humanize(self.humanize.then_some(HumanizeSettings {
+            timing_variance_pct: self.humanize_timing_pct,
+            click_scatter_px: self.humanize_scatter_px,
+            seed: self.humanize_seed,
+        }));
+        player.set_cursor_method(if self.absolute_mouse_input {
+            CursorMethod::SendInputAbsolute
+        } else {
+            CursorMethod::SetCursorPos
+        });
+        player.set_timing_mode(if self.fixed_interval {
+            TimingMode::FixedInterval(Duration::from_millis(self.fixed_interval_ms))
+        } else {
+            TimingMode::Recorded
+        });
+    }
+    
+    // Runs the next event on its own, without any timing, for walking through a macro
+    fn step_playback(&mut self) {
+        if !matches!(self.state, AppState::Idle) || !self.has_loaded_macro() {
+            return;
+        }
+        let target_window = match self.resolve_target_window() {
+            Ok(target_window) => target_window,
+            Err(()) => return,
+        };
+        
+        let stepped = match self.player.clone().lock() {
+            Ok(mut player) => {
+                self.apply_playback_settings(&mut player, target_window);
+                let stepped = player.step();
+                if stepped.is_none() {
+                    player.seek(0);
+                }
+                stepped
+            }
+            Err(_) => return,
+        };
+        
+        match stepped {
+            Some((index, event, Ok(()))) => self.add_log(format!("⏯️ Step #{}: {}", index, event.get_description())),
+            Some((index, event, Err(e))) => self.add_log(format!("❌ Step #{} ({}) failed: {}", index, event.get_description(), e)),
+            None => self.add_log("⏮️ End of macro reached; the next step starts over".to_string()),
+        }
+    }
+    
+    fn begin_playback(&mut self) {
+        self.preflight_warnings.clear();
+        
+        if matches!(self.state, AppState::Idle) && self.has_loaded_macro() {
+            if self.playback_countdown_secs == 0 {
+                self.launch_playback();
+                return;
+            }
+            // Time to switch to the target window, so the first clicks don't land on this one
+            self.state = AppState::Countdown;
+            self.playback_countdown = Some(Instant::now() + Duration::from_secs(self.playback_countdown_secs));
+            self.add_log(format!("⏳ Playback starts in {}s - switch to the target window", self.playback_countdown_secs));
+        }
+    }
+    
+    fn poll_playback_countdown(&mut self, now: Instant) {
+        match self.playback_countdown {
+            Some(deadline) if now >= deadline => self.playback_countdown = None,
+            _ => return,
+        }
+        self.state = AppState::Idle;
+        self.launch_playback();
+    }
+    
+    fn launch_playback(&mut self) {
+        if matches!(self.state, AppState::Idle) && self.has_loaded_macro() {
+            let target_window = match self.resolve_target_window() {
+                Ok(target_window) => target_window,
+                Err(()) => return,
+            };
+            
+            self.state = AppState::Playing;
+            self.events_played = self.play_from_index;
+            
+            let speed = self.playback_speed;
+            if let Ok(mut player) = self.player.clone().lock() {
+                self.apply_playback_settings(&mut player, target_window);
+                player.start_from(self.play_from_index);
+            }
+            if self.play_from_index > 0 {
+                self.add_log(format!("▶️ Playback started from event #{} ({}x speed)", self.play_from_index, speed));
+            } else {
+                self.add_log(format!("▶️ Playback started ({}x speed)", speed));
+            }
+            
+            if self.block_input_during_playback {
+                self.engage_input_block();
+            }
+        }
+    }
+}
+
+impl eframe::App for MacroApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let pressed_hotkey = ctx.input(pressed_hotkey);
+        if let Some(action) = self.rebinding_hotkey {
+            // Plain Esc cancels; Esc with modifiers is a valid binding
+            if pressed_hotkey == Some(Hotkey { ctrl: false, shift: false, alt: false, vk: hotkeys::VK_ESCAPE }) {
+                self.rebinding_hotkey = None;
+            } else if let Some(hotkey) = pressed_hotkey {
+                self.rebinding_hotkey = None;
+                self.rebind_hotkey(action, hotkey);
+            }
+        } else if let Some(action) = pressed_hotkey
+            .and_then(|hotkey| self.hotkeys.action_for(hotkey.vk, hotkey.ctrl, hotkey.shift, hotkey.alt))
+        {
+            self.run_hotkey_action(action);
+        }
+        
+        ctx.input(|i| {
+            if i.modifiers.ctrl {
+                if i.key_pressed(egui::Key::Z) {
+                    self.undo_edit();
+                } else if i.key_pressed(egui::Key::Y) {
+                    self.redo_edit();
+                }
+            }
+        });
+        
+        if ctx.input(|i| i.viewport().close_requested()) && !self.exit_confirmed {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            // Only hidden: hooks and any recording or playback keep running
+            if self.close_to_tray && self.tray.is_some() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+                self.add_log("📥 Hidden to the tray - use its Quit entry to exit".to_string());
+            } else {
+                self.request_exit(ctx);
+            }
+        }
+        
+        let toggle_key = self.record_toggle_key.map(|n| FUNCTION_KEYS[n as usize - 1]);
+        let toggle_pressed = toggle_key.map(|key| ctx.input(|i| i.key_pressed(key))).unwrap_or(false)
+            || self.hooks.lock().map(|hooks| hooks.take_record_toggle()).unwrap_or(false);
+        if toggle_pressed {
+            self.toggle_recording();
+        }
+        
+        let kill_switch = self.hooks.lock()
+            .map(|hooks| hooks.take_kill_switch())
+            .unwrap_or(false);
+        if kill_switch {
+            self.add_log("🛑 Kill switch pressed".to_string());
+            self.stop_current_action();
+        }
+        
+        self.poll_background_triggers();
+        
+        if matches!(self.state, AppState::Countdown) {
+            self.poll_playback_countdown(Instant::now());
+        }
+        
+        if self.cursor_pick_deadline.is_some() {
+            self.poll_cursor_pick(Instant::now());
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        
+        if let Some(test) = &self.self_test {
+            if let Some(result) = test.poll(&self.input_probe, Instant::now()) {
+                self.finish_input_self_test(result);
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(50));
+            }
+        }
+        
+        let (limit_reached, idle_changes) = if let Ok(mut recorder) = self.recorder.lock() {
+            recorder.check_limits();
+            recorder.check_idle();
+            self.events_recorded = recorder.get_events().len();
+            self.recording_time = recorder.get_duration();
+            (recorder.take_limit_reached(), recorder.take_idle_changes())
+        } else {
+            (None, Vec::new())
+        };
+        for change in idle_changes {
+            self.apply_idle_change(change);
+        }
+        if let Some(limit) = limit_reached {
+            self.add_log(format!("⏹️ Recording stopped automatically: {}", limit.describe()));
+            self.stop_current_action();
+            self.limit_notice = Some(limit);
+        }
+        
+        let live: Vec<MacroEvent> = self.live_events.lock()
+            .map(|mut live| live.drain(..).collect())
+            .unwrap_or_default();
+        for event in &live {
+            self.log_event(event);
+        }
+        
+        self.autosave_recording(Instant::now());
+        
+        if let Ok(player) = self.player.lock() {
+            self.events_played = player.get_current_position();
+            self.current_loop = player.get_current_loop();
+            self.events_skipped = player.get_skipped_events();
+        }
+        
+        let emergency_stop = self.player.lock()
+            .map(|player| player.take_emergency_stop())
+            .unwrap_or(false);
+        if emergency_stop {
+            self.add_log(format!("🛑 Emergency stop ({}) pressed", self.hotkeys.emergency.label()));
+            self.stop_current_action();
+        }
+        
+        let failsafe_tripped = self.player.lock()
+            .map(|player| player.take_failsafe_tripped())
+            .unwrap_or(false);
+        if failsafe_tripped {
+            self.add_log("🛑 Failsafe: mouse moved during playback - stopped".to_string());
+            self.stop_current_action();
+        }
+        
+        let playback_end = self.player.lock().ok().and_then(|mut player| player.take_playback_end());
+        if let Some(end) = playback_end {
+            self.finish_playback(end);
+        }
+        
+        if matches!(self.state, AppState::Playing | AppState::PlayingPaused) {
+            self.poll_playback_fault();
+        }
+        
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("📁 Open .mcr").clicked() {
+                        let mut dialog = rfd::FileDialog::new()
+                            .add_filter("Macro files", &["mcr", "gz"])
+                            .add_filter("JSON macros", &["json"]);
+                        // Start next to whatever was open last
+                        if let Some(dir) = self.current_file.as_deref().and_then(|file| std::path::Path::new(file).parent()) {
+                            dialog = dialog.set_directory(dir);
+                        }
+                        // None means the dialog was cancelled
+                        if let Some(path) = dialog.pick_file() {
+                            self.load_recording(&path.display().to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    
+                    ui.add_enabled_ui(!self.recent_files.is_empty(), |ui| {
+                        ui.menu_button("🕘 Open Recent", |ui| {
+                            let mut to_load = None;
+                            for path in &self.recent_files {
+                                if ui.button(path).clicked() {
+                                    to_load = Some(path.clone());
+                                }
+                            }
+                            if let Some(path) = to_load {
+                                self.open_recent(&path);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    
+                    if ui.button("📁 Open JSON").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("JSON macros", &["json"])
+                            .pick_file() {
+                            self.load_recording(&path.display().to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    
+                    ui.horizontal(|ui| {
+                        let idle = matches!(self.state, AppState::Idle);
+                        if ui.add_enabled(idle, egui::Button::new("➕ Append .mcr")).clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Macro files", &["mcr", "gz"])
+                                .pick_file() {
+                                self.append_recording(&path.display().to_string());
+                            }
+                            ui.close_menu();
+                        }
+                        ui.label("after");
+                        ui.add(egui::DragValue::new(&mut self.append_gap_ms)
+                            .speed(50.0)
+                            .clamp_range(0..=600_000)
+                            .suffix("ms"));
+                    });
+                    
+                    if ui.button("💾 Save As").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Macro files", &["mcr"])
+                            .add_filter("Compressed macro files", &["gz"])
+                            .add_filter("JSON macros", &["json"])
+                            .save_file() {
+                            self.save_recording(&path.display().to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    
+                    let idle = matches!(self.state, AppState::Idle);
+                    if ui.add_enabled(idle, egui::Button::new("✂️ Split into two files...")).clicked() {
+                        self.split_at_secs = Some(0.0);
+                        ui.close_menu();
+                    }
+                    
+                    if ui.button("💾 Save as JSON").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("JSON macros", &["json"])
+                            .save_file() {
+                            self.save_recording(&path.display().to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    
+                    ui.menu_button("📤 Export", |ui| {
+                        for format in [ExportFormat::Ahk, ExportFormat::Json, ExportFormat::PortableJson, ExportFormat::Csv] {
+                            if ui.button(format.name()).clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter(format.name(), &[format.extension()])
+                                    .save_file() {
+                                    self.export_macro(format, &path.display().to_string());
+                                }
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    
+                    ui.separator();
+                    
+                    if ui.button("🚪 Exit").clicked() {
+                        self.request_exit(ctx);
+                        ui.close_menu();
+                    }
+                });
+                
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.show_library, "📚 Library panel");
+                    ui.checkbox(&mut self.show_histogram, "📊 Timing histogram");
+                    ui.checkbox(&mut self.show_event_table, "📋 Event table");
+                });
+                
+                ui.menu_button("Edit", |ui| {
+                    let idle = matches!(self.state, AppState::Idle);
+                    let (can_undo, can_redo) = self.recorder.lock()
+                        .map(|recorder| (recorder.can_undo(), recorder.can_redo()))
+                        .unwrap_or((false, false));
+                    
+                    if ui.add_enabled(idle && can_undo, egui::Button::new("↩️ Undo (Ctrl+Z)")).clicked() {
+                        self.undo_edit();
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(idle && can_redo, egui::Button::new("↪️ Redo (Ctrl+Y)")).clicked() {
+                        self.redo_edit();
+                        ui.close_menu();
+                    }
+                    
+                    ui.separator();
+                    
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.retime_factor)
+                            .speed(0.05)
+                            .clamp_range(0.01..=100.0)
+                            .suffix("x"));
+                        if ui.add_enabled(idle, egui::Button::new("⏱️ Rescale time")).clicked() {
+                            self.rescale_recording(self.retime_factor);
+                            ui.close_menu();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.retime_offset)
+                            .speed(0.1)
+                            .suffix("s"));
+                        if ui.add_enabled(idle, egui::Button::new("⏱️ Shift time")).clicked() {
+                            self.shift_recording(self.retime_offset);
+                            ui.close_menu();
+                        }
+                    });
+                    if ui.add_enabled(idle, egui::Button::new("✂️ Trim dead time"))
+                        .on_hover_text("Start at the first event and end at the last click, key or scroll")
+                        .clicked()
+                    {
+                        self.trim_recording();
+                        ui.close_menu();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.scroll_merge_ms)
+                            .speed(5.0)
+                            .clamp_range(1..=2000)
+                            .suffix("ms"));
+                        if ui.add_enabled(idle, egui::Button::new("🎡 Merge scrolls")).clicked() {
+                            self.coalesce_scrolls();
+                            ui.close_menu();
+                        }
+                    });
+                    
+                    ui.separator();
+                    
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.insert_delay_ms)
+                            .speed(50.0)
+                            .clamp_range(1..=600_000)
+                            .suffix("ms"));
+                        if ui.add_enabled(idle, egui::Button::new("⏳ Insert Wait")).clicked() {
+                            self.insert_delay();
+                            ui.close_menu();
+                        }
+                    });
+                    
+                    let can_pick = matches!(self.state, AppState::Idle) && self.cursor_pick_deadline.is_none();
+                    if ui.add_enabled(can_pick, egui::Button::new("📍 Insert Move (pick from screen)")).clicked() {
+                        self.start_cursor_pick();
+                        ui.close_menu();
+                    }
+                });
+                
+                ui.menu_button("Settings", |ui| {
+                    ui.checkbox(&mut self.show_mouse_moves, "Show mouse moves in log");
+                    ui.horizontal(|ui| {
+                        ui.label("Mouse move sampling:");
+                        ui.add(egui::Slider::new(&mut self.min_move_interval_ms, 0..=100)
+                            .text("ms"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Mouse move dead-zone:");
+                        ui.add(egui::Slider::new(&mut self.min_move_distance_px, 0..=50)
+                            .text("px"));
+                    });
+                    ui.checkbox(&mut self.show_recording_overlay, "Show recording overlay");
+                    ui.add_enabled(self.tray.is_some(), egui::Checkbox::new(&mut self.close_to_tray, "Close to tray (Quit from the tray icon exits)"));
+                    
+                    ui.horizontal(|ui| {
+                        let selected = self.record_target.as_ref()
+                            .map(|window| window.label())
+                            .unwrap_or_else(|| "Any window".to_string());
+                        let combo = egui::ComboBox::from_label("Record only")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.record_target, None, "Any window");
+                                for window in &self.record_target_choices {
+                                    ui.selectable_value(&mut self.record_target, Some(window.clone()), window.label());
+                                }
+                            });
+                        // Re-list the windows each time the dropdown is opened
+                        if combo.response.clicked() {
+                            self.record_target_choices = target::visible_windows();
+                        }
+                    });
+                    ui.add_enabled(self.record_target.is_some(), egui::Checkbox::new(
+                        &mut self.record_target_by_process, "Include every window of the same process"));
+                    
+                    let mut toggle_key = self.record_toggle_key;
+                    egui::ComboBox::from_label("Record toggle key")
+                        .selected_text(toggle_key.map(|n| format!("F{}", n)).unwrap_or_else(|| "None".to_string()))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut toggle_key, None, "None");
+                            for n in 1..=12 {
+                                ui.selectable_value(&mut toggle_key, Some(n), format!("F{}", n));
+                            }
+                        });
+                    if toggle_key != self.record_toggle_key {
+                        self.set_record_toggle_key(toggle_key);
+                    }
+                    
+                    ui.label("Hotkeys:");
+                    for action in HotkeyAction::ALL {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}:", action.label()));
+                            let text = if self.rebinding_hotkey == Some(action) {
+                                "Press a key combo... (Esc cancels)".to_string()
+                            } else {
+                                self.hotkeys.get(action).label()
+                            };
+                            if ui.button(text).clicked() {
+                                self.rebinding_hotkey = Some(action);
+                            }
+                        });
+                    }
+                    for (first, second) in self.hotkeys.conflicts() {
+                        ui.colored_label(egui::Color32::YELLOW, format!("⚠️ {} and {} share {}",
+                            first.label(), second.label(), self.hotkeys.get(first).label()));
+                    }
+                    if ui.button("Reset hotkeys to defaults").clicked() {
+                        for action in HotkeyAction::ALL {
+                            self.rebind_hotkey(action, HotkeyConfig::default().get(action));
+                        }
+                    }
+                    
+                    ui.checkbox(&mut self.recognize_shortcuts, "Label shortcuts (Ctrl+C, Alt+F4, ...)");
+                    ui.checkbox(&mut self.capture_clicks_only, "Capture clicks only (one Click per press/release)");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.detect_hovers, "Record hovers longer than");
+                        ui.add_enabled(self.detect_hovers, egui::Slider::new(&mut self.hover_threshold_ms, 200..=5000)
+                            .text("ms"));
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Stop recording after (0 = no limit):");
+                        ui.add(egui::DragValue::new(&mut self.max_events).speed(1000).suffix(" events"));
+                        ui.add(egui::DragValue::new(&mut self.max_duration_secs).speed(10).suffix(" s"));
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.idle_pause_enabled, "Pause recording after")
+                            .on_hover_text("Resumes on the next key or mouse input; the idle stretch is cut down to this length");
+                        ui.add_enabled(self.idle_pause_enabled, egui::Slider::new(&mut self.idle_pause_secs, 1..=300)
+                            .text("s without input"));
+                    });
+                    
+                    ui.checkbox(&mut self.compact_mouse_paths, "Save mouse paths compactly (MPATH)")
+                        .on_hover_text("Off writes one line per mouse move, readable by older versions");
+                    
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.autosave_enabled, "Auto-save recordings every");
+                        ui.add_enabled(self.autosave_enabled, egui::Slider::new(&mut self.autosave_secs, 5..=300)
+                            .text("s"));
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Playback speed:");
+                        let speed_changed = ui.add(egui::Slider::new(&mut self.playback_speed, 0.1..=10.0)
+                            .text("x")).changed();
+                        // A running playback picks the new speed up before its next wait
+                        if speed_changed && matches!(self.state, AppState::Playing | AppState::PlayingPaused) {
+                            if let Ok(mut player) = self.player.lock() {
+                                player.set_speed(self.playback_speed);
+                            }
+                        }
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Repeat:");
+                        ui.add_enabled(!self.infinite_loop, egui::DragValue::new(&mut self.repeat_count)
+                            .clamp_range(1..=10000)
+                            .suffix(" times"));
+                        ui.checkbox(&mut self.infinite_loop, "Infinite loop");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Delay between loops:");
+                        ui.add(egui::Slider::new(&mut self.loop_delay_ms, 0..=10000)
+                            .text("ms"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Countdown before playback (0 = none):");
+                        ui.add(egui::Slider::new(&mut self.playback_countdown_secs, 0..=10)
+                            .text("s"));
+                    });
+                    
+                    ui.checkbox(&mut self.abort_on_malformed, "Abort playback on malformed events");
+                    ui.checkbox(&mut self.pause_on_error, "Pause playback when an event fails");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.post_to_window, "Replay into window:");
+                        ui.add_enabled(self.post_to_window, egui::TextEdit::singleline(&mut self.target_window_title)
+                            .hint_text("exact window title"));
+                    });
+                    ui.checkbox(&mut self.repair_on_load, "Release keys a loaded macro leaves held");
+                    ui.checkbox(&mut self.rebase_on_load, "Start a loaded macro at its first event")
+                        .on_hover_text("Off keeps any wait before the first event, as saved");
+                    ui.checkbox(&mut self.block_input_during_playback,
+                        format!("Block my input during playback ({} still stops)", self.hotkeys.stop.label()));
+                    ui.checkbox(&mut self.failsafe_enabled, "Panic stop on mouse move");
+                    ui.checkbox(&mut self.absolute_mouse_input, "Move the mouse with SendInput (absolute) instead of SetCursorPos");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.smooth_motion, "Smooth mouse motion at");
+                        ui.add_enabled(self.smooth_motion, egui::Slider::new(&mut self.smooth_motion_hz, 10..=240)
+                            .text("steps/s"));
+                    });
+                    ui.checkbox(&mut self.humanize, "Humanize playback (random jitter, best-effort)")
+                        .on_hover_text("Varies timing and click positions a little on each run. It makes replay less mechanical but doesn't guarantee it won't be detected as automated.");
+                    ui.add_enabled_ui(self.humanize, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Timing variance:");
+                            ui.add(egui::Slider::new(&mut self.humanize_timing_pct, 0..=50).text("%"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Click scatter:");
+                            ui.add(egui::Slider::new(&mut self.humanize_scatter_px, 0..=20).text("px"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Seed:");
+                            ui.add(egui::DragValue::new(&mut self.humanize_seed));
+                        });
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Click hold:");
+                        ui.add(egui::Slider::new(&mut self.click_hold_ms, 0..=500)
+                            .text("ms"));
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.fixed_interval, "Fixed interval (ignore timestamps):");
+                        ui.add_enabled(self.fixed_interval, egui::Slider::new(&mut self.fixed_interval_ms, 1..=2000)
+                            .text("ms"));
+                    });
+                    
+                    ui.checkbox(&mut self.double_clicks, "Keep double-clicks double at any speed");
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Settle after window switch:");
+                        ui.add(egui::Slider::new(&mut self.activation_settle_ms, 0..=2000)
+                            .text("ms"));
+                    });
+                    
+                    let can_test_input = matches!(self.state, AppState::Idle) && self.self_test.is_none();
+                    if ui.add_enabled(can_test_input, egui::Button::new("🩺 Test Input")).clicked() {
+                        self.start_input_self_test();
+                        ui.close_menu();
+                    }
+                    
+                    ui.separator();
+                    ui.label("Tracks:");
+                    if let Ok(mut player) = self.player.lock() {
+                        let tracks = player.get_tracks();
+                        if tracks.is_empty() {
+                            ui.colored_label(egui::Color32::GRAY, "No macro loaded");
+                        }
+                        for track in tracks {
+                            let mut enabled = !player.is_track_muted(&track);
+                            if ui.checkbox(&mut enabled, format!("Play {} track", track)).changed() {
+                                player.set_track_muted(&track, !enabled);
+                            }
+                        }
+                    }
+                });
+                
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let (color, text) = match self.state {
+                        AppState::Idle => (egui::Color32::GRAY, "⚪ Idle"),
+                        AppState::Recording => (egui::Color32::RED, "🔴 Recording"),
+                        AppState::RecordingPaused => (egui::Color32::YELLOW, "⏸️ Rec Paused"),
+                        AppState::Countdown => (egui::Color32::YELLOW, "⏳ Starting"),
+                        AppState::Playing => (egui::Color32::GREEN, "▶️ Playing"),
+                        AppState::PlayingPaused => (egui::Color32::YELLOW, "⏸️ Play Paused"),
+                    };
+                    
+                    ui.colored_label(color, text);
+                });
+            });
+        });
+        
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let can_record = matches!(self.state, AppState::Idle);
+                let can_pause = matches!(self.state, AppState::Recording | AppState::RecordingPaused | AppState::Playing | AppState::PlayingPaused);
+                let can_stop = !matches!(self.state, AppState::Idle);
+                let can_test = matches!(self.state, AppState::Recording | AppState::RecordingPaused);
+                let can_play = matches!(self.state, AppState::Idle) && self.has_loaded_macro();
+                
+                if ui.add_enabled(can_record, egui::Button::new(format!("🔴 Record ({})", self.hotkeys.record.label()))).clicked() {
+                    self.start_recording();
+                }
+                
+                if ui.add_enabled(can_play, egui::Button::new("▶️ Play")).clicked() {
+                    self.start_playback(0);
+                }
+                
+                if let Some(selected) = self.selected_event {
+                    if ui.add_enabled(can_play, egui::Button::new(format!("▶️ Play from #{}", selected))).clicked() {
+                        self.start_playback(selected);
+                    }
+                }
+                
+                if ui.add_enabled(can_play, egui::Button::new("Step ▶|")).clicked() {
+                    self.step_playback();
+                }
+                
+                let pause_text = match self.state {
+                    AppState::RecordingPaused | AppState::PlayingPaused => format!("▶️ Resume ({})", self.hotkeys.pause.label()),
+                    _ => format!("⏸️ Pause ({})", self.hotkeys.pause.label()),
+                };
+                
+                if ui.add_enabled(can_pause, egui::Button::new(pause_text)).clicked() {
+                    self.pause_resume_recording();
+                }
+                
+                if ui.add_enabled(can_stop, egui::Button::new(format!("⏹️ Stop ({})", self.hotkeys.stop.label()))).clicked() {
+                    self.stop_current_action();
+                }
+                
+                if ui.add_enabled(can_test, egui::Button::new("🧪 Stop & Test")).clicked() {
+                    self.stop_and_test();
+                }
+                
+                ui.separator();
+                
+                if matches!(self.state, AppState::Playing | AppState::PlayingPaused) {
+                    let total = self.player.lock().map(|player| player.get_total_events()).unwrap_or(0);
+                    ui.label(format!("▶️ Played: {}/{}", self.events_played, total));
+                    if self.infinite_loop {
+                        ui.label(format!("🔁 Loop {}/∞", self.current_loop));
+                    } else if self.repeat_count > 1 {
+                        ui.label(format!("🔁 Loop {}/{}", self.current_loop, self.repeat_count));
+                    }
+                } else if let (AppState::Countdown, Some(deadline)) = (&self.state, self.playback_countdown) {
+                    ui.label(format!("⏳ Starting in {}…", countdown_secs_left(deadline, Instant::now())));
+                } else {
+                    ui.label(format!("📊 Events: {}", self.events_recorded));
+                }
+                if self.recording_time > 0.0 {
+                    ui.label(format!("⏱️ Time: {:.1}s", self.recording_time));
+                }
+                
+                if self.events_skipped > 0 {
+                    ui.colored_label(egui::Color32::YELLOW, format!("⚠️ Skipped: {}", self.events_skipped));
+                }
+                
+                if let Some(file) = &self.current_file {
+                    let name = std::path::Path::new(file).file_name().unwrap_or_default().to_string_lossy();
+                    if self.is_dirty() {
+                        ui.label(format!("📄 File: {} ●", name))
+                            .on_hover_text("Modified since it was last saved");
+                    } else {
+                        ui.label(format!("📄 File: {}", name));
+                    }
+                }
+            });
+        });
+        
+        egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("🔥 Global Hotkeys:");
+                ui.label(format!("{} (Record) | {} (Pause/Resume) | {} (Stop)",
+                    self.hotkeys.record.label(), self.hotkeys.pause.label(), self.hotkeys.stop.label()));
+                
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.hyperlink_to("🦀 Rust Edition", "https://github.com");
+                });
+            });
+        });
+        
+        let recording = matches!(self.state, AppState::Recording | AppState::RecordingPaused);
+        if recording && self.show_recording_overlay {
+            let paused = matches!(self.state, AppState::RecordingPaused);
+            let text = format_overlay_text(Duration::from_secs_f32(self.recording_time.max(0.0)), paused);
+            
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("recording_overlay"),
+                egui::ViewportBuilder::default()
+                    .with_title("Recording")
+                    .with_inner_size([140.0, 32.0])
+                    .with_decorations(false)
+                    .with_resizable(false)
+                    .with_always_on_top(),
+                |ctx, _class| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        ui.centered_and_justified(|ui| {
+                            ui.colored_label(egui::Color32::RED, egui::RichText::new(text).strong());
+                        });
+                    });
+                },
+            );
+        }
+        
+        if let Some(summary) = self.pending_recovery.clone() {
+            let mut recover = false;
+            let mut discard = false;
+            
+            egui::Window::new("♻️ Recover recording?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label("An unfinished recording was found from a previous session:");
+                    ui.label(format!("{} events · {:.1}s", summary.event_count, summary.duration));
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        recover = ui.button("♻️ Recover").clicked();
+                        discard = ui.button("🗑️ Discard").clicked();
+                    });
+                });
+            
+            if recover {
+                self.recover_recording();
+            } else if discard {
+                self.pending_recovery = None;
+                recovery::discard_recovery(&recovery::recovery_path());
+                self.add_log("🗑️ Discarded unfinished recording".to_string());
+            }
+        }
+        
+        if self.confirm_exit {
+            let mut save = false;
+            let mut discard = false;
+            let mut cancel = false;
+            
+            egui::Window::new("💾 Unsaved recording")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("The current recording ({} events) hasn't been saved.", self.events_recorded));
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        save = ui.button("💾 Save & Exit").clicked();
+                        discard = ui.button("Exit without saving").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            
+            if save {
+                // Finish the recording first so the file gets all of it
+                self.stop_current_action();
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Macro files", &["mcr"])
+                    .add_filter("Compressed macro files", &["gz"])
+                    .save_file() {
+                    self.save_recording(&path.display().to_string());
+                }
+                if !self.has_unsaved_recording() {
+                    self.confirm_exit = false;
+                    self.request_exit(ctx);
+                }
+            } else if discard {
+                self.confirm_exit = false;
+                self.exit_confirmed = true;
+                self.request_exit(ctx);
+            } else if cancel {
+                self.confirm_exit = false;
+            }
+        }
+        
+        if let Some(mut edit) = self.editing_event.clone() {
+            let mut save = false;
+            let mut cancel = false;
+            
+            egui::Window::new(format!("✏️ Edit event #{}", edit.index))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    egui::Grid::new("event_edit").num_columns(2).show(ui, |ui| {
+                        ui.label("Time:");
+                        ui.add(egui::DragValue::new(&mut edit.timestamp)
+                            .speed(0.01)
+                            .clamp_range(0.0..=f64::MAX)
+                            .suffix("s"));
+                        ui.end_row();
+                        
+                        if let Some((x, y)) = &mut edit.position {
+                            ui.label("Position:");
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(x).prefix("x: "));
+                                ui.add(egui::DragValue::new(y).prefix("y: "));
+                            });
+                            ui.end_row();
+                        }
+                        
+                        if let Some(key_name) = &mut edit.key_name {
+                            ui.label("Key:");
+                            ui.text_edit_singleline(key_name);
+                            ui.end_row();
+                        }
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        save = ui.button("💾 Apply").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            
+            self.editing_event = Some(edit.clone());
+            if save {
+                self.apply_event_edit(&edit);
+                self.editing_event = None;
+            } else if cancel {
+                self.editing_event = None;
+            }
+        }
+        
+        if let Some(mut at) = self.split_at_secs {
+            let mut split = false;
+            let mut cancel = false;
+            
+            egui::Window::new("✂️ Split recording")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Cut at:");
+                        ui.add(egui::DragValue::new(&mut at)
+                            .speed(0.1)
+                            .clamp_range(0.0..=f64::MAX)
+                            .suffix("s"));
+                    });
+                    ui.label("Both halves are saved next to the file you pick, as _part1 and _part2.");
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        split = ui.button("✂️ Split...").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            
+            self.split_at_secs = Some(at);
+            if split {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Macro files", &["mcr"])
+                    .add_filter("Compressed macro files", &["gz"])
+                    .save_file() {
+                    self.split_recording(at, &path.display().to_string());
+                    self.split_at_secs = None;
+                }
+            } else if cancel {
+                self.split_at_secs = None;
+            }
+        }
+        
+        if let Some(limit) = self.limit_notice {
+            let mut dismissed = false;
+            egui::Window::new("⏹️ Recording limit reached")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Recording stopped automatically: it {}.", limit.describe()));
+                    ui.label("Everything captured up to that point is kept. The limits can be changed in Settings.");
+                    dismissed = ui.button("OK").clicked();
+                });
+            if dismissed {
+                self.limit_notice = None;
+            }
+        }
+        
+        if let Some(fault) = self.playback_fault.clone() {
+            let mut action = None;
+            let mut stop = false;
+            
+            egui::Window::new("❌ Playback error")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Event #{} failed: {}", fault.index, fault.description));
+                    ui.colored_label(egui::Color32::RED, fault.error.to_string());
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("🔁 Retry").clicked() {
+                            action = Some(FaultAction::Retry);
+                        }
+                        if ui.button("⏭️ Skip").clicked() {
+                            action = Some(FaultAction::Skip);
+                        }
+                        stop = ui.button("⏹️ Stop").clicked();
+                    });
+                });
+            
+            if let Some(action) = action {
+                self.resolve_playback_fault(action);
+            } else if stop {
+                self.playback_fault = None;
+                self.stop_current_action();
+            }
+        }
+        
+        if !self.preflight_warnings.is_empty() {
+            let mut play_anyway = false;
+            let mut cancel = false;
+            
+            egui::Window::new("⚠️ Pre-flight check")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label("The loaded macro may not replay as expected:");
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for warning in &self.preflight_warnings {
+                            ui.colored_label(egui::Color32::YELLOW, warning.to_string());
+                        }
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        play_anyway = ui.button("▶️ Play anyway").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                });
+            
+            if play_anyway {
+                self.begin_playback();
+            } else if cancel {
+                self.preflight_warnings.clear();
+                self.add_log("🛑 Playback cancelled after pre-flight check".to_string());
+            }
+        }
+        
+        if self.show_histogram {
+            let histogram = match self.recorder.lock() {
+                Ok(recorder) => stats::timing_histogram(recorder.get_events(), self.histogram_bucket_ms),
+                Err(_) => Vec::new(),
+            };
+            
+            egui::Window::new("📊 Timing histogram")
+                .open(&mut self.show_histogram)
+                .default_width(360.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Bucket:");
+                        ui.add(egui::Slider::new(&mut self.histogram_bucket_ms, 10..=5000)
+                            .logarithmic(true)
+                            .text("ms"));
+                    });
+                    ui.separator();
+                    
+                    if histogram.is_empty() {
+                        ui.colored_label(egui::Color32::GRAY, "Record at least two events to see gaps");
+                        return;
+                    }
+                    
+                    let tallest = histogram.iter().map(|(_, count)| *count).max().unwrap_or(1).max(1);
+                    let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width(), 120.0), egui::Sense::hover());
+                    let rect = response.rect;
+                    let bar_width = rect.width() / histogram.len() as f32;
+                    
+                    for (i, (_, count)) in histogram.iter().enumerate() {
+                        let height = rect.height() * *count as f32 / tallest as f32;
+                        let left = rect.left() + i as f32 * bar_width;
+                        let bar = egui::Rect::from_min_max(
+                            egui::pos2(left + 1.0, rect.bottom() - height),
+                            egui::pos2(left + bar_width - 1.0, rect.bottom()),
+                        );
+                        painter.rect_filled(bar, 0.0, egui::Color32::LIGHT_BLUE);
+                    }
+                    
+                    if let Some(pointer) = response.hover_pos() {
+                        let i = (((pointer.x - rect.left()) / bar_width) as usize).min(histogram.len() - 1);
+                        let (range, count) = &histogram[i];
+                        let label = if range.end == u64::MAX {
+                            format!("≥ {}ms: {} gaps", range.start, count)
+                        } else {
+                            format!("{}–{}ms: {} gaps", range.start, range.end, count)
+                        };
+                        response.on_hover_text(label);
+                    }
+                });
+        }
+        
+        if self.show_library {
+            egui::SidePanel::left("library_panel")
+                .resizable(true)
+                .default_width(220.0)
+                .show(ctx, |ui| {
+                    ui.heading("📚 Library");
+                    ui.horizontal(|ui| {
+                        if ui.button("📂 Folder").clicked() {
+                            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                                self.library_dir = Some(dir);
+                                self.refresh_library();
+                            }
+                        }
+                        if ui.add_enabled(self.library_dir.is_some(), egui::Button::new("🔄 Rescan")).clicked() {
+                            self.refresh_library();
+                        }
+                    });
+                    ui.separator();
+                    
+                    let mut to_load = None;
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for entry in &self.library_entries {
+                            let label = format!(
+                                "{}\n{} events · {:.1}s",
+                                entry.name, entry.summary.event_count, entry.summary.duration
+                            );
+                            if ui.selectable_label(false, label).double_clicked() {
+                                to_load = Some(entry.path.display().to_string());
+                            }
+                        }
+                        
+                        if self.library_entries.is_empty() {
+                            ui.colored_label(egui::Color32::GRAY, "Pick a folder of .mcr files");
+                        }
+                    });
+                    
+                    if let Some(path) = to_load {
+                        self.load_recording(&path);
+                    }
+                });
+        }
+        
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.show_event_table {
+                self.event_table_ui(ui);
+                ui.separator();
+            }
+            
+            egui::CollapsingHeader::new("📈 Statistics").show(ui, |ui| self.stats_ui(ui));
+            ui.separator();
+            
+            ui.horizontal(|ui| {
+                ui.heading("📝 Activity Log");
+                ui.separator();
+                ui.label("Show:");
+                ui.checkbox(&mut self.show_keys, "Keys");
+                ui.checkbox(&mut self.show_clicks, "Clicks");
+                ui.checkbox(&mut self.show_scrolls, "Scroll");
+                ui.checkbox(&mut self.show_mouse_moves, "Moves");
+            });
+            ui.separator();
+            
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for entry in self.log_messages.iter().filter(|entry| self.log_shows(entry.category)) {
+                        let message = &entry.text;
+                        let color = if message.contains("❌") {
+                            egui::Color32::RED
+                        } else if message.contains("🔴") || message.contains("▶️") {
+                            egui::Color32::GREEN
+                        } else if message.contains("⏸️") {
+                            egui::Color32::YELLOW
+                        } else if message.contains("💾") || message.contains("📁") {
+                            egui::Color32::BLUE
+                        } else {
+                            ui.visuals().text_color()
+                        };
+                        
+                        ui.colored_label(color, message);
+                    }
+                    
+                    if self.log_messages.is_empty() {
+                        ui.colored_label(egui::Color32::GRAY, "🚀 Welcome to Macro Recorder Rust Edition!");
+                        ui.colored_label(egui::Color32::GRAY, format!("📌 Click 'Record' or press {} to start recording", self.hotkeys.record.label()));
+                        ui.colored_label(egui::Color32::GRAY, "⚡ This version uses native Windows hooks for maximum performance");
+                    }
+                });
+        });
+        
+        self.handle_dropped_files(ctx);
+        self.persist_settings(ctx);
+        
+        if let Some(interval) = repaint_interval(&self.state) {
+            ctx.request_repaint_after(interval);
+        }
+    }
+    
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        log::info!("Application shutting down - performing cleanup");
+        
+        self.stop_current_action();
+        
+        // stop() joins the playback thread, so nothing is left injecting input past this point
+        if let Ok(mut player) = self.player.lock() {
+            player.stop();
+        }
+        
+        self.capture.uninstall_hooks();
+        
+        // Dropping them unregisters the hotkey and removes the icon
+        self.play_hotkey = None;
+        self.tray = None;
+        
+        let settings = self.current_settings();
+        if settings != self.saved_settings {
+            if let Err(e) = settings.save(&settings::settings_path()) {
+                log::warn!("Failed to save settings: {}", e);
+            }
+        }
+        
+        log::info!("Application cleanup completed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn repaints_only_while_something_is_running() {
+        assert_eq!(repaint_interval(&AppState::Idle), None);
+        assert_eq!(repaint_interval(&AppState::Recording), Some(Duration::from_millis(50)));
+        assert_eq!(repaint_interval(&AppState::Playing), Some(Duration::from_millis(50)));
+        // Paused states only need the clock to tick
+        assert!(repaint_interval(&AppState::RecordingPaused) > repaint_interval(&AppState::Recording));
+        assert!(repaint_interval(&AppState::PlayingPaused) > repaint_interval(&AppState::Playing));
+        assert!(repaint_interval(&AppState::Countdown).is_some());
+    }
+    
+    #[test]
+    fn a_burst_of_moves_logs_once_per_interval() {
+        let start = Instant::now();
+        let mut last_logged = None;
+        let mut logged = 0;
+        // A second of moves every 5ms
+        for step in 0..200 {
+            let now = start + Duration::from_millis(step * 5);
+            if should_log_move(last_logged, now) {
+                last_logged = Some(now);
+                logged += 1;
+            }
+        }
+        assert_eq!(logged, 10);
+    }
+    
+    #[test]
+    fn overlay_shows_minutes_and_seconds() {
+        assert_eq!(format_overlay_text(Duration::ZERO, false), "● REC 00:00");
+        assert_eq!(format_overlay_text(Duration::from_millis(65_900), false), "● REC 01:05");
+        assert_eq!(format_overlay_text(Duration::from_secs(3600 + 7), true), "⏸ REC 60:07");
+    }
+    
+    #[test]
+    fn only_unsaved_edits_count_as_unsaved_work() {
+        assert!(!has_unsaved_work(0, 3, None));
+        assert!(has_unsaved_work(5, 3, None));
+        assert!(!has_unsaved_work(5, 3, Some(3)));
+        // Edited since the last save
+        assert!(has_unsaved_work(5, 4, Some(3)));
+    }
+    
+    #[test]
+    fn the_record_toggle_never_interrupts_playback() {
+        assert_eq!(toggle_transition(&AppState::Idle), ToggleAction::StartRecording);
+        assert_eq!(toggle_transition(&AppState::Recording), ToggleAction::StopRecording);
+        assert_eq!(toggle_transition(&AppState::RecordingPaused), ToggleAction::StopRecording);
+        assert_eq!(toggle_transition(&AppState::Countdown), ToggleAction::Ignore);
+        assert_eq!(toggle_transition(&AppState::Playing), ToggleAction::Ignore);
+        assert_eq!(toggle_transition(&AppState::PlayingPaused), ToggleAction::Ignore);
+    }
+}
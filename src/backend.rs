@@ -0,0 +1,343 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::events::MacroEvent;
+use crate::monitors::Monitor;
+use crate::player::{CursorMethod, SendError};
+use crate::preflight::ScreenBounds;
+
+// Where a backend hands each event it captures, on whatever thread the OS delivers it
+pub type EventSink = Box<dyn Fn(MacroEvent) + Send + Sync>;
+
+// What recording and playback need from the OS. Everything crossing it is in .mcr terms
+// (virtual key codes, screen pixels, buttons 1-5), so files stay portable between backends.
+pub trait InputBackend: Send + Sync {
+    /// Starts capturing the user's keyboard and mouse into `sink` until `uninstall_hooks`;
+    /// installing again swaps the sink
+    fn install_hooks(&self, sink: EventSink) -> Result<(), String>;
+    
+    fn uninstall_hooks(&self);
+    
+    /// `extended` marks keys from the E0-prefixed part of the keyboard (arrows, numpad Enter, ...)
+    fn send_key(&self, vk_code: u16, scan_code: u16, is_down: bool, extended: bool) -> Result<(), SendError>;
+    
+    /// Types the text as-is, whatever keyboard layout is active
+    fn send_text(&self, text: &str) -> Result<(), SendError>;
+    
+    fn move_mouse(&self, x: i32, y: i32, method: CursorMethod) -> Result<(), SendError>;
+    
+    /// Presses or releases a button wherever the cursor is
+    fn send_mouse_button(&self, button: u32, is_down: bool) -> Result<(), SendError>;
+    
+    /// Wheel turn in WHEEL_DELTA units (120 per notch) at the cursor
+    fn send_scroll(&self, delta: i32, horizontal: bool) -> Result<(), SendError>;
+    
+    /// Where the cursor is now, in virtual-desktop pixels
+    fn cursor_position(&self) -> Option<(i32, i32)>;
+    
+    /// The scan code the active layout gives a virtual key, for files that didn't record one
+    fn scan_code(&self, vk_code: u16) -> u16;
+    
+    /// Longest gap between two presses that still makes a double-click
+    fn double_click_time(&self) -> Duration;
+    
+    /// Bounding box of all monitors
+    fn screen_bounds(&self) -> ScreenBounds;
+    
+    fn monitors(&self) -> Vec<Monitor>;
+    
+    /// Queues a raw window message for `window`; replaying into a background window is
+    /// the one place playback speaks the OS's own terms
+    fn post_message(&self, window: isize, msg: u32, wparam: usize, lparam: isize) -> Result<(), SendError>;
+    
+    /// Converts a screen point to `window`'s client-area coordinates
+    fn screen_to_client(&self, window: isize, x: i32, y: i32) -> (i32, i32);
+}
+
+// The backend for the OS this was built for
+#[cfg(windows)]
+pub fn native_backend() -> Arc<dyn InputBackend> {
+    Arc::new(windows_backend::WindowsBackend::new())
+}
+
+// No backend yet for this OS (X11/evdev would go here); every call fails
+#[cfg(not(windows))]
+pub fn native_backend() -> Arc<dyn InputBackend> {
+    Arc::new(UnsupportedBackend)
+}
+
+#[cfg(not(windows))]
+struct UnsupportedBackend;
+
+#[cfg(not(windows))]
+impl InputBackend for UnsupportedBackend {
+    fn install_hooks(&self, _sink: EventSink) -> Result<(), String> {
+        Err("Input capture isn't supported on this platform".to_string())
+    }
+    
+    fn uninstall_hooks(&self) {}
+    
+    fn send_key(&self, _vk_code: u16, _scan_code: u16, _is_down: bool, _extended: bool) -> Result<(), SendError> {
+        Err(SendError::Unsupported)
+    }
+    
+    fn send_text(&self, _text: &str) -> Result<(), SendError> {
+        Err(SendError::Unsupported)
+    }
+    
+    fn move_mouse(&self, _x: i32, _y: i32, _method: CursorMethod) -> Result<(), SendError> {
+        Err(SendError::Unsupported)
+    }
+    
+    fn send_mouse_button(&self, _button: u32, _is_down: bool) -> Result<(), SendError> {
+        Err(SendError::Unsupported)
+    }
+    
+    fn send_scroll(&self, _delta: i32, _horizontal: bool) -> Result<(), SendError> {
+        Err(SendError::Unsupported)
+    }
+    
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        None
+    }
+    
+    fn scan_code(&self, _vk_code: u16) -> u16 {
+        0
+    }
+    
+    // The Windows default
+    fn double_click_time(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+    
+    // No screen to play onto, so every point is off it
+    fn screen_bounds(&self) -> ScreenBounds {
+        ScreenBounds { left: 0, top: 0, right: 0, bottom: 0 }
+    }
+    
+    fn monitors(&self) -> Vec<Monitor> {
+        Vec::new()
+    }
+    
+    fn post_message(&self, _window: isize, _msg: u32, _wparam: usize, _lparam: isize) -> Result<(), SendError> {
+        Err(SendError::Unsupported)
+    }
+    
+    fn screen_to_client(&self, _window: isize, x: i32, y: i32) -> (i32, i32) {
+        (x, y)
+    }
+}
+
+#[cfg(windows)]
+pub use windows_backend::WindowsBackend;
+
+#[cfg(windows)]
+mod windows_backend {
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::Duration;
+    use windows::Win32::{
+        Foundation::{HWND, LPARAM, POINT, WPARAM},
+        Graphics::Gdi::ScreenToClient,
+        UI::{Input::KeyboardAndMouse::*, WindowsAndMessaging::*},
+    };
+    use super::{EventSink, InputBackend};
+    use crate::hooks::GlobalHooks;
+    use crate::monitors::{self, Monitor};
+    use crate::player::{CursorMethod, SendError};
+    use crate::preflight::ScreenBounds;
+    
+    // Low-level hooks for capture, SendInput for replay, PostMessage for background windows.
+    // The hooks are only made on first install, so a playback-only backend never owns any.
+    #[derive(Default)]
+    pub struct WindowsBackend {
+        hooks: OnceLock<Arc<Mutex<GlobalHooks>>>,
+    }
+    
+    impl WindowsBackend {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        
+        /// Captures through `hooks`, so whatever else is set on them (hotkeys, move
+        /// throttling, input blocking) applies while recording
+        pub fn with_hooks(hooks: Arc<Mutex<GlobalHooks>>) -> Self {
+            Self { hooks: OnceLock::from(hooks) }
+        }
+        
+        fn hooks(&self) -> &Arc<Mutex<GlobalHooks>> {
+            self.hooks.get_or_init(|| Arc::new(Mutex::new(GlobalHooks::new())))
+        }
+    }
+    
+    // Compare the number of inputs SendInput reports as injected against what we passed
+    fn check_sent(sent: usize, expected: usize) -> Result<(), SendError> {
+        if sent >= expected {
+            Ok(())
+        } else if sent == 0 {
+            Err(SendError::Blocked)
+        } else {
+            Err(SendError::Partial { sent, expected })
+        }
+    }
+    
+    fn send_inputs(inputs: &[INPUT]) -> Result<(), SendError> {
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        
+        let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+        check_sent(sent as usize, inputs.len())
+    }
+    
+    // Map a virtual-desktop pixel to the 0-65535 range MOUSEEVENTF_VIRTUALDESK expects
+    fn normalize_absolute(x: i32, y: i32, bounds: &ScreenBounds) -> (i32, i32) {
+        let scale = |value: i64, origin: i64, extent: i64| {
+            let span = (extent - 1).max(1);
+            (((value - origin).clamp(0, span) * 65535 + span / 2) / span) as i32
+        };
+        (
+            scale(x as i64, bounds.left, bounds.right - bounds.left),
+            scale(y as i64, bounds.top, bounds.bottom - bounds.top),
+        )
+    }
+    
+    fn mouse_input(dx: i32, dy: i32, mouse_data: i32, flags: MOUSE_EVENT_FLAGS) -> INPUT {
+        INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT {
+                    dx,
+                    dy,
+                    mouseData: mouse_data,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }
+    }
+    
+    fn key_input(vk_code: u16, scan_code: u16, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(vk_code),
+                    wScan: scan_code,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }
+    }
+    
+    impl InputBackend for WindowsBackend {
+        fn install_hooks(&self, sink: EventSink) -> Result<(), String> {
+            let mut hooks = self.hooks().lock().map_err(|_| "Hook state is poisoned".to_string())?;
+            hooks.uninstall();
+            hooks.set_callback(sink);
+            hooks.install()
+        }
+        
+        fn uninstall_hooks(&self) {
+            if let Some(hooks) = self.hooks.get() {
+                if let Ok(mut hooks) = hooks.lock() {
+                    hooks.uninstall();
+                }
+            }
+        }
+        
+        fn send_key(&self, vk_code: u16, scan_code: u16, is_down: bool, extended: bool) -> Result<(), SendError> {
+            let mut flags = if is_down { KEYBD_EVENT_FLAGS(0) } else { KEYEVENTF_KEYUP };
+            if extended {
+                flags |= KEYEVENTF_EXTENDEDKEY;
+            }
+            send_inputs(&[key_input(vk_code, scan_code, flags)])
+        }
+        
+        // KEYEVENTF_UNICODE types the UTF-16 unit itself, whatever keyboard layout is active
+        fn send_text(&self, text: &str) -> Result<(), SendError> {
+            let inputs: Vec<INPUT> = text.encode_utf16()
+                .flat_map(|unit| {
+                    [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP].map(|flags| key_input(0, unit, flags))
+                })
+                .collect();
+            send_inputs(&inputs)
+        }
+        
+        fn move_mouse(&self, x: i32, y: i32, method: CursorMethod) -> Result<(), SendError> {
+            match method {
+                CursorMethod::SetCursorPos => unsafe { SetCursorPos(x, y) }.map_err(|_| SendError::Blocked),
+                CursorMethod::SendInputAbsolute => {
+                    // Re-read every time: monitors can be plugged in or rearranged mid-playback
+                    let (dx, dy) = normalize_absolute(x, y, &ScreenBounds::virtual_screen());
+                    send_inputs(&[mouse_input(dx, dy, 0, MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK)])
+                }
+            }
+        }
+        
+        fn send_mouse_button(&self, button: u32, is_down: bool) -> Result<(), SendError> {
+            let (flags, mouse_data) = match (button, is_down) {
+                (1, true) => (MOUSEEVENTF_LEFTDOWN, 0),
+                (1, false) => (MOUSEEVENTF_LEFTUP, 0),
+                (2, true) => (MOUSEEVENTF_RIGHTDOWN, 0),
+                (2, false) => (MOUSEEVENTF_RIGHTUP, 0),
+                (3, true) => (MOUSEEVENTF_MIDDLEDOWN, 0),
+                (3, false) => (MOUSEEVENTF_MIDDLEUP, 0),
+                (4, true) => (MOUSEEVENTF_XDOWN, XBUTTON1 as i32),
+                (4, false) => (MOUSEEVENTF_XUP, XBUTTON1 as i32),
+                (5, true) => (MOUSEEVENTF_XDOWN, XBUTTON2 as i32),
+                (5, false) => (MOUSEEVENTF_XUP, XBUTTON2 as i32),
+                _ => return Ok(()),
+            };
+            send_inputs(&[mouse_input(0, 0, mouse_data, flags)])
+        }
+        
+        fn send_scroll(&self, delta: i32, horizontal: bool) -> Result<(), SendError> {
+            let flags = if horizontal { MOUSEEVENTF_HWHEEL } else { MOUSEEVENTF_WHEEL };
+            send_inputs(&[mouse_input(0, 0, delta, flags)])
+        }
+        
+        fn cursor_position(&self) -> Option<(i32, i32)> {
+            crate::hooks::cursor_position()
+        }
+        
+        fn scan_code(&self, vk_code: u16) -> u16 {
+            unsafe { MapVirtualKeyW(vk_code as u32, MAPVK_VK_TO_VSC) as u16 }
+        }
+        
+        fn double_click_time(&self) -> Duration {
+            Duration::from_millis(unsafe { GetDoubleClickTime() } as u64)
+        }
+        
+        fn screen_bounds(&self) -> ScreenBounds {
+            ScreenBounds::virtual_screen()
+        }
+        
+        fn monitors(&self) -> Vec<Monitor> {
+            monitors::all_monitors()
+        }
+        
+        fn post_message(&self, window: isize, msg: u32, wparam: usize, lparam: isize) -> Result<(), SendError> {
+            unsafe { PostMessageW(HWND(window), msg, WPARAM(wparam), LPARAM(lparam)) }.map_err(|_| SendError::Blocked)
+        }
+        
+        fn screen_to_client(&self, window: isize, x: i32, y: i32) -> (i32, i32) {
+            let mut point = POINT { x, y };
+            unsafe { ScreenToClient(HWND(window), &mut point) };
+            (point.x, point.y)
+        }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        
+        #[test]
+        fn short_sendinput_counts_are_errors() {
+            assert_eq!(check_sent(2, 2), Ok(()));
+            assert_eq!(check_sent(1, 2), Err(SendError::Partial { sent: 1, expected: 2 }));
+            assert_eq!(check_sent(0, 2), Err(SendError::Blocked));
+        }
+    }
+}
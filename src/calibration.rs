@@ -0,0 +1,154 @@
+use crate::events::MacroEvent;
+use crate::player::SendError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// F24 exists on virtually no physical keyboard, so injecting it is harmless
+pub const PROBE_VK: u16 = 0x87;
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfTestResult {
+    Working,
+    SendFailed(String),
+    NotObserved,
+}
+
+impl SelfTestResult {
+    pub fn describe(&self) -> String {
+        match self {
+            SelfTestResult::Working => "SendInput is working - injected input reached the hooks".to_string(),
+            SelfTestResult::SendFailed(e) => format!("SendInput failed: {}", e),
+            SelfTestResult::NotObserved => {
+                "Injected input was not delivered (blocked by UIPI or another hook?) - try running as Administrator".to_string()
+            }
+        }
+    }
+}
+
+// Shared between the hook callback, which reports what it sees, and the UI polling the result
+pub struct InputProbe {
+    armed: AtomicBool,
+    observed: AtomicBool,
+}
+
+impl InputProbe {
+    pub fn new() -> Self {
+        Self {
+            armed: AtomicBool::new(false),
+            observed: AtomicBool::new(false),
+        }
+    }
+    
+    pub fn arm(&self) {
+        self.observed.store(false, Ordering::SeqCst);
+        self.armed.store(true, Ordering::SeqCst);
+    }
+    
+    pub fn disarm(&self) {
+        self.armed.store(false, Ordering::SeqCst);
+    }
+    
+    // Returns true when the event is our probe key so callers can keep it out of recordings
+    pub fn observe(&self, event: &MacroEvent) -> bool {
+        if !self.armed.load(Ordering::SeqCst) {
+            return false;
+        }
+        
+        let is_probe = event.data.get("vk_code")
+            .and_then(|vk| vk.as_u64())
+            .map(|vk| vk == PROBE_VK as u64)
+            .unwrap_or(false);
+        
+        if is_probe {
+            self.observed.store(true, Ordering::SeqCst);
+        }
+        is_probe
+    }
+    
+    pub fn was_observed(&self) -> bool {
+        self.observed.load(Ordering::SeqCst)
+    }
+}
+
+pub struct SelfTest {
+    started: Instant,
+}
+
+impl SelfTest {
+    // Arms the probe and injects via `send`; the hook delivers asynchronously so the result is polled
+    pub fn start<F>(probe: &InputProbe, send: F) -> Result<Self, SelfTestResult>
+    where
+        F: FnOnce() -> Result<(), SendError>,
+    {
+        probe.arm();
+        
+        match send() {
+            Ok(()) => Ok(Self { started: Instant::now() }),
+            Err(e) => {
+                probe.disarm();
+                Err(SelfTestResult::SendFailed(e.to_string()))
+            }
+        }
+    }
+    
+    pub fn poll(&self, probe: &InputProbe, now: Instant) -> Option<SelfTestResult> {
+        if probe.was_observed() {
+            probe.disarm();
+            Some(SelfTestResult::Working)
+        } else if now.duration_since(self.started) >= PROBE_TIMEOUT {
+            probe.disarm();
+            Some(SelfTestResult::NotObserved)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn send_probe_key() -> Result<(), SendError> {
+    let backend = crate::backend::native_backend();
+    backend.send_key(PROBE_VK, 0, true, false)?;
+    backend.send_key(PROBE_VK, 0, false, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    
+    fn key_event(vk_code: u16) -> MacroEvent {
+        MacroEvent::with_data(0.0, EventType::KeyDown, serde_json::json!({ "vk_code": vk_code }))
+    }
+    
+    #[test]
+    fn the_probe_seen_by_the_hook_means_input_works() {
+        let probe = InputProbe::new();
+        let test = SelfTest::start(&probe, || Ok(())).unwrap();
+        let started = Instant::now();
+        
+        assert!(!probe.observe(&key_event(0x41)));
+        assert_eq!(test.poll(&probe, started), None);
+        
+        assert!(probe.observe(&key_event(PROBE_VK)));
+        assert_eq!(test.poll(&probe, started), Some(SelfTestResult::Working));
+        // Disarmed once decided, so a real F24 afterwards is recorded normally
+        assert!(!probe.observe(&key_event(PROBE_VK)));
+    }
+    
+    #[test]
+    fn a_probe_never_seen_times_out() {
+        let probe = InputProbe::new();
+        let test = SelfTest::start(&probe, || Ok(())).unwrap();
+        
+        assert_eq!(test.poll(&probe, Instant::now() + PROBE_TIMEOUT), Some(SelfTestResult::NotObserved));
+    }
+    
+    #[test]
+    fn a_failed_send_is_reported_right_away() {
+        let probe = InputProbe::new();
+        let result = SelfTest::start(&probe, || Err(SendError::Blocked));
+        
+        assert!(matches!(result, Err(SelfTestResult::SendFailed(_))));
+        assert!(!probe.observe(&key_event(PROBE_VK)));
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(windows)]
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex};
+#[cfg(windows)]
+use std::time::Duration;
+#[cfg(windows)]
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE},
+};
+#[cfg(windows)]
+use crate::emergency::EmergencyStop;
+#[cfg(windows)]
+use crate::backend::WindowsBackend;
+#[cfg(windows)]
+use crate::hooks::GlobalHooks;
+use crate::hotkeys::{hotkey_config_path, HotkeyConfig};
+use crate::player::MacroPlayer;
+#[cfg(windows)]
+use crate::recorder::MacroRecorder;
+
+pub const USAGE: &str = "usage:
+  macro_recorder record <out.mcr> [--classic]   (--classic writes one line per mouse move)
+  macro_recorder play <in.mcr> [--speed N] [--repeat N]   (--repeat 0 loops until stopped)";
+
+// How often the record loop drains the hook thread's message queue
+#[cfg(windows)]
+const PUMP_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Record { path: String, classic: bool },
+    Play { path: String, speed: f32, repeat: usize },
+    Help,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    match args.as_slice() {
+        ["record", path] => Ok(Command::Record { path: path.to_string(), classic: false }),
+        ["record", path, "--classic"] => Ok(Command::Record { path: path.to_string(), classic: true }),
+        ["play", path, rest @ ..] => {
+            let mut speed = 1.0;
+            let mut repeat = 1;
+            let mut options = rest.iter();
+            while let Some(option) = options.next() {
+                match *option {
+                    "--speed" => speed = parse_value(option, options.next())?,
+                    "--repeat" => repeat = parse_value(option, options.next())?,
+                    // `play <file> <speed>` from before the options existed
+                    value => speed = value.parse().map_err(|_| format!("unexpected argument '{}'", value))?,
+                }
+            }
+            Ok(Command::Play { path: path.to_string(), speed, repeat })
+        }
+        ["help"] | ["--help"] | ["-h"] => Ok(Command::Help),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<&&str>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", option))?;
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, option))
+}
+
+// Runs a subcommand without the GUI; returns once it's done
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let hotkeys = HotkeyConfig::load(&hotkey_config_path());
+    
+    match parse_args(args)? {
+        Command::Record { path, classic } => record(&path, classic, &hotkeys),
+        Command::Play { path, speed, repeat } => play(&path, speed, repeat, &hotkeys),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+fn record(path: &str, classic: bool, hotkeys: &HotkeyConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let recorder = Arc::new(Mutex::new(MacroRecorder::new()));
+    let stop = Arc::new(AtomicBool::new(false));
+    let _stop_hotkey = EmergencyStop::register(hotkeys.stop, stop.clone(), stop.clone())?;
+    
+    let mut hooks = GlobalHooks::new();
+    hooks.set_hotkeys(hotkeys);
+    let backend = WindowsBackend::with_hooks(Arc::new(Mutex::new(hooks)));
+    
+    MacroRecorder::start_capture(&recorder, &backend)?;
+    println!("Recording to {}; press {} to stop", path, hotkeys.stop.label());
+    
+    // Low-level hooks are only called while the installing thread pumps messages
+    let mut msg = MSG::default();
+    while !stop.load(Ordering::SeqCst) {
+        unsafe {
+            while PeekMessageW(&mut msg, HWND(0), 0, 0, PM_REMOVE).as_bool() {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        
+        let limit = recorder.lock().ok().and_then(|mut recorder| {
+            recorder.check_limits();
+            recorder.take_limit_reached()
+        });
+        if let Some(limit) = limit {
+            println!("Recording stopped automatically: {}", limit.describe());
+            break;
+        }
+        std::thread::sleep(PUMP_INTERVAL);
+    }
+    
+    MacroRecorder::stop_capture(&recorder, &backend);
+    let mut recorder = recorder.lock().unwrap();
+    recorder.set_compact_paths(!classic);
+    recorder.save_to_file(path)?;
+    println!("Saved {} events to {}", recorder.get_events().len(), path);
+    Ok(())
+}
+
+// Capture needs the Windows hooks; there's no other backend for it yet
+#[cfg(not(windows))]
+fn record(_path: &str, _classic: bool, _hotkeys: &HotkeyConfig) -> Result<(), Box<dyn std::error::Error>> {
+    Err("recording isn't supported on this platform yet".into())
+}
+
+fn play(path: &str, speed: f32, repeat: usize, hotkeys: &HotkeyConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut player = MacroPlayer::new();
+    let count = player.load_from_file(path)?;
+    player.set_speed(speed);
+    player.set_repeat_count(repeat);
+    // Without a window to focus, the stop hotkey has to work system-wide
+    player.set_emergency_hotkey(Some(hotkeys.stop));
+    
+    println!("Playing {} events from {} at {}x; press {} to stop", count, path, speed, hotkeys.stop.label());
+    player.start();
+    player.wait();
+    
+    if player.take_emergency_stop() {
+        println!("Stopped by {}", hotkeys.stop.label());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+    
+    #[test]
+    fn parses_play_options_and_legacy_speed() {
+        assert_eq!(
+            parse_args(&args(&["play", "a.mcr", "--speed", "2", "--repeat", "0"])),
+            Ok(Command::Play { path: "a.mcr".to_string(), speed: 2.0, repeat: 0 })
+        );
+        assert_eq!(
+            parse_args(&args(&["play", "a.mcr", "0.5"])),
+            Ok(Command::Play { path: "a.mcr".to_string(), speed: 0.5, repeat: 1 })
+        );
+        assert!(parse_args(&args(&["play", "a.mcr", "--speed"])).is_err());
+    }
+    
+    #[test]
+    fn parses_record() {
+        assert_eq!(
+            parse_args(&args(&["record", "out.mcr", "--classic"])),
+            Ok(Command::Record { path: "out.mcr".to_string(), classic: true })
+        );
+        assert_eq!(parse_args(&args(&[])), Err(USAGE.to_string()));
+    }
+    
+    // Runs in headless builds too, where this is the whole front-end
+    #[test]
+    fn runs_without_the_gui() {
+        assert!(run(&args(&["help"])).is_ok());
+        assert!(run(&args(&["play", "no/such/file.mcr"])).is_err());
+    }
+}
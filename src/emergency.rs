@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::Input::KeyboardAndMouse::*,
+    UI::WindowsAndMessaging::{GetMessageW, PostThreadMessageW, MSG, WM_HOTKEY, WM_QUIT},
+};
+use crate::hotkeys::Hotkey;
+
+// RegisterHotKey ids only need to be unique per thread, but distinct ones make logs clearer
+static NEXT_HOTKEY_ID: AtomicI32 = AtomicI32::new(1);
+
+// A system-wide hotkey on its own message-loop thread, so it fires whichever window has focus.
+// Dropping it unregisters the hotkey.
+pub struct GlobalHotkey {
+    thread_id: u32,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl GlobalHotkey {
+    // Calls `on_press`, on the hotkey's own thread, each time `hotkey` is pressed anywhere
+    pub fn register(hotkey: Hotkey, on_press: impl Fn() + Send + 'static) -> Result<Self, String> {
+        let id = NEXT_HOTKEY_ID.fetch_add(1, Ordering::Relaxed);
+        let (ready_tx, ready_rx) = mpsc::channel();
+        
+        let thread = thread::spawn(move || unsafe {
+            if let Err(e) = RegisterHotKey(HWND(0), id, hotkey_modifiers(&hotkey), hotkey.vk) {
+                let _ = ready_tx.send(Err(format!("Failed to register {}: {}", hotkey.label(), e)));
+                return;
+            }
+            let _ = ready_tx.send(Ok(GetCurrentThreadId()));
+            
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, HWND(0), 0, 0).as_bool() {
+                if msg.message == WM_HOTKEY && msg.wParam.0 == id as usize {
+                    on_press();
+                }
+            }
+            
+            let _ = UnregisterHotKey(HWND(0), id);
+        });
+        
+        match ready_rx.recv() {
+            Ok(Ok(thread_id)) => Ok(Self { thread_id, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err("Hotkey thread exited unexpectedly".to_string()),
+        }
+    }
+}
+
+impl Drop for GlobalHotkey {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Stops playback from any window. Dropping it unregisters the hotkey.
+pub struct EmergencyStop {
+    _hotkey: GlobalHotkey,
+}
+
+impl EmergencyStop {
+    // Raises `stop_signal` (and `triggered`) when `hotkey` is pressed anywhere
+    pub fn register(hotkey: Hotkey, stop_signal: Arc<AtomicBool>, triggered: Arc<AtomicBool>) -> Result<Self, String> {
+        let hotkey = GlobalHotkey::register(hotkey, move || {
+            log::warn!("Emergency stop hotkey {} pressed", hotkey.label());
+            stop_signal.store(true, Ordering::SeqCst);
+            triggered.store(true, Ordering::SeqCst);
+        })?;
+        Ok(Self { _hotkey: hotkey })
+    }
+}
+
+fn hotkey_modifiers(hotkey: &Hotkey) -> HOT_KEY_MODIFIERS {
+    let mut modifiers = MOD_NOREPEAT;
+    if hotkey.ctrl {
+        modifiers |= MOD_CONTROL;
+    }
+    if hotkey.shift {
+        modifiers |= MOD_SHIFT;
+    }
+    if hotkey.alt {
+        modifiers |= MOD_ALT;
+    }
+    modifiers
+}
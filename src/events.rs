@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EventType {
     KeyDown,
     KeyUp,
@@ -9,6 +11,14 @@ pub enum EventType {
     MouseDown,
     MouseUp,
     MouseScroll,
+    MouseHScroll,
+    FocusChange,
+    Hover,
+    Click,
+    // Characters the layout produced that have no plain virtual key (accents, Cyrillic, ...)
+    TextInput,
+    // Hand-authored pause: at least `ms` of real time passes before the next event
+    Delay,
 }
 
 impl fmt::Display for EventType {
@@ -20,11 +30,18 @@ impl fmt::Display for EventType {
             EventType::MouseDown => write!(f, "MDOWN"),
             EventType::MouseUp => write!(f, "MUP"),
             EventType::MouseScroll => write!(f, "MSCROLL"),
+            EventType::MouseHScroll => write!(f, "MHSCROLL"),
+            EventType::FocusChange => write!(f, "FOCUS"),
+            EventType::Hover => write!(f, "HOVER"),
+            EventType::Click => write!(f, "CLICK"),
+            EventType::TextInput => write!(f, "TEXT"),
+            EventType::Delay => write!(f, "WAIT"),
         }
     }
 }
 
 impl EventType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "KDOWN" => Some(EventType::KeyDown),
@@ -33,24 +50,337 @@ impl EventType {
             "MDOWN" => Some(EventType::MouseDown),
             "MUP" => Some(EventType::MouseUp),
             "MSCROLL" => Some(EventType::MouseScroll),
+            "MHSCROLL" => Some(EventType::MouseHScroll),
+            "FOCUS" => Some(EventType::FocusChange),
+            "HOVER" => Some(EventType::Hover),
+            "CLICK" => Some(EventType::Click),
+            "TEXT" => Some(EventType::TextInput),
+            "WAIT" => Some(EventType::Delay),
             _ => None,
         }
     }
 }
 
+// Shared by whatever captures input and the recorder, so an event keeps the time it happened
+// rather than the time it was handed over
+static CAPTURE_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+// Now, in seconds on the capture clock; captured events carry this as their timestamp until
+// the recorder rebases them onto the recording
+pub fn capture_clock() -> f64 {
+    CAPTURE_EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+// The moment a capture-clock time refers to
+pub fn capture_instant(time: f64) -> Instant {
+    let epoch = *CAPTURE_EPOCH.get_or_init(Instant::now);
+    epoch + Duration::from_secs_f64(time.max(0.0))
+}
+
+// JSON keeps every data field; .mcr is the compact (and Python-compatible) default
+pub fn is_json_path(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+// Written as the first line of every .mcr file; files without it predate versioning and are v1.
+// v3 added MPATH lines.
+pub const MCR_FORMAT_VERSION: u32 = 3;
+// Files without any MPATH line are still declared this version, so builds from before v3 open them
+pub const MCR_BASE_VERSION: u32 = 2;
+const MCR_VERSION_PREFIX: &str = "# MCR v";
+const MCR_EPOCH_PREFIX: &str = "# epoch_ms=";
+
+// Version line for a file made of `lines`: the oldest version that can hold them
+pub fn mcr_header(lines: &[String]) -> String {
+    let version = if lines.iter().any(|line| is_path_line(line)) {
+        MCR_FORMAT_VERSION
+    } else {
+        MCR_BASE_VERSION
+    };
+    format!("{}{}", MCR_VERSION_PREFIX, version)
+}
+
+// Version declared by a `# MCR vN` line, if `line` is one
+pub fn parse_mcr_version(line: &str) -> Option<u32> {
+    line.trim().strip_prefix(MCR_VERSION_PREFIX)?.trim().parse().ok()
+}
+
+// Header line recording the wall clock (Unix ms) when recording started
+pub fn mcr_epoch_line(epoch_ms: i64) -> String {
+    format!("{}{}", MCR_EPOCH_PREFIX, epoch_ms)
+}
+
+// Recording start time from the header comments, if the file has one
+pub fn parse_mcr_epoch(content: &str) -> Option<i64> {
+    content.lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with('#'))
+        .find_map(|line| line.strip_prefix(MCR_EPOCH_PREFIX)?.trim().parse().ok())
+}
+
+// Format version and events of a whole .mcr file. Newer versions are still read line by line,
+// but fields this build doesn't know are dropped, so that is logged.
+pub fn parse_mcr(content: &str) -> (u32, Vec<MacroEvent>) {
+    let version = content.lines()
+        .find(|line| !line.trim().is_empty())
+        .and_then(parse_mcr_version)
+        .unwrap_or(1);
+    if version > MCR_FORMAT_VERSION {
+        log::warn!("File is .mcr v{} but this build reads up to v{}; newer fields will be ignored",
+            version, MCR_FORMAT_VERSION);
+    }
+    
+    let events = content.lines()
+        .flat_map(parse_mcr_line)
+        .collect();
+    (version, events)
+}
+
+// A run needs at least this many moves before an MPATH line is shorter than the plain lines
+pub const MIN_PATH_POINTS: usize = 3;
+// Keeps a single line from growing without bound during long drags
+const MAX_PATH_POINTS: usize = 500;
+const MCR_PATH_TYPE: &str = "MPATH";
+
+// Events of one .mcr line: one for an ordinary line, several for an MPATH line, none for
+// comments and lines that don't parse
+pub fn parse_mcr_line(line: &str) -> Vec<MacroEvent> {
+    parse_path_line(line).unwrap_or_else(|| MacroEvent::from_mcr_line(line).into_iter().collect())
+}
+
+// The .mcr lines for `events`. With `compact_paths`, runs of plain mouse moves are written as
+// one MPATH line each; otherwise every event gets its own line, as older builds expect.
+pub fn mcr_lines(events: &[MacroEvent], compact_paths: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        let run = if compact_paths { path_run_len(&events[i..]) } else { 0 };
+        if run >= MIN_PATH_POINTS {
+            lines.push(path_line(&events[i..i + run]));
+            i += run;
+        } else {
+            lines.push(events[i].to_mcr_line());
+            i += 1;
+        }
+    }
+    lines
+}
+
+// Monitor fields the hooks add to every mouse event; an MPATH line carries them once, so a run
+// only spans moves on the same monitor
+const PATH_SHARED_FIELDS: [&str; 3] = ["monitor", "monitor_left", "monitor_top"];
+
+// A move with a position. Of the other fields a plain MMOVE line would write, the monitor is
+// shared by the whole run and the window tag (set only when the window changed) can only be on
+// its first point; anything else isn't written for moves in either form.
+fn is_path_point(event: &MacroEvent) -> bool {
+    event.is_mouse_move()
+        && event.data.get("x").and_then(|x| x.as_i64()).is_some()
+        && event.data.get("y").and_then(|y| y.as_i64()).is_some()
+}
+
+fn is_path_line(line: &str) -> bool {
+    line.split(';').nth(1) == Some(MCR_PATH_TYPE)
+}
+
+fn micros(timestamp: f64) -> i64 {
+    (timestamp * 1_000_000.0).round() as i64
+}
+
+// How many events from the start of `events` fit in one MPATH line: moves on the same track and
+// monitor, in time order, with seq numbers that can be rebuilt from the first one, and no window
+// tag past the first
+fn path_run_len(events: &[MacroEvent]) -> usize {
+    let first = match events.first() {
+        Some(first) if is_path_point(first) => first,
+        _ => return 0,
+    };
+    let same_monitor = |event: &MacroEvent| PATH_SHARED_FIELDS.iter()
+        .all(|field| event.data.get(field) == first.data.get(field));
+    let mut previous = first.timestamp;
+    events.iter()
+        .take(MAX_PATH_POINTS)
+        .enumerate()
+        .take_while(|(i, event)| {
+            let in_order = event.timestamp >= previous;
+            previous = event.timestamp;
+            let seq_follows = if first.seq == 0 { event.seq == 0 } else { event.seq == first.seq + *i as u64 };
+            let untagged = *i == 0 || event.data.get("window").is_none();
+            is_path_point(event) && event.source == first.source && same_monitor(event)
+                && untagged && in_order && seq_follows
+        })
+        .count()
+}
+
+// `t;MPATH;pts=dt:x:y|dt:x:y|...` where t is the first point's time and each dt is whole
+// microseconds since the previous point (0 for the first). win= belongs to the first point,
+// mon=/monx=/mony= to all of them.
+fn path_line(run: &[MacroEvent]) -> String {
+    let first = &run[0];
+    let mut previous = micros(first.timestamp);
+    let points: Vec<String> = run.iter()
+        .map(|event| {
+            let now = micros(event.timestamp);
+            let dt = now - previous;
+            previous = now;
+            let x = event.data.get("x").and_then(|x| x.as_i64()).unwrap_or(0);
+            let y = event.data.get("y").and_then(|y| y.as_i64()).unwrap_or(0);
+            format!("{}:{}:{}", dt, x, y)
+        })
+        .collect();
+    
+    let mut parts = vec![
+        format!("{:.6}", first.timestamp),
+        MCR_PATH_TYPE.to_string(),
+        format!("pts={}", points.join("|")),
+    ];
+    if let Some(window) = first.data.get("window").and_then(|w| w.as_str()) {
+        parts.push(format!("win={}", escape_value(window)));
+    }
+    if let Some(monitor) = first.data.get("monitor").and_then(|m| m.as_str()) {
+        parts.push(format!("mon={}", escape_value(monitor)));
+        for (field, key) in [("monitor_left", "monx"), ("monitor_top", "mony")] {
+            if let Some(value) = first.data.get(field).and_then(|v| v.as_i64()) {
+                parts.push(format!("{}={}", key, value));
+            }
+        }
+    }
+    if let Some(source) = &first.source {
+        parts.push(format!("src={}", source));
+    }
+    if first.seq > 0 {
+        parts.push(format!("seq={}", first.seq));
+    }
+    parts.join(";")
+}
+
+// The moves of an MPATH line; None for any other line, or one too damaged to expand
+fn parse_path_line(line: &str) -> Option<Vec<MacroEvent>> {
+    let parts: Vec<&str> = line.trim().split(';').collect();
+    if parts.len() < 3 || parts[1] != MCR_PATH_TYPE {
+        return None;
+    }
+    let start = parts[0].parse::<f64>().ok().filter(|timestamp| timestamp.is_finite())?;
+    
+    let mut points = None;
+    let mut window = None;
+    let mut shared = serde_json::Map::new();
+    let mut source = None;
+    let mut seq = 0;
+    for part in &parts[2..] {
+        match part.split_once('=') {
+            Some(("pts", value)) => points = Some(value),
+            Some(("win", value)) => window = Some(unescape_value(value)),
+            Some(("mon", value)) => {
+                shared.insert("monitor".to_string(), serde_json::Value::String(unescape_value(value)));
+            }
+            Some((key @ ("monx" | "mony"), value)) => {
+                let field = if key == "monx" { "monitor_left" } else { "monitor_top" };
+                if let Ok(num) = value.parse::<i64>() {
+                    shared.insert(field.to_string(), serde_json::Value::from(num));
+                }
+            }
+            Some(("src", value)) => source = Some(value.to_string()),
+            Some(("seq", value)) => seq = value.parse::<u64>().unwrap_or(0),
+            _ => {}
+        }
+    }
+    
+    let mut now = micros(start);
+    let mut events = Vec::new();
+    for (i, point) in points?.split('|').enumerate() {
+        let mut fields = point.split(':').map(|field| field.parse::<i64>().ok());
+        let (dt, x, y) = (fields.next()??, fields.next()??, fields.next()??);
+        now += dt;
+        let mut event = MacroEvent::with_data(now as f64 / 1_000_000.0, EventType::MouseMove, serde_json::json!({ "x": x, "y": y }));
+        for (field, value) in &shared {
+            event.data[field] = value.clone();
+        }
+        if i == 0 {
+            if let Some(window) = &window {
+                event.data["window"] = serde_json::Value::String(window.clone());
+            }
+        }
+        event.source = source.clone();
+        event.seq = if seq > 0 { seq + i as u64 } else { 0 };
+        events.push(event);
+    }
+    Some(events)
+}
+
+// .mcr.gz holds the same lines as .mcr, gzip-compressed
+pub fn is_gzip_path(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".mcr.gz")
+}
+
+// Contents of a .mcr file, decompressing .mcr.gz transparently
+pub fn read_mcr_text(path: &str) -> std::io::Result<String> {
+    use std::io::Read;
+    
+    if !is_gzip_path(path) {
+        return std::fs::read_to_string(path);
+    }
+    
+    let mut content = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(path)?).read_to_string(&mut content)?;
+    Ok(content)
+}
+
+// Writes .mcr text, compressing it when the path ends in .mcr.gz
+pub fn write_mcr_text(path: &str, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    
+    if !is_gzip_path(path) {
+        return std::fs::write(path, content);
+    }
+    
+    let mut encoder = flate2::write::GzEncoder::new(std::fs::File::create(path)?, flate2::Compression::default());
+    encoder.write_all(content.as_bytes())?;
+    encoder.finish()?;
+    Ok(())
+}
+
+// One notch of a standard mouse wheel
+pub const WHEEL_DELTA: i64 = 120;
+
+// Track labels assigned by the hooks so keyboard and mouse input can be muted separately
+pub const TRACK_KEYBOARD: &str = "keyboard";
+pub const TRACK_MOUSE: &str = "mouse";
+
+// Key names for the numpad, written by the hooks and read by the player so recorded names map back to the same keys
+pub(crate) const NUMPAD_KEY_NAMES: [(u32, &str); 16] = [
+    (0x60, "num0"), (0x61, "num1"), (0x62, "num2"), (0x63, "num3"), (0x64, "num4"),
+    (0x65, "num5"), (0x66, "num6"), (0x67, "num7"), (0x68, "num8"), (0x69, "num9"),
+    (0x6A, "num*"), (0x6B, "num+"), (0x6D, "num-"), (0x6E, "num."), (0x6F, "num/"),
+    (0x90, "numlock"),
+];
+// Numpad Enter shares VK_RETURN with the main Enter key; only the extended flag tells them apart
+pub(crate) const NUMPAD_ENTER_NAME: &str = "numenter";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MacroEvent {
     pub timestamp: f64,
     pub event_type: EventType,
     pub data: serde_json::Value,
+    #[serde(default)]
+    pub source: Option<String>,
+    // Capture order, used to break ties between events with identical timestamps
+    #[serde(default)]
+    pub seq: u64,
 }
 
 impl MacroEvent {
+    /// An event with no data fields
     pub fn new(timestamp: f64, event_type: EventType) -> Self {
         Self {
             timestamp,
             event_type,
             data: serde_json::Value::Object(serde_json::Map::new()),
+            source: None,
+            seq: 0,
         }
     }
     
@@ -59,10 +389,12 @@ impl MacroEvent {
             timestamp,
             event_type,
             data,
+            source: None,
+            seq: 0,
         }
     }
     
-    // Convert to .mcr file format (compatible with Python version)
+    /// Convert to .mcr file format (compatible with Python version)
     pub fn to_mcr_line(&self) -> String {
         let mut parts = vec![
             format!("{:.6}", self.timestamp),
@@ -77,6 +409,23 @@ impl MacroEvent {
                         parts.push(format!("char={}", key_str));
                     }
                 }
+                
+                for (field, key) in [("vk_code", "vk"), ("scan_code", "sc")] {
+                    if let Some(code) = self.data.get(field).and_then(|v| v.as_u64()) {
+                        parts.push(format!("{}={}", key, code));
+                    }
+                }
+                
+                // Only written when set to keep ordinary lines short
+                for (field, key) in [("extended", "ext"), ("injected", "inj")] {
+                    if self.data.get(field).and_then(|v| v.as_bool()).unwrap_or(false) {
+                        parts.push(format!("{}=1", key));
+                    }
+                }
+                
+                if let Some(action) = self.data.get("action").and_then(|a| a.as_str()) {
+                    parts.push(format!("action={}", escape_value(action)));
+                }
             }
             EventType::MouseMove => {
                 if let (Some(x), Some(y)) = (self.data.get("x"), self.data.get("y")) {
@@ -86,7 +435,7 @@ impl MacroEvent {
                     }
                 }
             }
-            EventType::MouseDown | EventType::MouseUp => {
+            EventType::MouseDown | EventType::MouseUp | EventType::Click => {
                 if let (Some(x), Some(y), Some(button)) = (
                     self.data.get("x"),
                     self.data.get("y"),
@@ -101,6 +450,8 @@ impl MacroEvent {
                             1 => "left",
                             2 => "right",
                             3 => "middle",
+                            4 => "x1",
+                            5 => "x2",
                             _ => "unknown",
                         };
                         parts.push(format!("button={}", button_name));
@@ -108,6 +459,11 @@ impl MacroEvent {
                         parts.push(format!("y={}", y_val));
                     }
                 }
+                
+                // Second press of a double-click
+                if self.is_double_click() {
+                    parts.push("dbl=1".to_string());
+                }
             }
             EventType::MouseScroll => {
                 if let (Some(x), Some(y), Some(delta)) = (
@@ -120,20 +476,84 @@ impl MacroEvent {
                         y.as_i64(),
                         delta.as_i64()
                     ) {
+                        // dx/dy keep the Python version's one-notch direction; delta is the real amount
                         let dy = if delta_val > 0 { 1 } else { -1 };
-                        parts.push(format!("dx=0"));
+                        parts.push("dx=0".to_string());
                         parts.push(format!("dy={}", dy));
+                        parts.push(format!("delta={}", delta_val));
                         parts.push(format!("x={}", x_val));
                         parts.push(format!("y={}", y_val));
                     }
                 }
             }
+            EventType::MouseHScroll => {
+                if let (Some(x), Some(y), Some(delta)) = (
+                    self.data.get("x").and_then(|x| x.as_i64()),
+                    self.data.get("y").and_then(|y| y.as_i64()),
+                    self.data.get("delta").and_then(|d| d.as_i64())
+                ) {
+                    parts.push(format!("hdx={}", delta));
+                    parts.push(format!("x={}", x));
+                    parts.push(format!("y={}", y));
+                }
+            }
+            EventType::FocusChange => {
+                if let Some(window) = self.data.get("window").and_then(|w| w.as_str()) {
+                    parts.push(format!("window={}", escape_value(window)));
+                }
+            }
+            EventType::Hover => {
+                if let (Some(x), Some(y), Some(duration)) = (
+                    self.data.get("x").and_then(|x| x.as_i64()),
+                    self.data.get("y").and_then(|y| y.as_i64()),
+                    self.data.get("duration_ms").and_then(|d| d.as_i64())
+                ) {
+                    parts.push(format!("x={}", x));
+                    parts.push(format!("y={}", y));
+                    parts.push(format!("duration_ms={}", duration));
+                }
+            }
+            EventType::TextInput => {
+                if let Some(text) = self.data.get("text").and_then(|t| t.as_str()) {
+                    parts.push(format!("text={}", escape_value(text)));
+                }
+            }
+            EventType::Delay => {
+                if let Some(ms) = self.data.get("ms").and_then(|ms| ms.as_u64()) {
+                    parts.push(format!("ms={}", ms));
+                }
+            }
+        }
+        
+        // Focus changes already carry their title as window=
+        if !matches!(self.event_type, EventType::FocusChange) {
+            if let Some(window) = self.data.get("window").and_then(|w| w.as_str()) {
+                parts.push(format!("win={}", escape_value(window)));
+            }
+        }
+        
+        // Lets replay follow a monitor that was moved or rearranged since recording
+        if let Some(monitor) = self.data.get("monitor").and_then(|m| m.as_str()) {
+            parts.push(format!("mon={}", escape_value(monitor)));
+            for (field, key) in [("monitor_left", "monx"), ("monitor_top", "mony")] {
+                if let Some(value) = self.data.get(field).and_then(|v| v.as_i64()) {
+                    parts.push(format!("{}={}", key, value));
+                }
+            }
+        }
+        
+        if let Some(source) = &self.source {
+            parts.push(format!("src={}", source));
+        }
+        
+        if self.seq > 0 {
+            parts.push(format!("seq={}", self.seq));
         }
         
         parts.join(";")
     }
     
-    // Parse from .mcr file format
+    /// Parse from .mcr file format
     pub fn from_mcr_line(line: &str) -> Option<Self> {
         let line = line.trim();
         if line.is_empty() || line.starts_with('#') {
@@ -145,19 +565,61 @@ impl MacroEvent {
             return None;
         }
         
-        let timestamp = parts[0].parse::<f64>().ok()?;
+        // "nan" and "inf" parse as f64 but can't be scheduled
+        let timestamp = parts[0].parse::<f64>().ok().filter(|timestamp| timestamp.is_finite())?;
         let event_type = EventType::from_str(parts[1])?;
         
         let mut data = serde_json::Map::new();
+        let mut source = None;
+        let mut seq = 0;
         
         // Parse remaining parts as key=value pairs
         for part in parts.iter().skip(2) {
             if let Some((key, value)) = part.split_once('=') {
                 match key {
+                    "src" => {
+                        source = Some(value.to_string());
+                    }
+                    "seq" => {
+                        seq = value.parse::<u64>().unwrap_or(0);
+                    }
                     "char" => {
                         data.insert("key_name".to_string(), serde_json::Value::String(value.to_string()));
                     }
-                    "x" | "y" | "dx" | "dy" => {
+                    "hdx" => {
+                        if let Ok(num) = value.parse::<i64>() {
+                            data.insert("delta".to_string(), serde_json::Value::from(num));
+                        }
+                    }
+                    "vk" | "sc" => {
+                        let field = if key == "vk" { "vk_code" } else { "scan_code" };
+                        if let Ok(code) = value.parse::<u64>() {
+                            data.insert(field.to_string(), serde_json::Value::Number(serde_json::Number::from(code)));
+                        }
+                    }
+                    "ext" | "inj" => {
+                        let field = if key == "ext" { "extended" } else { "injected" };
+                        data.insert(field.to_string(), serde_json::Value::Bool(value == "1"));
+                    }
+                    "dbl" => {
+                        data.insert("double".to_string(), serde_json::Value::Bool(value == "1"));
+                    }
+                    "window" | "action" | "text" => {
+                        data.insert(key.to_string(), serde_json::Value::String(unescape_value(value)));
+                    }
+                    "win" => {
+                        data.insert("window".to_string(), serde_json::Value::String(unescape_value(value)));
+                    }
+                    "mon" => {
+                        data.insert("monitor".to_string(), serde_json::Value::String(unescape_value(value)));
+                    }
+                    "monx" | "mony" => {
+                        let field = if key == "monx" { "monitor_left" } else { "monitor_top" };
+                        if let Ok(num) = value.parse::<i64>() {
+                            data.insert(field.to_string(), serde_json::Value::from(num));
+                        }
+                    }
+                    "x" | "y" | "dx" | "dy" | "delta" | "duration_ms" | "ms" => {
                         if let Ok(num) = value.parse::<i64>() {
                             data.insert(key.to_string(), serde_json::Value::Number(serde_json::Number::from(num)));
                         }
@@ -167,6 +629,8 @@ impl MacroEvent {
                             "left" => 1,
                             "right" => 2,
                             "middle" => 3,
+                            "x1" => 4,
+                            "x2" => 5,
                             _ => 0,
                         };
                         data.insert(key.to_string(), serde_json::Value::Number(serde_json::Number::from(button_num)));
@@ -178,22 +642,53 @@ impl MacroEvent {
             }
         }
         
+        // Files without a delta only recorded a direction; treat it as one wheel notch
+        if matches!(event_type, EventType::MouseScroll) && !data.contains_key("delta") {
+            if let Some(dy) = data.get("dy").and_then(|dy| dy.as_i64()) {
+                data.insert("delta".to_string(), serde_json::Value::from(dy.signum() * WHEEL_DELTA));
+            }
+        }
+        
         Some(MacroEvent {
             timestamp,
             event_type,
             data: serde_json::Value::Object(data),
+            source,
+            seq,
         })
     }
     
+    pub fn get_source(&self) -> &str {
+        self.source.as_deref().unwrap_or("default")
+    }
+    
+    /// Wall-clock time of this event, given the recording's `epoch_ms` header.
+    /// None if the result is out of range.
+    pub fn absolute_time(&self, epoch_ms: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+        let offset_ms = (self.timestamp * 1000.0).round() as i64;
+        chrono::DateTime::from_timestamp_millis(epoch_ms.checked_add(offset_ms)?)
+    }
+    
+    /// True for the press that completes a double-click
+    pub fn is_double_click(&self) -> bool {
+        matches!(self.event_type, EventType::MouseDown)
+            && self.data.get("double").and_then(|d| d.as_bool()).unwrap_or(false)
+    }
+    
     pub fn is_mouse_move(&self) -> bool {
         matches!(self.event_type, EventType::MouseMove)
     }
     
+    /// One-line human-readable summary, as shown in the log
     pub fn get_description(&self) -> String {
         match self.event_type {
             EventType::KeyDown => {
                 if let Some(key) = self.data.get("key_name") {
-                    format!("Key Down: {}", key.as_str().unwrap_or("?"))
+                    let key = key.as_str().unwrap_or("?");
+                    match self.data.get("action").and_then(|a| a.as_str()) {
+                        Some(action) => format!("Key Down: {} ({})", key, action),
+                        None => format!("Key Down: {}", key),
+                    }
                 } else {
                     "Key Down".to_string()
                 }
@@ -221,17 +716,21 @@ impl MacroEvent {
                         1 => "Left",
                         2 => "Right", 
                         3 => "Middle",
+                        4 => "X1",
+                        5 => "X2",
                         _ => "Unknown",
                     })
                     .unwrap_or("Unknown");
                     
+                let kind = if self.is_double_click() { "Double-Click Down" } else { "Click Down" };
                 if let (Some(x), Some(y)) = (self.data.get("x"), self.data.get("y")) {
-                    format!("{} Click Down: ({}, {})", 
+                    format!("{} {}: ({}, {})", 
                         button,
+                        kind,
                         x.as_i64().unwrap_or(0), 
                         y.as_i64().unwrap_or(0))
                 } else {
-                    format!("{} Click Down", button)
+                    format!("{} {}", button, kind)
                 }
             }
             EventType::MouseUp => {
@@ -241,6 +740,8 @@ impl MacroEvent {
                         1 => "Left",
                         2 => "Right",
                         3 => "Middle", 
+                        4 => "X1",
+                        5 => "X2",
                         _ => "Unknown",
                     })
                     .unwrap_or("Unknown");
@@ -254,6 +755,25 @@ impl MacroEvent {
                     format!("{} Click Up", button)
                 }
             }
+            EventType::Click => {
+                let button = match self.data.get("button").and_then(|b| b.as_u64()) {
+                    Some(1) => "Left",
+                    Some(2) => "Right",
+                    Some(3) => "Middle",
+                    Some(4) => "X1",
+                    Some(5) => "X2",
+                    _ => "Unknown",
+                };
+                
+                if let (Some(x), Some(y)) = (self.data.get("x"), self.data.get("y")) {
+                    format!("{} Click: ({}, {})",
+                        button,
+                        x.as_i64().unwrap_or(0),
+                        y.as_i64().unwrap_or(0))
+                } else {
+                    format!("{} Click", button)
+                }
+            }
             EventType::MouseScroll => {
                 let delta = self.data.get("delta")
                     .and_then(|d| d.as_i64())
@@ -269,6 +789,528 @@ impl MacroEvent {
                     format!("Scroll {}", direction)
                 }
             }
+            EventType::MouseHScroll => {
+                let delta = self.data.get("delta")
+                    .and_then(|d| d.as_i64())
+                    .unwrap_or(0);
+                let direction = if delta > 0 { "Right" } else { "Left" };
+                
+                if let (Some(x), Some(y)) = (self.data.get("x"), self.data.get("y")) {
+                    format!("Scroll {}: ({}, {})",
+                        direction,
+                        x.as_i64().unwrap_or(0),
+                        y.as_i64().unwrap_or(0))
+                } else {
+                    format!("Scroll {}", direction)
+                }
+            }
+            EventType::FocusChange => {
+                let window = self.data.get("window")
+                    .and_then(|w| w.as_str())
+                    .unwrap_or("?");
+                format!("Focus: {}", window)
+            }
+            EventType::Hover => {
+                format!("Hover: ({}, {}) for {}ms",
+                    self.data.get("x").and_then(|x| x.as_i64()).unwrap_or(0),
+                    self.data.get("y").and_then(|y| y.as_i64()).unwrap_or(0),
+                    self.data.get("duration_ms").and_then(|d| d.as_i64()).unwrap_or(0))
+            }
+            EventType::TextInput => {
+                let text = self.data.get("text")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("");
+                format!("Text: \"{}\"", text)
+            }
+            EventType::Delay => {
+                format!("Wait {}ms", self.data.get("ms").and_then(|ms| ms.as_u64()).unwrap_or(0))
+            }
+        }
+    }
+}
+
+// Field values may contain the ';' separator, so it (and the escape char) get backslash codes
+pub fn escape_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(';', "\\s").replace('\n', "\\n")
+}
+
+pub fn unescape_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(';'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    
+    result
+}
+
+// Distinct track labels in first-seen order
+pub fn collect_tracks(events: &[MacroEvent]) -> Vec<String> {
+    let mut tracks: Vec<String> = Vec::new();
+    for event in events {
+        let source = event.get_source();
+        if !tracks.iter().any(|t| t == source) {
+            tracks.push(source.to_string());
+        }
+    }
+    tracks
+}
+
+// Shift the whole timeline so the earliest event sits at zero if any timestamp is negative.
+// Returns the offset that was added, or None when nothing needed fixing.
+pub fn normalize_timestamps(events: &mut [MacroEvent]) -> Option<f64> {
+    let earliest = events.iter()
+        .map(|event| event.timestamp)
+        .filter(|timestamp| timestamp.is_finite())
+        .fold(f64::INFINITY, f64::min);
+    if !earliest.is_finite() || earliest >= 0.0 {
+        return None;
+    }
+    
+    for event in events.iter_mut() {
+        event.timestamp -= earliest;
+    }
+    Some(-earliest)
+}
+
+// Shift the whole timeline so the earliest event sits at zero, whichever side of zero it was on.
+// Returns the offset that was subtracted, or None when the first event was already at zero.
+pub fn rebase_timestamps(events: &mut [MacroEvent]) -> Option<f64> {
+    let earliest = events.iter()
+        .map(|event| event.timestamp)
+        .filter(|timestamp| timestamp.is_finite())
+        .fold(f64::INFINITY, f64::min);
+    if !earliest.is_finite() || earliest == 0.0 {
+        return None;
+    }
+    
+    for event in events.iter_mut() {
+        event.timestamp -= earliest;
+    }
+    Some(earliest)
+}
+
+// Moves, hovers and waits only fill time; nothing the target application reacts to
+fn is_idle_filler(event: &MacroEvent) -> bool {
+    matches!(event.event_type, EventType::MouseMove | EventType::Hover | EventType::Delay)
+}
+
+// The recording without the dead air around it: filler after the last real action is dropped
+// and the first remaining event moves to zero. A recording of nothing but filler keeps it all.
+pub fn trim_dead_time(events: &[MacroEvent]) -> Vec<MacroEvent> {
+    let end = events.iter()
+        .rposition(|event| !is_idle_filler(event))
+        .map_or(events.len(), |last| last + 1);
+    let mut trimmed = events[..end].to_vec();
+    rebase_timestamps(&mut trimmed);
+    trimmed
+}
+
+// Chronological order with capture sequence as the tiebreaker for simultaneous events.
+// total_cmp rather than partial_cmp so a NaN that slipped in (e.g. from JSON) can't panic the sort
+pub fn sort_events(events: &mut [MacroEvent]) {
+    events.sort_by(|a, b| {
+        a.timestamp.total_cmp(&b.timestamp)
+            .then(a.seq.cmp(&b.seq))
+    });
+}
+
+// Identifies what a press/release acts on: the key's vk (or name for files without one) or the
+// mouse button. None for anything that isn't a press or release.
+fn held_input_id(event: &MacroEvent) -> Option<String> {
+    match event.event_type {
+        EventType::KeyDown | EventType::KeyUp => event.data.get("vk_code")
+            .and_then(|vk| vk.as_u64())
+            .map(|vk| format!("vk:{}", vk))
+            .or_else(|| event.data.get("key_name").and_then(|k| k.as_str()).map(|k| format!("key:{}", k))),
+        EventType::MouseDown | EventType::MouseUp => event.data.get("button")
+            .and_then(|b| b.as_u64())
+            .map(|b| format!("button:{}", b)),
+        _ => None,
+    }
+}
+
+// Update `held` (presses not yet released, in press order) for one more event
+pub fn track_held(held: &mut Vec<MacroEvent>, event: &MacroEvent) {
+    let id = match held_input_id(event) {
+        Some(id) => id,
+        None => return,
+    };
+    match event.event_type {
+        EventType::KeyDown | EventType::MouseDown => {
+            if !held.iter().any(|press| held_input_id(press).as_ref() == Some(&id)) {
+                held.push(event.clone());
+            }
+        }
+        _ => held.retain(|press| held_input_id(press).as_ref() != Some(&id)),
+    }
+}
+
+// Indices of presses never released and of releases with no press before them
+pub fn unbalanced_inputs(events: &[MacroEvent]) -> (Vec<usize>, Vec<usize>) {
+    let mut held: Vec<(String, usize)> = Vec::new();
+    let mut unmatched_releases = Vec::new();
+    
+    for (index, event) in events.iter().enumerate() {
+        let id = match held_input_id(event) {
+            Some(id) => id,
+            None => continue,
+        };
+        match event.event_type {
+            EventType::KeyDown | EventType::MouseDown => {
+                // Auto-repeat sends extra downs for a held key; only the first one counts
+                if !held.iter().any(|(held_id, _)| *held_id == id) {
+                    held.push((id, index));
+                }
+            }
+            _ => match held.iter().position(|(held_id, _)| *held_id == id) {
+                Some(position) => {
+                    held.remove(position);
+                }
+                None => unmatched_releases.push(index),
+            },
         }
     }
+    
+    (held.into_iter().map(|(_, index)| index).collect(), unmatched_releases)
+}
+
+// Key and button presses still down after the last event, in the order they were pressed
+pub fn held_inputs(events: &[MacroEvent]) -> Vec<MacroEvent> {
+    let mut held = Vec::new();
+    for event in events {
+        track_held(&mut held, event);
+    }
+    held
+}
+
+// The KeyUp/MouseUp that lets go of `press`
+pub fn release_for(press: &MacroEvent, timestamp: f64) -> MacroEvent {
+    let mut release = press.clone();
+    release.timestamp = timestamp;
+    release.event_type = match press.event_type {
+        EventType::MouseDown => EventType::MouseUp,
+        _ => EventType::KeyUp,
+    };
+    if let Some(data) = release.data.as_object_mut() {
+        data.remove("action");
+        data.remove("window");
+    }
+    release
+}
+
+// `appended` placed `gap` seconds after the last event of `events`. Anything still held at the
+// end of `events` is released at the seam, and the appended events are renumbered after it so
+// events sharing a timestamp keep their file order.
+pub fn append_events(events: &[MacroEvent], appended: &[MacroEvent], gap: f64) -> Vec<MacroEvent> {
+    let end = events.iter().map(|event| event.timestamp).fold(0.0, f64::max);
+    let offset = if events.is_empty() { 0.0 } else { end + gap.max(0.0) };
+    let mut next_seq = events.iter().map(|event| event.seq).max().unwrap_or(0) + 1;
+    
+    let mut combined = events.to_vec();
+    for press in held_inputs(events) {
+        let mut release = release_for(&press, end);
+        release.seq = next_seq;
+        next_seq += 1;
+        combined.push(release);
+    }
+    
+    let mut appended = appended.to_vec();
+    sort_events(&mut appended);
+    for mut event in appended {
+        event.timestamp += offset;
+        event.seq = next_seq;
+        next_seq += 1;
+        combined.push(event);
+    }
+    
+    sort_events(&mut combined);
+    combined
+}
+
+// A release at `timestamp` for each press in `presses`, numbered on from the last seq in `events`
+// so they sort after everything already there
+pub fn releases_after<'a>(events: &[MacroEvent], presses: impl Iterator<Item = &'a MacroEvent>, timestamp: f64) -> Vec<MacroEvent> {
+    let first_seq = events.iter().map(|event| event.seq).max().unwrap_or(0) + 1;
+    presses.zip(first_seq..)
+        .map(|(press, seq)| {
+            let mut release = release_for(press, timestamp);
+            release.seq = seq;
+            release
+        })
+        .collect()
+}
+
+// Events before `at` and from `at` on, the second half rebased to start at zero. Keys and
+// buttons held across the cut are released at the end of the first half and pressed again at
+// the start of the second, so each half replays on its own without leaving anything stuck down.
+pub fn split_events(events: &[MacroEvent], at: f64) -> (Vec<MacroEvent>, Vec<MacroEvent>) {
+    let mut sorted = events.to_vec();
+    sort_events(&mut sorted);
+    let cut = sorted.partition_point(|event| event.timestamp < at);
+    let (before, after) = sorted.split_at(cut);
+    let held = held_inputs(before);
+    
+    let mut first = before.to_vec();
+    first.extend(releases_after(before, held.iter(), at));
+    
+    let mut second: Vec<MacroEvent> = held.into_iter()
+        .map(|mut press| {
+            press.timestamp = at;
+            press
+        })
+        .chain(after.iter().cloned())
+        .collect();
+    for (seq, event) in second.iter_mut().enumerate() {
+        event.timestamp = (event.timestamp - at).max(0.0);
+        event.seq = seq as u64 + 1;
+    }
+    
+    (first, second)
+}
+
+// Fold runs of adjacent wheel events at the same point, each within `window` seconds of the
+// previous one, into a single scroll carrying the summed delta
+pub fn merge_scrolls(events: &[MacroEvent], window: f64) -> Vec<MacroEvent> {
+    let position = |event: &MacroEvent| (
+        event.data.get("x").and_then(|x| x.as_i64()),
+        event.data.get("y").and_then(|y| y.as_i64()),
+    );
+    let delta = |event: &MacroEvent| event.data.get("delta").and_then(|d| d.as_i64());
+    
+    let mut merged: Vec<MacroEvent> = Vec::with_capacity(events.len());
+    let mut run_end: Option<f64> = None;
+    
+    for event in events {
+        let mergeable = matches!(event.event_type, EventType::MouseScroll) && delta(event).is_some();
+        
+        if let (true, Some(last_timestamp), Some(previous)) = (mergeable, run_end, merged.last_mut()) {
+            if position(previous) == position(event) && event.timestamp - last_timestamp <= window {
+                let total = delta(previous).unwrap_or(0) + delta(event).unwrap_or(0);
+                previous.data["delta"] = serde_json::Value::from(total);
+                run_end = Some(event.timestamp);
+                continue;
+            }
+        }
+        
+        run_end = if mergeable { Some(event.timestamp) } else { None };
+        merged.push(event.clone());
+    }
+    
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn key(timestamp: f64, key_name: &str) -> MacroEvent {
+        MacroEvent::with_data(timestamp, EventType::KeyDown, json!({ "key_name": key_name }))
+    }
+    
+    fn key_names(events: &[MacroEvent]) -> Vec<&str> {
+        events.iter().filter_map(|event| event.data.get("key_name")?.as_str()).collect()
+    }
+    
+    // What saving and loading a file does to the events
+    fn round_trip(events: &[MacroEvent]) -> Vec<MacroEvent> {
+        round_trip_with(events, false)
+    }
+    
+    fn round_trip_with(events: &[MacroEvent], compact_paths: bool) -> Vec<MacroEvent> {
+        let content = mcr_lines(events, compact_paths).join("\n");
+        let (_, mut loaded) = parse_mcr(&content);
+        sort_events(&mut loaded);
+        loaded
+    }
+    
+    // Everything a loaded event carries, to compare events from different saves
+    fn described(events: &[MacroEvent]) -> Vec<(String, f64, u64, Option<String>)> {
+        events.iter()
+            .map(|event| (event.to_mcr_line(), event.timestamp, event.seq, event.source.clone()))
+            .collect()
+    }
+    
+    // A recorded move on the primary monitor
+    fn path_point(timestamp: f64, seq: u64, x: i64) -> MacroEvent {
+        let mut event = MacroEvent::with_data(timestamp, EventType::MouseMove, json!({
+            "x": x, "y": x * 2, "monitor": "DISPLAY1", "monitor_left": -1920, "monitor_top": 0,
+        }));
+        event.source = Some(TRACK_MOUSE.to_string());
+        event.seq = seq;
+        event
+    }
+    
+    #[test]
+    fn simultaneous_events_keep_capture_order() {
+        let ctrl = MacroEvent { seq: 1, ..key(1.0, "ctrl") };
+        let c = MacroEvent { seq: 2, ..key(1.0, "c") };
+        
+        assert_eq!(key_names(&round_trip(&[c, ctrl])), vec!["ctrl", "c"]);
+    }
+    
+    #[test]
+    fn key_flags_survive_a_round_trip() {
+        let flagged = MacroEvent::with_data(0.5, EventType::KeyDown, json!({
+            "key_name": "right", "vk_code": 0x27, "scan_code": 0x4D, "extended": true, "injected": true,
+        }));
+        let line = flagged.to_mcr_line();
+        assert!(line.contains("ext=1") && line.contains("inj=1"));
+        
+        let loaded = MacroEvent::from_mcr_line(&line).unwrap();
+        assert_eq!(loaded.data["extended"], json!(true));
+        assert_eq!(loaded.data["injected"], json!(true));
+        assert_eq!(loaded.data["vk_code"], json!(0x27));
+        assert_eq!(loaded.data["scan_code"], json!(0x4D));
+        
+        // Unset flags aren't written, and read back as absent
+        let plain = MacroEvent::from_mcr_line(&key(0.5, "a").to_mcr_line()).unwrap();
+        assert!(plain.data.get("extended").is_none() && plain.data.get("injected").is_none());
+    }
+    
+    #[test]
+    fn scrolls_within_the_window_merge() {
+        let scroll = |timestamp, delta| MacroEvent::with_data(timestamp, EventType::MouseScroll, json!({ "x": 5, "y": 5, "delta": delta }));
+        let events = [scroll(0.0, 120), scroll(0.05, 120), scroll(0.1, 120), scroll(1.0, 120)];
+        
+        let merged = merge_scrolls(&events, 0.1);
+        
+        let deltas: Vec<_> = merged.iter().map(|event| event.data["delta"].as_i64()).collect();
+        assert_eq!(deltas, vec![Some(360), Some(120)]);
+        assert_eq!(merged[0].timestamp, 0.0);
+    }
+    
+    #[test]
+    fn compact_paths_load_the_same_as_plain_lines() {
+        let mut events = vec![MacroEvent { seq: 1, source: Some(TRACK_KEYBOARD.to_string()), ..key(0.0, "a") }];
+        events.extend((0..5).map(|i| path_point(0.1 + i as f64 * 0.0125, 2 + i, 100 + i as i64)));
+        events[1].data["window"] = json!("Notes; draft=1");
+        events.push(MacroEvent { seq: 7, ..key(0.2, "b") });
+        
+        let lines = mcr_lines(&events, true);
+        assert_eq!(lines.len(), 3);
+        assert!(is_path_line(&lines[1]));
+        for field in ["win=", "mon=DISPLAY1", "monx=-1920", "mony=0", "src=mouse", "seq=2"] {
+            assert!(lines[1].contains(field), "{} missing from {}", field, lines[1]);
+        }
+        
+        let compact = round_trip_with(&events, true);
+        assert_eq!(described(&compact), described(&round_trip_with(&events, false)));
+        // The window tag stays on the first point only
+        assert_eq!(compact[1].data["window"], json!("Notes; draft=1"));
+        assert!(compact[2].data.get("window").is_none());
+        assert_eq!(compact.iter().map(|event| event.seq).collect::<Vec<_>>(), (1..=7).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    fn path_runs_stop_where_a_line_couldnt_rebuild_the_moves() {
+        let run = |events: &[MacroEvent]| path_run_len(events);
+        let moves = |count: u64| -> Vec<MacroEvent> {
+            (0..count).map(|i| path_point(i as f64 * 0.01, 1 + i, i as i64)).collect()
+        };
+        assert_eq!(run(&moves(5)), 5);
+        
+        let mut gap = moves(5);
+        gap[3].seq += 1;
+        assert_eq!(run(&gap), 3);
+        
+        let mut other_monitor = moves(5);
+        other_monitor[2].data["monitor"] = json!("DISPLAY2");
+        assert_eq!(run(&other_monitor), 2);
+        
+        let mut out_of_order = moves(5);
+        out_of_order[2].timestamp = 0.001;
+        assert_eq!(run(&out_of_order), 2);
+        
+        let mut retagged = moves(5);
+        retagged[4].data["window"] = json!("Other");
+        assert_eq!(run(&retagged), 4);
+        
+        // Whatever breaks a run, the file still loads as the same events
+        for events in [gap, other_monitor, out_of_order, retagged] {
+            let mut sorted = events.clone();
+            sort_events(&mut sorted);
+            assert_eq!(described(&round_trip_with(&events, true)), described(&sorted));
+        }
+    }
+    
+    #[test]
+    fn files_without_a_path_line_are_declared_v2() {
+        let moves = [path_point(0.0, 1, 0), path_point(0.01, 2, 1)];
+        
+        let short = mcr_lines(&moves, true);
+        assert!(!short.iter().any(|line| is_path_line(line)));
+        assert_eq!(mcr_header(&short), format!("# MCR v{}", MCR_BASE_VERSION));
+        
+        let long = mcr_lines(&[moves[0].clone(), moves[1].clone(), path_point(0.02, 3, 2)], true);
+        assert_eq!(mcr_header(&long), format!("# MCR v{}", MCR_FORMAT_VERSION));
+        assert_eq!(parse_mcr(&format!("{}\n{}", mcr_header(&long), long.join("\n"))).0, MCR_FORMAT_VERSION);
+    }
+    
+    
+    fn numbered(mut events: Vec<MacroEvent>) -> Vec<MacroEvent> {
+        for (seq, event) in events.iter_mut().enumerate() {
+            event.seq = seq as u64 + 1;
+        }
+        events
+    }
+    
+    fn key_up(timestamp: f64, key_name: &str) -> MacroEvent {
+        MacroEvent::with_data(timestamp, EventType::KeyUp, json!({ "key_name": key_name }))
+    }
+    
+    // (type, key, timestamp, seq) for each event, in order
+    fn key_steps(events: &[MacroEvent]) -> Vec<(EventType, &str, f64, u64)> {
+        events.iter()
+            .map(|event| (event.event_type.clone(), event.data["key_name"].as_str().unwrap(), event.timestamp, event.seq))
+            .collect()
+    }
+    
+    #[test]
+    fn splitting_releases_held_keys_and_presses_them_again() {
+        let events = numbered(vec![
+            key(0.25, "Shift"), key(0.5, "A"), key_up(0.75, "A"), key(1.5, "B"), key_up(1.75, "Shift"),
+        ]);
+        let (first, second) = split_events(&events, 1.0);
+        
+        assert_eq!(key_steps(&first), vec![
+            (EventType::KeyDown, "Shift", 0.25, 1),
+            (EventType::KeyDown, "A", 0.5, 2),
+            (EventType::KeyUp, "A", 0.75, 3),
+            (EventType::KeyUp, "Shift", 1.0, 4),
+        ]);
+        assert_eq!(key_steps(&second), vec![
+            (EventType::KeyDown, "Shift", 0.0, 1),
+            (EventType::KeyDown, "B", 0.5, 2),
+            (EventType::KeyUp, "Shift", 0.75, 3),
+        ]);
+    }
+    
+    
+    #[test]
+    fn appending_releases_held_keys_at_the_seam_and_numbers_on() {
+        let events = numbered(vec![key(0.25, "Ctrl"), key(0.5, "A"), key_up(0.75, "A")]);
+        // Out of order and numbered from 1 again, as a second file would be
+        let appended = numbered(vec![key_up(0.5, "B"), key(0.25, "B")]);
+        
+        assert_eq!(key_steps(&append_events(&events, &appended, 1.0)), vec![
+            (EventType::KeyDown, "Ctrl", 0.25, 1),
+            (EventType::KeyDown, "A", 0.5, 2),
+            (EventType::KeyUp, "A", 0.75, 3),
+            (EventType::KeyUp, "Ctrl", 0.75, 4),
+            (EventType::KeyDown, "B", 2.0, 5),
+            (EventType::KeyUp, "B", 2.25, 6),
+        ]);
+    }
 }
@@ -0,0 +1,298 @@
+use crate::events::{MacroEvent, EventType, WHEEL_DELTA};
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Ahk,
+    Json,
+    // Documented, versioned JSON for other tools (see `portable`)
+    PortableJson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Ahk => "AutoHotkey script",
+            ExportFormat::Json => "JSON",
+            ExportFormat::PortableJson => "Portable JSON",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+    
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ahk => "ahk",
+            ExportFormat::Json | ExportFormat::PortableJson => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+// New output formats implement this and get registered in `exporter_for`
+pub trait Exporter {
+    fn export(&self, events: &[MacroEvent], writer: &mut dyn Write) -> std::io::Result<()>;
+}
+
+pub fn exporter_for(format: ExportFormat) -> Box<dyn Exporter> {
+    match format {
+        ExportFormat::Ahk => Box::new(AhkExporter),
+        ExportFormat::Json => Box::new(JsonExporter),
+        ExportFormat::PortableJson => Box::new(PortableJsonExporter),
+        ExportFormat::Csv => Box::new(CsvExporter),
+    }
+}
+
+// Shared by the recorder and the player, which each export their own events
+pub fn export_file(events: &[MacroEvent], format: ExportFormat, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let file = std::fs::File::create(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    exporter_for(format).export(events, &mut writer)?;
+    writer.flush()?;
+    
+    log::info!("Exported {} events to {} as {}", events.len(), path, format.name());
+    Ok(())
+}
+
+// Quote a string for an AutoHotkey v2 double-quoted literal
+fn ahk_quote(text: &str) -> String {
+    text.replace('`', "``").replace('"', "`\"").replace('\n', "`n")
+}
+
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn export(&self, events: &[MacroEvent], writer: &mut dyn Write) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(&mut *writer, events)?;
+        writeln!(writer)
+    }
+}
+
+// The tool-neutral JSON schema from crate::portable
+pub struct PortableJsonExporter;
+
+impl Exporter for PortableJsonExporter {
+    fn export(&self, events: &[MacroEvent], writer: &mut dyn Write) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(&mut *writer, &crate::portable::to_portable(events))?;
+        writeln!(writer)
+    }
+}
+
+// Quote a CSV field if it holds a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// One row per event for spreadsheets; columns that don't apply to an event stay empty.
+// Export only: nothing reads it back.
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn export(&self, events: &[MacroEvent], writer: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(writer, "index,timestamp,type,x,y,button,key,delta")?;
+        for (index, event) in events.iter().enumerate() {
+            let number = |field: &str| event.data.get(field).and_then(|v| v.as_i64()).map(|v| v.to_string()).unwrap_or_default();
+            let button = match event.data.get("button").and_then(|b| b.as_u64()) {
+                Some(1) => "left",
+                Some(2) => "right",
+                Some(3) => "middle",
+                Some(4) => "x1",
+                Some(5) => "x2",
+                _ => "",
+            };
+            let key = event.data.get("key_name").and_then(|k| k.as_str()).unwrap_or("");
+            writeln!(writer, "{},{:.6},{},{},{},{},{},{}",
+                index, event.timestamp, event.event_type, number("x"), number("y"), button, csv_field(key), number("delta"))?;
+        }
+        Ok(())
+    }
+}
+
+// AutoHotkey v2 script. Not everything carries over exactly:
+// - scrolls become whole wheel notches (delta / 120, at least one), so high-resolution
+//   touchpad scrolling is rounded
+// - focus changes only become comments; the script doesn't activate windows
+// - hovers are plain MouseMoves, their dwell being the Sleep before the next event
+// - a double-click's second press is an ordinary press; the timing between the presses
+//   is what makes it a double-click, as when it was recorded
+pub struct AhkExporter;
+
+impl AhkExporter {
+    // AHK name for a recorded key name, for use inside Send's braces
+    fn key_name(key: &str) -> String {
+        let name = match key {
+            "space" => "Space",
+            "enter" => "Enter",
+            crate::events::NUMPAD_ENTER_NAME => "NumpadEnter",
+            "backspace" => "Backspace",
+            "tab" => "Tab",
+            "shift" => "Shift",
+            "ctrl" => "Ctrl",
+            "alt" => "Alt",
+            "esc" => "Esc",
+            "left" => "Left",
+            "up" => "Up",
+            "right" => "Right",
+            "down" => "Down",
+            "delete" => "Delete",
+            "insert" => "Insert",
+            "home" => "Home",
+            "end" => "End",
+            "page_up" => "PgUp",
+            "page_down" => "PgDn",
+            "apps" | "menu" => "AppsKey",
+            "sleep" => "Sleep",
+            "num*" => "NumpadMult",
+            "num+" => "NumpadAdd",
+            "num-" => "NumpadSub",
+            "num." => "NumpadDot",
+            "num/" => "NumpadDiv",
+            "numlock" => "NumLock",
+            _ => {
+                if let Some(digit) = key.strip_prefix("num").filter(|d| d.len() == 1 && d.chars().all(|c| c.is_ascii_digit())) {
+                    return format!("Numpad{}", digit);
+                }
+                if let Some(n) = key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    return format!("F{}", n);
+                }
+                // AHK takes unnamed keys by virtual key code in hex
+                if let Some(vk) = key.strip_prefix("vk_").and_then(|vk| vk.parse::<u32>().ok()) {
+                    return format!("vk{:02X}", vk);
+                }
+                key
+            }
+        };
+        name.to_string()
+    }
+    
+    fn button_name(event: &MacroEvent) -> &'static str {
+        match event.data.get("button").and_then(|b| b.as_u64()) {
+            Some(2) => "Right",
+            Some(3) => "Middle",
+            Some(4) => "X1",
+            Some(5) => "X2",
+            _ => "Left",
+        }
+    }
+    
+    fn coords(event: &MacroEvent) -> (i64, i64) {
+        (
+            event.data.get("x").and_then(|x| x.as_i64()).unwrap_or(0),
+            event.data.get("y").and_then(|y| y.as_i64()).unwrap_or(0),
+        )
+    }
+}
+
+impl Exporter for AhkExporter {
+    fn export(&self, events: &[MacroEvent], writer: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(writer, "; Generated by Rust Macro Recorder")?;
+        writeln!(writer, "#Requires AutoHotkey v2.0")?;
+        writeln!(writer, "CoordMode \"Mouse\", \"Screen\"")?;
+        writeln!(writer)?;
+        
+        let mut last_timestamp = events.first().map(|e| e.timestamp).unwrap_or(0.0);
+        for (index, event) in events.iter().enumerate() {
+            let gap_ms = ((event.timestamp - last_timestamp) * 1000.0).round() as i64;
+            if gap_ms > 0 {
+                writeln!(writer, "Sleep {}", gap_ms)?;
+            }
+            last_timestamp = event.timestamp;
+            
+            match event.event_type {
+                EventType::KeyDown | EventType::KeyUp => {
+                    if let Some(key) = event.data.get("key_name").and_then(|k| k.as_str()) {
+                        let state = if matches!(event.event_type, EventType::KeyDown) { "down" } else { "up" };
+                        writeln!(writer, "Send \"{{{} {}}}\"", ahk_quote(&Self::key_name(key)), state)?;
+                    }
+                }
+                EventType::MouseMove => {
+                    let (x, y) = Self::coords(event);
+                    writeln!(writer, "MouseMove {}, {}, 0", x, y)?;
+                }
+                EventType::MouseDown | EventType::MouseUp => {
+                    let (x, y) = Self::coords(event);
+                    let state = if matches!(event.event_type, EventType::MouseDown) { "D" } else { "U" };
+                    writeln!(writer, "MouseClick \"{}\", {}, {}, 1, 0, \"{}\"", Self::button_name(event), x, y, state)?;
+                }
+                EventType::MouseScroll | EventType::MouseHScroll => {
+                    let delta = event.data.get("delta").and_then(|d| d.as_i64()).unwrap_or(0);
+                    let direction = match (&event.event_type, delta > 0) {
+                        (EventType::MouseHScroll, true) => "WheelRight",
+                        (EventType::MouseHScroll, false) => "WheelLeft",
+                        (_, true) => "WheelUp",
+                        (_, false) => "WheelDown",
+                    };
+                    let notches = (delta.abs() / WHEEL_DELTA).max(1);
+                    writeln!(writer, "MouseClick \"{}\", , , {}", direction, notches)?;
+                }
+                EventType::FocusChange => {
+                    let window = event.data.get("window").and_then(|w| w.as_str()).unwrap_or("");
+                    writeln!(writer, "; Focus: {}", window)?;
+                }
+                EventType::Click => {
+                    let (x, y) = Self::coords(event);
+                    writeln!(writer, "MouseClick \"{}\", {}, {}", Self::button_name(event), x, y)?;
+                }
+                EventType::Hover => {
+                    // The dwell itself comes out as the Sleep before the next event
+                    let (x, y) = Self::coords(event);
+                    writeln!(writer, "MouseMove {}, {}, 0", x, y)?;
+                }
+                EventType::Delay => {
+                    // The gap to the next event already counts toward the wait; Sleep the rest
+                    let ms = event.data.get("ms").and_then(|ms| ms.as_i64()).unwrap_or(0);
+                    let gap_ms = events.get(index + 1)
+                        .map(|next| ((next.timestamp - event.timestamp) * 1000.0).round() as i64)
+                        .unwrap_or(0);
+                    if ms > gap_ms {
+                        writeln!(writer, "Sleep {}", ms - gap_ms)?;
+                    }
+                }
+                EventType::TextInput => {
+                    let text = event.data.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                    writeln!(writer, "SendText \"{}\"", ahk_quote(text))?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn export_to_string(format: ExportFormat, events: &[MacroEvent]) -> String {
+        let mut output = Vec::new();
+        exporter_for(format).export(events, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+    
+    #[test]
+    fn each_format_gets_its_own_exporter() {
+        let events = vec![
+            MacroEvent::with_data(0.0, EventType::MouseMove, json!({ "x": 10, "y": 20 })),
+            MacroEvent::with_data(0.25, EventType::KeyDown, json!({ "key_name": "a" })),
+        ];
+        
+        let ahk = export_to_string(ExportFormat::Ahk, &events);
+        assert!(ahk.contains("#Requires AutoHotkey v2.0"));
+        assert!(ahk.contains("MouseMove 10, 20, 0\nSleep 250\nSend \"{a down}\""));
+        
+        let json: Vec<MacroEvent> = serde_json::from_str(&export_to_string(ExportFormat::Json, &events)).unwrap();
+        assert_eq!(json.len(), 2);
+        
+        let portable: serde_json::Value = serde_json::from_str(&export_to_string(ExportFormat::PortableJson, &events)).unwrap();
+        assert!(crate::portable::is_portable(&portable));
+        
+        let csv = export_to_string(ExportFormat::Csv, &events);
+        assert!(csv.starts_with("index,timestamp,type,x,y,button,key,delta\n0,0.000000,"));
+        assert_eq!(csv.lines().count(), 3);
+    }
+}
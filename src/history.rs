@@ -0,0 +1,125 @@
+use crate::events::MacroEvent;
+use std::collections::VecDeque;
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+// A single reversible change to an event list
+#[derive(Debug, Clone)]
+pub enum EditOp {
+    Insert { index: usize, event: MacroEvent },
+    Remove { index: usize, event: MacroEvent },
+    Update { index: usize, before: MacroEvent, after: MacroEvent },
+    // Exchanges two rows but leaves timestamps and seq in place, so the list stays in time order
+    Swap { a: usize, b: usize },
+    ReplaceAll { before: Vec<MacroEvent>, after: Vec<MacroEvent> },
+}
+
+impl EditOp {
+    pub fn apply(&self, events: &mut Vec<MacroEvent>) {
+        match self {
+            EditOp::Insert { index, event } => {
+                events.insert((*index).min(events.len()), event.clone());
+            }
+            EditOp::Remove { index, .. } => {
+                if *index < events.len() {
+                    events.remove(*index);
+                }
+            }
+            EditOp::Update { index, after, .. } => {
+                if let Some(slot) = events.get_mut(*index) {
+                    *slot = after.clone();
+                }
+            }
+            EditOp::Swap { a, b } => {
+                if *a < events.len() && *b < events.len() {
+                    events.swap(*a, *b);
+                    let (first, second) = ((*a).min(*b), (*a).max(*b));
+                    let (head, tail) = events.split_at_mut(second);
+                    std::mem::swap(&mut head[first].timestamp, &mut tail[0].timestamp);
+                    std::mem::swap(&mut head[first].seq, &mut tail[0].seq);
+                }
+            }
+            EditOp::ReplaceAll { after, .. } => {
+                *events = after.clone();
+            }
+        }
+    }
+    
+    pub fn inverse(&self) -> EditOp {
+        match self {
+            EditOp::Insert { index, event } => EditOp::Remove { index: *index, event: event.clone() },
+            EditOp::Remove { index, event } => EditOp::Insert { index: *index, event: event.clone() },
+            EditOp::Update { index, before, after } => EditOp::Update {
+                index: *index,
+                before: after.clone(),
+                after: before.clone(),
+            },
+            EditOp::Swap { a, b } => EditOp::Swap { a: *a, b: *b },
+            EditOp::ReplaceAll { before, after } => EditOp::ReplaceAll {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+}
+
+// Bounded undo/redo stacks; recording a new edit discards the redo branch
+pub struct EditHistory {
+    undo_stack: VecDeque<EditOp>,
+    redo_stack: Vec<EditOp>,
+    max_depth: usize,
+}
+
+impl EditHistory {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            max_depth,
+        }
+    }
+    
+    pub fn record(&mut self, op: EditOp) {
+        self.undo_stack.push_back(op);
+        self.redo_stack.clear();
+        
+        while self.undo_stack.len() > self.max_depth {
+            self.undo_stack.pop_front();
+        }
+    }
+    
+    pub fn undo(&mut self, events: &mut Vec<MacroEvent>) -> bool {
+        match self.undo_stack.pop_back() {
+            Some(op) => {
+                op.inverse().apply(events);
+                self.redo_stack.push(op);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn redo(&mut self, events: &mut Vec<MacroEvent>) -> bool {
+        match self.redo_stack.pop() {
+            Some(op) => {
+                op.apply(events);
+                self.undo_stack.push_back(op);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+    
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+    
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicIsize, AtomicU32, AtomicU64, Ordering};
 use windows::{
     Win32::{
         Foundation::*,
@@ -7,37 +8,192 @@ use windows::{
         System::LibraryLoader::GetModuleHandleW,
     },
 };
-use crate::events::{MacroEvent, EventType};
+use crate::events::{MacroEvent, EventType, NUMPAD_ENTER_NAME, NUMPAD_KEY_NAMES, TRACK_KEYBOARD, TRACK_MOUSE};
+use crate::hotkeys::{Hotkey, HotkeyConfig};
+use crate::input_queue::InputQueue;
+use crate::target::{self, RecordTarget};
 
 type HookCallback = Box<dyn Fn(MacroEvent) + Send + Sync>;
+type SharedCallback = Arc<dyn Fn(MacroEvent) + Send + Sync>;
 
-static GLOBAL_HOOKS: OnceLock<Arc<Mutex<Option<GlobalHooks>>>> = OnceLock::new();
+// Ownership: the hook procs are plain `extern "system"` fns, so they reach the recording state
+// through this static. `install()` puts a fresh `Arc<HookState>` here and `uninstall()` takes it
+// out again; the `GlobalHooks` that installed it keeps only a second `Arc` to recognise it by.
+// Nothing is ever bit-copied, so the callback has exactly one owner chain and drops once.
+static GLOBAL_HOOKS: OnceLock<Mutex<Option<Arc<HookState>>>> = OnceLock::new();
+
+// While set, real (non-injected) input is swallowed so it can't collide with playback
+static BLOCK_USER_INPUT: AtomicBool = AtomicBool::new(false);
+static KILL_SWITCH_PRESSED: AtomicBool = AtomicBool::new(false);
+
+// Single key that starts/stops recording (0 = none); swallowed by the hook, so never recorded
+static RECORD_TOGGLE_VK: AtomicU32 = AtomicU32::new(0);
+static RECORD_TOGGLE_PRESSED: AtomicBool = AtomicBool::new(false);
+// Record/pause/stop/emergency/play chords (Hotkey::to_bits) that are kept out of recordings
+static HOTKEY_BINDINGS: [AtomicU32; 5] = [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)];
+const STOP_SLOT: usize = 2;
+const EMERGENCY_SLOT: usize = 3;
+// Key whose chord press was dropped, so its release is dropped too even if the modifier is already up
+static SUPPRESSED_HOTKEY_VK: AtomicU32 = AtomicU32::new(0);
+// Foreground window title attached to the last recorded event; titles are only stored when they change
+static LAST_EVENT_WINDOW: Mutex<String> = Mutex::new(String::new());
+// When set, only input aimed at this window or process is recorded
+static RECORD_TARGET: Mutex<Option<RecordTarget>> = Mutex::new(None);
+// Last foreground window checked against the target and whether it matched, so the
+// process lookup doesn't run on every mouse move
+static TARGET_MATCH_CACHE: Mutex<Option<(isize, bool)>> = Mutex::new(None);
+
+pub const DEFAULT_MIN_MOVE_INTERVAL_MS: u64 = 8;
+pub const DEFAULT_MIN_MOVE_DISTANCE_PX: u32 = 0;
+
+// Sentinel for "no move recorded yet" in last_move_ms; also set after a click so the next move is always kept
+const NO_MOVE: u64 = u64::MAX;
 
 pub struct GlobalHooks {
     keyboard_hook: Option<HHOOK>,
     mouse_hook: Option<HHOOK>,
-    callback: Option<HookCallback>,
+    callback: Option<SharedCallback>,
+    // Moves closer together than this are dropped; clicks, keys and scrolls never are
+    min_move_interval_ms: u64,
+    // Moves that land closer than this to the last recorded one are dropped as jitter
+    min_move_distance_px: u32,
+    // The state this instance put into GLOBAL_HOOKS, while installed
+    installed: Option<Arc<HookState>>,
+    // Builds events from the queued input and runs the callback, off the hook thread
+    delivery_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+// Input as the hook proc saw it; the delivery thread turns it into a MacroEvent
+enum RawInput {
+    Text { text: String },
+    Key { vk_code: u32, scan_code: u32, flags: u32, is_key_down: bool },
+    Mouse { event_type: EventType, x: i32, y: i32, button: Option<u32>, delta: Option<i32>, double: bool },
+}
+
+struct CapturedInput {
+    timestamp: f64,
+    foreground: isize,
+    input: RawInput,
+}
+
+// Mouse moves beyond this many undelivered inputs are dropped, oldest first
+const INPUT_QUEUE_CAPACITY: usize = 1024;
+
+// Everything the hook procs need for one install; rebuilt from the GlobalHooks settings each time
+struct HookState {
+    callback: Option<SharedCallback>,
+    // Hook thread -> delivery thread
+    queue: InputQueue<CapturedInput>,
     start_time: std::time::Instant,
+    next_seq: AtomicU64,
+    last_foreground: AtomicIsize,
+    min_move_interval_ms: u64,
+    last_move_ms: AtomicU64,
+    min_move_distance_px: u32,
+    last_move_x: AtomicI32,
+    last_move_y: AtomicI32,
+    // Key whose press was recorded as TextInput, so its release is left out as well
+    text_input_vk: AtomicU32,
+    // Button, time and position of the last press, to spot the second press of a double-click
+    last_press: Mutex<Option<(u32, u64, i32, i32)>>,
+}
+
+impl Default for GlobalHooks {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GlobalHooks {
+    /// Hooks that are not installed yet; configure them, then call `install`
     pub fn new() -> Self {
         Self {
             keyboard_hook: None,
             mouse_hook: None,
             callback: None,
-            start_time: std::time::Instant::now(),
+            min_move_interval_ms: DEFAULT_MIN_MOVE_INTERVAL_MS,
+            min_move_distance_px: DEFAULT_MIN_MOVE_DISTANCE_PX,
+            installed: None,
+            delivery_thread: None,
         }
     }
     
+    /// Called from the hook procs with every captured event. Takes effect on the next install()
     pub fn set_callback(&mut self, callback: HookCallback) {
-        self.callback = Some(callback);
+        self.callback = Some(Arc::from(callback));
+    }
+    
+    pub fn set_block_user_input(&mut self, block: bool) {
+        BLOCK_USER_INPUT.store(block, Ordering::SeqCst);
+        KILL_SWITCH_PRESSED.store(false, Ordering::SeqCst);
+    }
+    
+    /// True once per kill-switch press seen while input was blocked
+    pub fn take_kill_switch(&self) -> bool {
+        KILL_SWITCH_PRESSED.swap(false, Ordering::SeqCst)
+    }
+    
+    pub fn set_record_toggle_key(&mut self, vk_code: Option<u32>) {
+        RECORD_TOGGLE_VK.store(vk_code.unwrap_or(0), Ordering::SeqCst);
+        RECORD_TOGGLE_PRESSED.store(false, Ordering::SeqCst);
+    }
+    
+    pub fn set_hotkeys(&mut self, config: &HotkeyConfig) {
+        let bindings = [config.record, config.pause, config.stop, config.emergency, config.play];
+        for (slot, hotkey) in HOTKEY_BINDINGS.iter().zip(bindings) {
+            slot.store(hotkey.to_bits(), Ordering::SeqCst);
+        }
+    }
+    
+    pub fn set_record_target(&mut self, record_target: Option<RecordTarget>) {
+        *RECORD_TARGET.lock().unwrap() = record_target;
+        *TARGET_MATCH_CACHE.lock().unwrap() = None;
+    }
+    
+    /// True once per toggle key press seen by the hook
+    pub fn take_record_toggle(&self) -> bool {
+        RECORD_TOGGLE_PRESSED.swap(false, Ordering::SeqCst)
+    }
+    
+    /// Takes effect on the next install()
+    pub fn set_min_move_interval_ms(&mut self, interval_ms: u64) {
+        self.min_move_interval_ms = interval_ms;
+    }
+    
+    /// Takes effect on the next install()
+    pub fn set_min_move_distance_px(&mut self, distance_px: u32) {
+        self.min_move_distance_px = distance_px;
     }
     
+    /// Installs the low-level keyboard and mouse hooks. They only fire while the installing
+    /// thread pumps window messages
     pub fn install(&mut self) -> std::result::Result<(), String> {
+        LAST_EVENT_WINDOW.lock().unwrap().clear();
+        
+        let state = Arc::new(HookState {
+            callback: self.callback.clone(),
+            queue: InputQueue::new(INPUT_QUEUE_CAPACITY),
+            start_time: std::time::Instant::now(),
+            next_seq: AtomicU64::new(1),
+            last_foreground: AtomicIsize::new(0),
+            min_move_interval_ms: self.min_move_interval_ms,
+            last_move_ms: AtomicU64::new(NO_MOVE),
+            min_move_distance_px: self.min_move_distance_px,
+            last_move_x: AtomicI32::new(0),
+            last_move_y: AtomicI32::new(0),
+            text_input_vk: AtomicU32::new(0),
+            last_press: Mutex::new(None),
+        });
+        *GLOBAL_HOOKS.get_or_init(|| Mutex::new(None)).lock().unwrap() = Some(state.clone());
+        let delivery = state.clone();
+        self.delivery_thread = Some(std::thread::spawn(move || {
+            while let Some(captured) = delivery.queue.pop_wait() {
+                delivery.deliver(captured);
+            }
+        }));
+        self.installed = Some(state);
+        
         unsafe {
-            let hooks_ref = GLOBAL_HOOKS.get_or_init(|| Arc::new(Mutex::new(None)));
-            *hooks_ref.lock().unwrap() = Some(std::ptr::read(self as *const _));
             
             let hinstance = match GetModuleHandleW(None) {
                 Ok(h) => h,
@@ -64,19 +220,34 @@ impl GlobalHooks {
                 Err(e) => return Err(format!("Failed to install mouse hook: {}", e)),
             });
             
-            self.start_time = std::time::Instant::now();
             log::info!("Global hooks installed successfully");
             Ok(())
         }
     }
     
     pub fn uninstall(&mut self) {
-        unsafe {
-            if let Some(hooks_ref) = GLOBAL_HOOKS.get() {
-                if let Ok(mut guard) = hooks_ref.try_lock() {
+        // Leave the static alone unless it holds our state; another instance may have installed since
+        let installed = self.installed.take();
+        if let (Some(installed), Some(hooks_ref)) = (&installed, GLOBAL_HOOKS.get()) {
+            if let Ok(mut guard) = hooks_ref.lock() {
+                if guard.as_ref().is_some_and(|current| Arc::ptr_eq(current, installed)) {
                     *guard = None;
                 }
             }
+        }
+        
+        // Whatever was captured before this point is still delivered before uninstall returns
+        if let Some(installed) = &installed {
+            installed.queue.close();
+            if installed.queue.dropped() > 0 {
+                log::warn!("Dropped {} mouse moves the callback couldn't keep up with", installed.queue.dropped());
+            }
+        }
+        if let Some(thread) = self.delivery_thread.take() {
+            let _ = thread.join();
+        }
+        
+        unsafe {
             
             std::thread::sleep(std::time::Duration::from_millis(10));
             
@@ -97,55 +268,181 @@ impl GlobalHooks {
             log::info!("Global hooks uninstalled");
         }
     }
+}
+
+impl HookState {
+    // Runs on the hook thread: only the filtering that has to see the input as it happens, then a
+    // hand-off to the delivery thread, so the hook proc gets back to CallNextHookEx quickly
+    fn capture(&self, input: RawInput) {
+        let droppable = matches!(input, RawInput::Mouse { event_type: EventType::MouseMove, .. });
+        self.queue.push(CapturedInput {
+            timestamp: crate::events::capture_clock(),
+            foreground: unsafe { GetForegroundWindow() }.0,
+            input,
+        }, droppable);
+    }
     
-    fn handle_keyboard_event(&self, vk_code: u32, scan_code: u32, is_key_down: bool) {
-        if let Some(callback) = &self.callback {
-            let timestamp = self.start_time.elapsed().as_secs_f64();
-            let key_name = vk_code_to_string(vk_code);
-            
-            let event = MacroEvent {
-                timestamp,
-                event_type: if is_key_down {
-                    EventType::KeyDown
-                } else {
-                    EventType::KeyUp
-                },
-                data: serde_json::json!({
-                    "vk_code": vk_code,
-                    "scan_code": scan_code,
-                    "key_name": key_name,
-                }),
-            };
-            
-            callback(event);
+    // `text` is what the key typed under the active layout, looked up for key presses only
+    fn handle_keyboard_event(&self, vk_code: u32, scan_code: u32, flags: u32, is_key_down: bool, text: Option<String>) {
+        if !target_accepts(None) {
+            return;
+        }
+        
+        if !is_key_down && self.text_input_vk.compare_exchange(vk_code, 0, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            return;
+        }
+        
+        if self.callback.is_none() {
+            return;
+        }
+        
+        if let Some(text) = text.filter(|text| needs_text_input(text)) {
+            self.text_input_vk.store(vk_code, Ordering::Relaxed);
+            self.capture(RawInput::Text { text });
+            return;
         }
+        
+        self.capture(RawInput::Key { vk_code, scan_code, flags, is_key_down });
+    }
+    
+    // Claims the slot for a move at `now_ms` unless the previous one was too recent or too close.
+    // A move to exactly where the last one ended is always dropped: Windows keeps firing moves
+    // while the cursor sits still, and replaying them changes nothing.
+    fn take_move_slot(&self, now_ms: u64, x: i32, y: i32) -> bool {
+        let last = self.last_move_ms.load(Ordering::Relaxed);
+        if last != NO_MOVE {
+            if !move_interval_elapsed(Some(last), now_ms, self.min_move_interval_ms) {
+                return false;
+            }
+            let last_point = (
+                self.last_move_x.load(Ordering::Relaxed),
+                self.last_move_y.load(Ordering::Relaxed),
+            );
+            if last_point == (x, y) || !move_distance_exceeded(last_point, (x, y), self.min_move_distance_px) {
+                return false;
+            }
+        }
+        self.last_move_ms.store(now_ms, Ordering::Relaxed);
+        self.last_move_x.store(x, Ordering::Relaxed);
+        self.last_move_y.store(y, Ordering::Relaxed);
+        true
     }
     
     fn handle_mouse_event(&self, event_type: EventType, x: i32, y: i32, button: Option<u32>, delta: Option<i32>) {
-        if let Some(callback) = &self.callback {
-            let timestamp = self.start_time.elapsed().as_secs_f64();
-            
-            let mut data = serde_json::json!({
-                "x": x,
-                "y": y,
-            });
-            
-            if let Some(btn) = button {
-                data["button"] = serde_json::Value::Number(serde_json::Number::from(btn));
+        if !target_accepts(Some((x, y))) {
+            return;
+        }
+        
+        let now_ms = self.start_time.elapsed().as_millis() as u64;
+        match event_type {
+            EventType::MouseMove
+                if !self.take_move_slot(now_ms, x, y) => return,
+            // Keep the first move after a click so the pointer path leads exactly to it
+            EventType::MouseDown | EventType::MouseUp => {
+                self.last_move_ms.store(NO_MOVE, Ordering::Relaxed);
             }
-            
-            if let Some(d) = delta {
-                data["delta"] = serde_json::Value::Number(serde_json::Number::from(d));
+            _ => {}
+        }
+        
+        let double = match (&event_type, button) {
+            (EventType::MouseDown, Some(button)) => self.take_double_click(button, now_ms, x, y),
+            _ => false,
+        };
+        
+        if self.callback.is_some() {
+            self.capture(RawInput::Mouse { event_type, x, y, button, delta, double });
+        }
+    }
+    
+    // Whether this press completes a double-click, by the system's own time and distance limits.
+    // A double-click consumes the earlier press, so a third quick press starts a new pair.
+    fn take_double_click(&self, button: u32, now_ms: u64, x: i32, y: i32) -> bool {
+        let mut last_press = self.last_press.lock().unwrap();
+        let (max_ms, max_dx, max_dy) = unsafe {
+            (GetDoubleClickTime() as u64, GetSystemMetrics(SM_CXDOUBLECLK) / 2, GetSystemMetrics(SM_CYDOUBLECLK) / 2)
+        };
+        let double = is_double_click(*last_press, (button, now_ms, x, y), max_ms, (max_dx, max_dy));
+        *last_press = if double { None } else { Some((button, now_ms, x, y)) };
+        double
+    }
+    
+    // Runs on the delivery thread: turns captured input into MacroEvents for the callback
+    fn deliver(&self, captured: CapturedInput) {
+        let callback = match &self.callback {
+            Some(callback) => callback,
+            None => return,
+        };
+        let CapturedInput { timestamp, foreground, input } = captured;
+        let foreground = HWND(foreground);
+        self.emit_focus_change(callback, timestamp, foreground);
+        
+        let (event_type, mut data, source) = match input {
+            RawInput::Text { text } => (EventType::TextInput, serde_json::json!({ "text": text }), TRACK_KEYBOARD),
+            RawInput::Key { vk_code, scan_code, flags, is_key_down } => {
+                let data = serde_json::json!({
+                    "vk_code": vk_code,
+                    "scan_code": scan_code,
+                    "key_name": vk_code_to_string(vk_code, flags & LLKHF_EXTENDED.0 != 0),
+                    "extended": flags & LLKHF_EXTENDED.0 != 0,
+                    "injected": flags & LLKHF_INJECTED.0 != 0,
+                });
+                let event_type = if is_key_down { EventType::KeyDown } else { EventType::KeyUp };
+                (event_type, data, TRACK_KEYBOARD)
             }
-            
-            let event = MacroEvent {
-                timestamp,
-                event_type,
-                data,
-            };
-            
-            callback(event);
+            RawInput::Mouse { event_type, x, y, button, delta, double } => {
+                let mut data = serde_json::json!({
+                    "x": x,
+                    "y": y,
+                });
+                
+                if let Some(monitor) = crate::monitors::monitor_at(x, y) {
+                    data["monitor"] = serde_json::Value::String(monitor.name);
+                    data["monitor_left"] = serde_json::Value::from(monitor.left);
+                    data["monitor_top"] = serde_json::Value::from(monitor.top);
+                }
+                
+                if let Some(btn) = button {
+                    data["button"] = serde_json::Value::Number(serde_json::Number::from(btn));
+                }
+                
+                if let Some(d) = delta {
+                    data["delta"] = serde_json::Value::Number(serde_json::Number::from(d));
+                }
+                
+                if double {
+                    data["double"] = serde_json::Value::Bool(true);
+                }
+                (event_type, data, TRACK_MOUSE)
+            }
+        };
+        tag_window_change(&mut data, foreground);
+        
+        callback(MacroEvent {
+            timestamp,
+            event_type,
+            data,
+            source: Some(source.to_string()),
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+        });
+    }
+    
+    // Emits a FocusChange ahead of the first event that lands in a different foreground window
+    fn emit_focus_change(&self, callback: &SharedCallback, timestamp: f64, hwnd: HWND) {
+        if self.last_foreground.swap(hwnd.0, Ordering::Relaxed) == hwnd.0 {
+            return;
         }
+        
+        let event = MacroEvent {
+            timestamp,
+            event_type: EventType::FocusChange,
+            data: serde_json::json!({
+                "window": window_title(hwnd),
+            }),
+            source: None,
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+        };
+        
+        callback(event);
     }
 }
 
@@ -155,27 +452,185 @@ impl Drop for GlobalHooks {
     }
 }
 
+fn move_interval_elapsed(last_ms: Option<u64>, now_ms: u64, min_interval_ms: u64) -> bool {
+    match last_ms {
+        Some(last) => now_ms.saturating_sub(last) >= min_interval_ms,
+        None => true,
+    }
+}
+
+fn move_distance_exceeded(last: (i32, i32), current: (i32, i32), min_distance_px: u32) -> bool {
+    let dx = (current.0 - last.0) as f64;
+    let dy = (current.1 - last.1) as f64;
+    dx.hypot(dy) >= min_distance_px as f64
+}
+
+// `previous` and `press` are (button, time in ms, x, y)
+fn is_double_click(previous: Option<(u32, u64, i32, i32)>, press: (u32, u64, i32, i32), max_ms: u64, max_offset: (i32, i32)) -> bool {
+    match previous {
+        Some((button, at_ms, x, y)) => {
+            button == press.0
+                && press.1.saturating_sub(at_ms) <= max_ms
+                && (press.2 - x).abs() <= max_offset.0
+                && (press.3 - y).abs() <= max_offset.1
+        }
+        None => false,
+    }
+}
+
+fn bound_hotkey(slot: usize) -> Option<Hotkey> {
+    Hotkey::from_bits(HOTKEY_BINDINGS[slot].load(Ordering::SeqCst))
+}
+
+// Whether a key belongs to `hotkey`: one of its modifiers, or its key pressed with exactly those
+// modifiers held. The stop and emergency chords have to get through to RegisterHotKey even while
+// input is blocked, so the user can always stop playback.
+fn is_chord_key(hotkey: Option<Hotkey>, vk_code: u32, ctrl: bool, shift: bool, alt: bool) -> bool {
+    let hotkey = match hotkey {
+        Some(hotkey) => hotkey,
+        None => return false,
+    };
+    let vk = VIRTUAL_KEY(vk_code as u16);
+    hotkey.matches(vk_code, ctrl, shift, alt)
+        || (hotkey.ctrl && [VK_CONTROL, VK_LCONTROL, VK_RCONTROL].contains(&vk))
+        || (hotkey.shift && [VK_SHIFT, VK_LSHIFT, VK_RSHIFT].contains(&vk))
+        || (hotkey.alt && [VK_MENU, VK_LMENU, VK_RMENU].contains(&vk))
+}
+
+// Only the exact bound chord counts: a plain "q" while typing is recorded like any other key
+fn is_hotkey_chord(vk_code: u32, ctrl: bool, shift: bool, alt: bool) -> bool {
+    HOTKEY_BINDINGS.iter()
+        .filter_map(|slot| Hotkey::from_bits(slot.load(Ordering::SeqCst)))
+        .any(|hotkey| hotkey.matches(vk_code, ctrl, shift, alt))
+}
+
+fn suppress_hotkey_key(vk_code: u32, is_key_down: bool, ctrl: bool, shift: bool, alt: bool) -> bool {
+    if is_key_down {
+        let chord = is_hotkey_chord(vk_code, ctrl, shift, alt);
+        if chord {
+            SUPPRESSED_HOTKEY_VK.store(vk_code, Ordering::SeqCst);
+        }
+        chord
+    } else {
+        SUPPRESSED_HOTKEY_VK.compare_exchange(vk_code, 0, Ordering::SeqCst, Ordering::SeqCst).is_ok()
+    }
+}
+
+// Plain ASCII replays fine as virtual keys; anything else only survives as literal text
+fn needs_text_input(text: &str) -> bool {
+    !text.is_empty() && !text.is_ascii()
+}
+
+// Characters the key produces in the foreground window's layout with the modifiers held right now
+unsafe fn typed_text(vk_code: u32, scan_code: u32) -> Option<String> {
+    let mut state = [0u8; 256];
+    for vk in [VK_SHIFT, VK_LSHIFT, VK_RSHIFT, VK_CONTROL, VK_LCONTROL, VK_RCONTROL, VK_MENU, VK_LMENU, VK_RMENU] {
+        if GetAsyncKeyState(vk.0 as i32) < 0 {
+            state[vk.0 as usize] = 0x80;
+        }
+    }
+    if GetKeyState(VK_CAPITAL.0 as i32) & 1 != 0 {
+        state[VK_CAPITAL.0 as usize] = 0x01;
+    }
+    
+    let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+    let layout = GetKeyboardLayout(thread);
+    let mut buffer = [0u16; 8];
+    // Flag 0x4 leaves the dead-key state alone so the user's own typing isn't disturbed
+    let len = ToUnicodeEx(vk_code, scan_code, &state, &mut buffer, 0x4, layout);
+    if len <= 0 {
+        return None;
+    }
+    String::from_utf16(&buffer[..len as usize]).ok()
+}
+
+// A clone of the installed state, so the lock is released before the input is handled and
+// uninstall() never waits on a hook call in progress.
+fn installed_state() -> Option<Arc<HookState>> {
+    GLOBAL_HOOKS.get()?.lock().ok()?.clone()
+}
+
+// Windows silently removes a low-level hook that keeps input waiting longer than
+// LowLevelHooksTimeout (about 300 ms by default, less on newer builds). The hook proc only
+// filters and queues (well under a millisecond; the callback runs on the delivery thread), so
+// anything near this threshold is worth knowing about before the hook gets dropped.
+const SLOW_HOOK_WARNING: std::time::Duration = std::time::Duration::from_millis(50);
+
+fn warn_if_slow(started: std::time::Instant) {
+    let elapsed = started.elapsed();
+    if elapsed >= SLOW_HOOK_WARNING {
+        log::warn!("Hook callback took {:?}; Windows may unhook slow low-level hooks", elapsed);
+    }
+}
+
+fn should_consume(blocking: bool, injected: bool, kill_switch: bool) -> bool {
+    blocking && !injected && !kill_switch
+}
+
 unsafe extern "system" fn keyboard_hook_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let blocking = BLOCK_USER_INPUT.load(Ordering::SeqCst);
+    if n_code >= 0 && blocking {
+        let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
+        let injected = kbd_struct.flags.0 & LLKHF_INJECTED.0 != 0;
+        let (ctrl, shift, alt) = (
+            GetAsyncKeyState(VK_CONTROL.0 as i32) < 0,
+            GetAsyncKeyState(VK_SHIFT.0 as i32) < 0,
+            GetAsyncKeyState(VK_MENU.0 as i32) < 0,
+        );
+        let vk_code = kbd_struct.vkCode;
+        let stop = bound_hotkey(STOP_SLOT);
+        let kill_switch = is_chord_key(stop, vk_code, ctrl, shift, alt)
+            || is_chord_key(bound_hotkey(EMERGENCY_SLOT), vk_code, ctrl, shift, alt);
+        
+        if stop.is_some_and(|stop| stop.matches(vk_code, ctrl, shift, alt)) && !injected {
+            KILL_SWITCH_PRESSED.store(true, Ordering::SeqCst);
+        }
+        
+        if should_consume(blocking, injected, kill_switch) {
+            return LRESULT(1);
+        }
+    }
+    
     if n_code >= 0 {
-        if let Some(hooks_ref) = GLOBAL_HOOKS.get() {
-            if let Ok(hooks_guard) = hooks_ref.lock() {
-                if let Some(hooks) = hooks_guard.as_ref() {
-                    let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
-                    let is_key_down = w_param.0 == WM_KEYDOWN as usize || w_param.0 == WM_SYSKEYDOWN as usize;
-                    
-                    let vk_code = kbd_struct.vkCode;
-                    if !(vk_code == VK_CONTROL.0 as u32 || 
-                         (vk_code >= 0x52 && vk_code <= 0x52) ||
-                         (vk_code >= 0x50 && vk_code <= 0x50) ||
-                         (vk_code >= 0x51 && vk_code <= 0x51)) {
-                        hooks.handle_keyboard_event(
-                            kbd_struct.vkCode,
-                            kbd_struct.scanCode,
-                            is_key_down,
-                        );
-                    }
-                }
+        let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
+        let injected = kbd_struct.flags.0 & LLKHF_INJECTED.0 != 0;
+        let toggle_vk = RECORD_TOGGLE_VK.load(Ordering::SeqCst);
+        if toggle_vk != 0 && kbd_struct.vkCode == toggle_vk && !injected {
+            if w_param.0 == WM_KEYDOWN as usize || w_param.0 == WM_SYSKEYDOWN as usize {
+                RECORD_TOGGLE_PRESSED.store(true, Ordering::SeqCst);
+            }
+            return LRESULT(1);
+        }
+    }
+    
+    if n_code >= 0 {
+        if let Some(hooks) = installed_state() {
+            let started = std::time::Instant::now();
+            let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
+            let is_key_down = w_param.0 == WM_KEYDOWN as usize || w_param.0 == WM_SYSKEYDOWN as usize;
+            
+            let suppressed = suppress_hotkey_key(
+                kbd_struct.vkCode,
+                is_key_down,
+                GetAsyncKeyState(VK_CONTROL.0 as i32) < 0,
+                GetAsyncKeyState(VK_SHIFT.0 as i32) < 0,
+                GetAsyncKeyState(VK_MENU.0 as i32) < 0,
+            );
+            if !suppressed {
+                let text = if is_key_down {
+                    typed_text(kbd_struct.vkCode, kbd_struct.scanCode)
+                } else {
+                    None
+                };
+                hooks.handle_keyboard_event(
+                    kbd_struct.vkCode,
+                    kbd_struct.scanCode,
+                    kbd_struct.flags.0,
+                    is_key_down,
+                    text,
+                );
             }
+            warn_if_slow(started);
         }
     }
     
@@ -183,56 +638,142 @@ unsafe extern "system" fn keyboard_hook_proc(n_code: i32, w_param: WPARAM, l_par
 }
 
 unsafe extern "system" fn mouse_hook_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let blocking = BLOCK_USER_INPUT.load(Ordering::SeqCst);
+    if n_code >= 0 && blocking {
+        let mouse_struct = *(l_param.0 as *const MSLLHOOKSTRUCT);
+        let injected = mouse_struct.flags & LLMHF_INJECTED != 0;
+        
+        if should_consume(blocking, injected, false) {
+            return LRESULT(1);
+        }
+    }
+    
     if n_code >= 0 {
-        if let Some(hooks_ref) = GLOBAL_HOOKS.get() {
-            if let Ok(hooks_guard) = hooks_ref.lock() {
-                if let Some(hooks) = hooks_guard.as_ref() {
-                    let mouse_struct = *(l_param.0 as *const MSLLHOOKSTRUCT);
-                    let x = mouse_struct.pt.x;
-                    let y = mouse_struct.pt.y;
-                    
-                    match w_param.0 as u32 {
-                        WM_MOUSEMOVE => {
-                            hooks.handle_mouse_event(EventType::MouseMove, x, y, None, None);
-                        }
-                        WM_LBUTTONDOWN => {
-                            hooks.handle_mouse_event(EventType::MouseDown, x, y, Some(1), None);
-                        }
-                        WM_LBUTTONUP => {
-                            hooks.handle_mouse_event(EventType::MouseUp, x, y, Some(1), None);
-                        }
-                        WM_RBUTTONDOWN => {
-                            hooks.handle_mouse_event(EventType::MouseDown, x, y, Some(2), None);
-                        }
-                        WM_RBUTTONUP => {
-                            hooks.handle_mouse_event(EventType::MouseUp, x, y, Some(2), None);
-                        }
-                        WM_MBUTTONDOWN => {
-                            hooks.handle_mouse_event(EventType::MouseDown, x, y, Some(3), None);
-                        }
-                        WM_MBUTTONUP => {
-                            hooks.handle_mouse_event(EventType::MouseUp, x, y, Some(3), None);
-                        }
-                        WM_MOUSEWHEEL => {
-                            let delta = ((mouse_struct.mouseData >> 16) & 0xFFFF) as i16 as i32;
-                            hooks.handle_mouse_event(EventType::MouseScroll, x, y, None, Some(delta));
-                        }
-                        _ => {}
-                    }
+        if let Some(hooks) = installed_state() {
+            let started = std::time::Instant::now();
+            let mouse_struct = *(l_param.0 as *const MSLLHOOKSTRUCT);
+            let x = mouse_struct.pt.x;
+            let y = mouse_struct.pt.y;
+            
+            match w_param.0 as u32 {
+                WM_MOUSEMOVE => {
+                    hooks.handle_mouse_event(EventType::MouseMove, x, y, None, None);
+                }
+                // Low-level hooks normally only see plain presses (DBLCLK is made later, per window
+                // class), but take them as presses if they do turn up; the timing check flags them
+                WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
+                    hooks.handle_mouse_event(EventType::MouseDown, x, y, Some(1), None);
+                }
+                WM_LBUTTONUP => {
+                    hooks.handle_mouse_event(EventType::MouseUp, x, y, Some(1), None);
+                }
+                WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => {
+                    hooks.handle_mouse_event(EventType::MouseDown, x, y, Some(2), None);
+                }
+                WM_RBUTTONUP => {
+                    hooks.handle_mouse_event(EventType::MouseUp, x, y, Some(2), None);
                 }
+                WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => {
+                    hooks.handle_mouse_event(EventType::MouseDown, x, y, Some(3), None);
+                }
+                WM_MBUTTONUP => {
+                    hooks.handle_mouse_event(EventType::MouseUp, x, y, Some(3), None);
+                }
+                WM_XBUTTONDOWN | WM_XBUTTONDBLCLK | WM_XBUTTONUP => {
+                    // High word says which side button: XBUTTON1 (back) or XBUTTON2 (forward)
+                    let button = if (mouse_struct.mouseData >> 16) & 0xFFFF == XBUTTON2 as u32 { 5 } else { 4 };
+                    let event_type = if w_param.0 as u32 == WM_XBUTTONUP { EventType::MouseUp } else { EventType::MouseDown };
+                    hooks.handle_mouse_event(event_type, x, y, Some(button), None);
+                }
+                WM_MOUSEWHEEL => {
+                    let delta = ((mouse_struct.mouseData >> 16) & 0xFFFF) as i16 as i32;
+                    hooks.handle_mouse_event(EventType::MouseScroll, x, y, None, Some(delta));
+                }
+                WM_MOUSEHWHEEL => {
+                    let delta = ((mouse_struct.mouseData >> 16) & 0xFFFF) as i16 as i32;
+                    hooks.handle_mouse_event(EventType::MouseHScroll, x, y, None, Some(delta));
+                }
+                _ => {}
             }
+            warn_if_slow(started);
         }
     }
     
     CallNextHookEx(None, n_code, w_param, l_param)
 }
 
-fn vk_code_to_string(vk_code: u32) -> String {
+pub(crate) fn window_title(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 256];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+// False when recording is restricted and the foreground window isn't the target, or the
+// mouse `point` is outside the foreground window's client area
+fn target_accepts(point: Option<(i32, i32)>) -> bool {
+    let record_target = RECORD_TARGET.lock().unwrap();
+    let record_target = match record_target.as_ref() {
+        Some(record_target) => record_target,
+        None => return true,
+    };
+    
+    let hwnd = unsafe { GetForegroundWindow() };
+    let mut cache = TARGET_MATCH_CACHE.lock().unwrap();
+    let matches = match *cache {
+        Some((cached_hwnd, matches)) if cached_hwnd == hwnd.0 => matches,
+        _ => {
+            let matches = target::window_matches(record_target, hwnd);
+            *cache = Some((hwnd.0, matches));
+            matches
+        }
+    };
+    if !matches {
+        return false;
+    }
+    
+    // The foreground window rather than the target itself, so clicks in its own dialogs are kept
+    match point {
+        Some((x, y)) => target::client_rect(hwnd)
+            .map(|rect| target::rect_contains(&rect, x, y))
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+// Sets data["window"] to the title of `foreground` (the window in front when the input happened)
+// when it differs from the last event's
+fn tag_window_change(data: &mut serde_json::Value, foreground: HWND) {
+    let title = window_title(foreground);
+    let mut last = LAST_EVENT_WINDOW.lock().unwrap();
+    if *last != title {
+        data["window"] = serde_json::Value::String(title.clone());
+        *last = title;
+    }
+}
+
+#[cfg(feature = "gui")]
+pub(crate) fn find_window(title: &str) -> Option<isize> {
+    let hwnd = unsafe { FindWindowW(windows::core::PCWSTR::null(), &windows::core::HSTRING::from(title)) };
+    if hwnd.0 == 0 { None } else { Some(hwnd.0) }
+}
+
+pub(crate) fn cursor_position() -> Option<(i32, i32)> {
+    let mut point = POINT::default();
+    unsafe { GetCursorPos(&mut point).ok()? };
+    Some((point.x, point.y))
+}
+
+fn vk_code_to_string(vk_code: u32, extended: bool) -> String {
+    if let Some((_, name)) = NUMPAD_KEY_NAMES.iter().find(|(vk, _)| *vk == vk_code) {
+        return name.to_string();
+    }
+    
     match vk_code {
         0x41..=0x5A => char::from(vk_code as u8).to_string().to_lowercase(),
         0x30..=0x39 => char::from(vk_code as u8).to_string(),
         0x70..=0x7B => format!("f{}", vk_code - 0x6F),
         val if val == VK_SPACE.0 as u32 => "space".to_string(),
+        val if val == VK_RETURN.0 as u32 && extended => NUMPAD_ENTER_NAME.to_string(),
         val if val == VK_RETURN.0 as u32 => "enter".to_string(),
         val if val == VK_BACK.0 as u32 => "backspace".to_string(),
         val if val == VK_TAB.0 as u32 => "tab".to_string(),
@@ -250,6 +791,8 @@ fn vk_code_to_string(vk_code: u32) -> String {
         val if val == VK_END.0 as u32 => "end".to_string(),
         val if val == VK_PRIOR.0 as u32 => "page_up".to_string(),
         val if val == VK_NEXT.0 as u32 => "page_down".to_string(),
+        val if val == VK_APPS.0 as u32 => "apps".to_string(),
+        val if val == VK_SLEEP.0 as u32 => "sleep".to_string(),
         0xBA => ";".to_string(),
         0xBB => "=".to_string(),
         0xBC => ",".to_string(),
@@ -264,3 +807,117 @@ fn vk_code_to_string(vk_code: u32) -> String {
         _ => format!("vk_{}", vk_code),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn names_the_context_menu_and_sleep_keys() {
+        assert_eq!(vk_code_to_string(VK_APPS.0 as u32, true), "apps");
+        assert_eq!(vk_code_to_string(VK_SLEEP.0 as u32, false), "sleep");
+    }
+    
+    #[test]
+    fn blocking_lets_the_kill_switch_through() {
+        // Stop rebound to Ctrl+Shift+X
+        let stop = Some(Hotkey { ctrl: true, shift: true, alt: false, vk: 0x58 });
+        let kill_switch = is_chord_key(stop, 0x58, true, true, false);
+        assert!(kill_switch);
+        assert!(!should_consume(true, false, kill_switch));
+        assert!(!should_consume(true, false, is_chord_key(stop, VK_LCONTROL.0 as u32, false, false, false)));
+        assert!(!should_consume(true, false, is_chord_key(stop, VK_RSHIFT.0 as u32, true, false, false)));
+        
+        // X without the modifiers is ordinary input, and so is the old Ctrl+Q
+        assert!(should_consume(true, false, is_chord_key(stop, 0x58, false, false, false)));
+        assert!(should_consume(true, false, is_chord_key(stop, 0x51, true, false, false)));
+        assert!(!is_chord_key(None, 0x51, true, false, false));
+        assert!(should_consume(true, false, false));
+        // Playback's own input and anything while not blocking pass
+        assert!(!should_consume(true, true, false));
+        assert!(!should_consume(false, false, false));
+    }
+    
+    #[test]
+    fn only_the_bound_chords_are_kept_out_of_recordings() {
+        let config = HotkeyConfig::default();
+        for (slot, hotkey) in HOTKEY_BINDINGS.iter().zip([config.record, config.pause, config.stop, config.emergency, config.play]) {
+            slot.store(hotkey.to_bits(), Ordering::SeqCst);
+        }
+        
+        // Typing "proper" hits P and R, both bound with Ctrl
+        for c in "PROPER".chars() {
+            assert!(!suppress_hotkey_key(c as u32, true, false, false, false), "{} was dropped", c);
+            assert!(!suppress_hotkey_key(c as u32, false, false, false, false));
+        }
+        
+        // Ctrl+R is dropped, release included, even if Ctrl goes up first
+        assert!(suppress_hotkey_key(0x52, true, true, false, false));
+        assert!(suppress_hotkey_key(0x52, false, false, false, false));
+        assert!(!suppress_hotkey_key(0x52, false, false, false, false));
+    }
+    
+    #[test]
+    fn hooks_install_and_uninstall_repeatedly() {
+        for _ in 0..20 {
+            let mut hooks = GlobalHooks::new();
+            hooks.set_callback(Box::new(|_| {}));
+            
+            assert_eq!(hooks.install(), Ok(()));
+            assert!(installed_state().is_some());
+            
+            hooks.uninstall();
+            assert!(installed_state().is_none());
+            // A second uninstall, and the one in drop, have nothing left to do
+            hooks.uninstall();
+        }
+        
+        // Dropping an installed instance unhooks it too
+        let mut hooks = GlobalHooks::new();
+        hooks.install().unwrap();
+        drop(hooks);
+        assert!(installed_state().is_none());
+    }
+    
+    #[test]
+    fn names_every_numpad_key() {
+        for (vk, name) in NUMPAD_KEY_NAMES {
+            assert_eq!(vk_code_to_string(vk, false), name);
+        }
+        assert_eq!(vk_code_to_string(VK_RETURN.0 as u32, true), NUMPAD_ENTER_NAME);
+        assert_eq!(vk_code_to_string(VK_RETURN.0 as u32, false), "enter");
+    }
+    
+    #[test]
+    fn identical_moves_are_recorded_once() {
+        let state = HookState {
+            callback: Some(Arc::new(|_| {})),
+            queue: InputQueue::new(INPUT_QUEUE_CAPACITY),
+            start_time: std::time::Instant::now(),
+            next_seq: AtomicU64::new(1),
+            last_foreground: AtomicIsize::new(0),
+            // No throttling, so only the repeated position drops moves
+            min_move_interval_ms: 0,
+            last_move_ms: AtomicU64::new(NO_MOVE),
+            min_move_distance_px: 0,
+            last_move_x: AtomicI32::new(0),
+            last_move_y: AtomicI32::new(0),
+            text_input_vk: AtomicU32::new(0),
+            last_press: Mutex::new(None),
+        };
+        
+        for _ in 0..10 {
+            state.handle_mouse_event(EventType::MouseMove, 300, 200, None, None);
+        }
+        state.handle_mouse_event(EventType::MouseMove, 301, 200, None, None);
+        state.queue.close();
+        
+        let moves: Vec<_> = std::iter::from_fn(|| state.queue.pop_wait())
+            .map(|captured| match captured.input {
+                RawInput::Mouse { x, y, .. } => (x, y),
+                _ => panic!("only moves were captured"),
+            })
+            .collect();
+        assert_eq!(moves, vec![(300, 200), (301, 200)]);
+    }
+}
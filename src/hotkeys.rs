@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const HOTKEY_FILE_NAME: &str = "hotkeys.json";
+
+pub const VK_ESCAPE: u32 = 0x1B;
+const VK_P: u32 = 0x50;
+const VK_Q: u32 = 0x51;
+const VK_R: u32 = 0x52;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub alt: bool,
+    pub vk: u32,
+}
+
+impl Hotkey {
+    pub const fn ctrl(vk: u32) -> Self {
+        Self { ctrl: true, shift: false, alt: false, vk }
+    }
+    
+    pub fn matches(&self, vk: u32, ctrl: bool, shift: bool, alt: bool) -> bool {
+        self.vk == vk && self.ctrl == ctrl && self.shift == shift && self.alt == alt
+    }
+    
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.ctrl {
+            label.push_str("Ctrl+");
+        }
+        if self.shift {
+            label.push_str("Shift+");
+        }
+        if self.alt {
+            label.push_str("Alt+");
+        }
+        label.push_str(&key_name(self.vk));
+        label
+    }
+    
+    // Packed into one word so the keyboard hook can read a binding without locking
+    pub fn to_bits(self) -> u32 {
+        (self.vk & 0xFFFF)
+            | (self.ctrl as u32) << 16
+            | (self.shift as u32) << 17
+            | (self.alt as u32) << 18
+    }
+    
+    // 0 means "nothing bound"
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        let vk = bits & 0xFFFF;
+        if vk == 0 {
+            return None;
+        }
+        Some(Self {
+            ctrl: bits & (1 << 16) != 0,
+            shift: bits & (1 << 17) != 0,
+            alt: bits & (1 << 18) != 0,
+            vk,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Record,
+    Pause,
+    Stop,
+    // Registered system-wide while playing, so it works even when the window isn't focused
+    EmergencyStop,
+    // Always registered system-wide: starts the loaded macro from anywhere
+    Play,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 5] = [
+        HotkeyAction::Record,
+        HotkeyAction::Pause,
+        HotkeyAction::Stop,
+        HotkeyAction::EmergencyStop,
+        HotkeyAction::Play,
+    ];
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::Record => "Record",
+            HotkeyAction::Pause => "Pause/Resume",
+            HotkeyAction::Stop => "Stop",
+            HotkeyAction::EmergencyStop => "Emergency stop",
+            HotkeyAction::Play => "Play (global)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotkeyConfig {
+    pub record: Hotkey,
+    pub pause: Hotkey,
+    pub stop: Hotkey,
+    // Configs saved before the emergency stop existed get the default
+    #[serde(default = "default_emergency_hotkey")]
+    pub emergency: Hotkey,
+    #[serde(default = "default_play_hotkey")]
+    pub play: Hotkey,
+}
+
+fn default_emergency_hotkey() -> Hotkey {
+    Hotkey { ctrl: true, shift: true, alt: false, vk: VK_ESCAPE }
+}
+
+// Ctrl+Alt rather than Ctrl+Shift: registering globally takes the chord away from every other app
+fn default_play_hotkey() -> Hotkey {
+    Hotkey { ctrl: true, shift: false, alt: true, vk: VK_P }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            record: Hotkey::ctrl(VK_R),
+            pause: Hotkey::ctrl(VK_P),
+            stop: Hotkey::ctrl(VK_Q),
+            emergency: default_emergency_hotkey(),
+            play: default_play_hotkey(),
+        }
+    }
+}
+
+impl HotkeyConfig {
+    pub fn get(&self, action: HotkeyAction) -> Hotkey {
+        match action {
+            HotkeyAction::Record => self.record,
+            HotkeyAction::Pause => self.pause,
+            HotkeyAction::Stop => self.stop,
+            HotkeyAction::EmergencyStop => self.emergency,
+            HotkeyAction::Play => self.play,
+        }
+    }
+    
+    pub fn set(&mut self, action: HotkeyAction, hotkey: Hotkey) {
+        match action {
+            HotkeyAction::Record => self.record = hotkey,
+            HotkeyAction::Pause => self.pause = hotkey,
+            HotkeyAction::Stop => self.stop = hotkey,
+            HotkeyAction::EmergencyStop => self.emergency = hotkey,
+            HotkeyAction::Play => self.play = hotkey,
+        }
+    }
+    
+    pub fn action_for(&self, vk: u32, ctrl: bool, shift: bool, alt: bool) -> Option<HotkeyAction> {
+        HotkeyAction::ALL.into_iter()
+            .find(|action| self.get(*action).matches(vk, ctrl, shift, alt))
+    }
+    
+    // Pairs of actions bound to the same chord; only the first of each pair would ever fire
+    pub fn conflicts(&self) -> Vec<(HotkeyAction, HotkeyAction)> {
+        let mut conflicts = Vec::new();
+        for (i, first) in HotkeyAction::ALL.iter().enumerate() {
+            for second in &HotkeyAction::ALL[i + 1..] {
+                if self.get(*first) == self.get(*second) {
+                    conflicts.push((*first, *second));
+                }
+            }
+        }
+        conflicts
+    }
+    
+    // Falls back to the defaults when the file is missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        if !path.is_file() {
+            return Self::default();
+        }
+        
+        match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("Ignoring unreadable hotkey config {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+// MacroRecorder/hotkeys.json in the user's config dir, or the temp dir when there's none
+pub fn hotkey_config_path() -> PathBuf {
+    config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("MacroRecorder")
+        .join(HOTKEY_FILE_NAME)
+}
+
+// The same lookup as dirs::config_dir(), which isn't among our dependencies: %APPDATA% on
+// Windows, ~/Library/Application Support on macOS, $XDG_CONFIG_HOME or ~/.config elsewhere
+fn config_dir() -> Option<PathBuf> {
+    let absolute = |var: &str| std::env::var_os(var).map(PathBuf::from).filter(|path| path.is_absolute());
+    if cfg!(windows) {
+        return absolute("APPDATA");
+    }
+    let home = absolute("HOME");
+    if cfg!(target_os = "macos") {
+        return home.map(|home| home.join("Library").join("Application Support"));
+    }
+    absolute("XDG_CONFIG_HOME").or_else(|| home.map(|home| home.join(".config")))
+}
+
+// Letters, digits, function keys and Esc; anything else is shown by its VK code
+pub fn key_name(vk: u32) -> String {
+    match vk {
+        VK_ESCAPE => "Esc".to_string(),
+        0x30..=0x39 | 0x41..=0x5A => char::from(vk as u8).to_string(),
+        0x70..=0x87 => format!("F{}", vk - 0x6F),
+        _ => format!("VK {}", vk),
+    }
+}
+
+pub fn vk_from_name(name: &str) -> Option<u32> {
+    if name == "Escape" {
+        return Some(VK_ESCAPE);
+    }
+    
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_uppercase() || c.is_ascii_digit() {
+            return Some(c as u32);
+        }
+    }
+    
+    let n: u32 = name.strip_prefix('F')?.parse().ok()?;
+    (1..=24).contains(&n).then_some(0x6F + n)
+}
@@ -0,0 +1,90 @@
+use crate::events::{EventType, MacroEvent, TRACK_MOUSE};
+
+// Long enough for typical tooltips and hover menus to open
+#[cfg(all(windows, feature = "gui"))]
+pub const DEFAULT_HOVER_THRESHOLD_SECS: f64 = 0.8;
+
+// Indices of mouse moves after which the cursor stayed put for at least `threshold`
+// seconds, with how long it stayed
+pub fn find_stationary_periods(events: &[MacroEvent], threshold: f64) -> Vec<(usize, f64)> {
+    let mut periods = Vec::new();
+    
+    for (index, event) in events.iter().enumerate() {
+        if !matches!(event.event_type, EventType::MouseMove) {
+            continue;
+        }
+        
+        // Any later event ends the dwell: a further move, a click or a key press
+        if let Some(next) = events.get(index + 1) {
+            let duration = next.timestamp - event.timestamp;
+            if duration >= threshold {
+                periods.push((index, duration));
+            }
+        }
+    }
+    
+    periods
+}
+
+pub fn hover_event(at: &MacroEvent, duration: f64) -> MacroEvent {
+    let mut hover = MacroEvent::with_data(
+        at.timestamp,
+        EventType::Hover,
+        serde_json::json!({
+            "x": at.data.get("x").cloned().unwrap_or_default(),
+            "y": at.data.get("y").cloned().unwrap_or_default(),
+            "duration_ms": (duration * 1000.0).round() as i64,
+        }),
+    );
+    for field in ["monitor", "monitor_left", "monitor_top"] {
+        if let Some(value) = at.data.get(field) {
+            hover.data[field] = value.clone();
+        }
+    }
+    hover.source = Some(TRACK_MOUSE.to_string());
+    hover.seq = at.seq;
+    hover
+}
+
+// Put a Hover right after every move that was followed by a long enough dwell
+pub fn insert_hovers(events: &mut Vec<MacroEvent>, threshold: f64) -> usize {
+    let periods = find_stationary_periods(events, threshold);
+    
+    for (index, duration) in periods.iter().rev() {
+        let hover = hover_event(&events[*index], *duration);
+        events.insert(index + 1, hover);
+    }
+    
+    periods.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn mouse_move(timestamp: f64, x: i64) -> MacroEvent {
+        MacroEvent::with_data(timestamp, EventType::MouseMove, json!({ "x": x, "y": 0 }))
+    }
+    
+    #[test]
+    fn finds_moves_followed_by_a_long_enough_dwell() {
+        let events = vec![
+            mouse_move(0.0, 0),
+            mouse_move(0.25, 10),
+            mouse_move(1.75, 20),
+            MacroEvent::with_data(2.0, EventType::Click, json!({ "x": 20, "y": 0, "button": 1 })),
+            mouse_move(2.25, 30),
+            mouse_move(3.25, 40),
+        ];
+        
+        // The click isn't a move, and the last move has nothing after it to end a dwell
+        assert_eq!(find_stationary_periods(&events, 0.8), vec![(1, 1.5), (4, 1.0)]);
+        
+        let mut with_hovers = events.clone();
+        assert_eq!(insert_hovers(&mut with_hovers, 1.2), 1);
+        assert!(matches!(with_hovers[2].event_type, EventType::Hover));
+        assert_eq!(with_hovers[2].data["duration_ms"], json!(1500));
+        assert_eq!(with_hovers[2].data["x"], json!(10));
+    }
+}
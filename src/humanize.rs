@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+pub const DEFAULT_TIMING_VARIANCE_PCT: u32 = 10;
+pub const DEFAULT_CLICK_SCATTER_PX: u32 = 2;
+
+// How far replay may stray from the recording. Only what gets sent is changed; the loaded
+// events stay exactly as recorded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HumanizeSettings {
+    // Each wait between events is stretched or shortened by up to this percentage
+    pub timing_variance_pct: u32,
+    // Clicks land up to this many pixels away from the recorded point, on each axis
+    pub click_scatter_px: u32,
+    // The same seed replays the same jitter, so a run can be reproduced
+    pub seed: u64,
+}
+
+// Jitter source for one playback. SplitMix64: small, fast and stable across builds and
+// platforms, which a seeded replay depends on; nothing here needs cryptographic quality.
+pub struct Humanizer {
+    settings: HumanizeSettings,
+    state: u64,
+}
+
+impl Humanizer {
+    pub fn new(settings: HumanizeSettings) -> Self {
+        Self { settings, state: settings.seed }
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    // Uniform in [-1, 1]
+    fn next_signed_unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+    
+    // Seconds to add to a wait of `gap`: at most timing_variance_pct of it either way
+    pub fn timing_offset(&mut self, gap: Duration) -> f64 {
+        let variance = self.settings.timing_variance_pct.min(100) as f64 / 100.0;
+        gap.as_secs_f64() * variance * self.next_signed_unit()
+    }
+    
+    // Pixel offset for one click; the press and release of a click should share it
+    pub fn click_offset(&mut self) -> (i32, i32) {
+        let scatter = self.settings.click_scatter_px as f64;
+        let dx = (scatter * self.next_signed_unit()).round() as i32;
+        let dy = (scatter * self.next_signed_unit()).round() as i32;
+        (dx, dy)
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+// Hand-off from the hook thread to the delivery thread. Bounded for items marked droppable
+// (mouse moves): when full, the oldest droppable item makes room. Everything else is always
+// queued, since losing a key or click would corrupt the recording.
+pub struct InputQueue<T> {
+    state: Mutex<QueueState<T>>,
+    ready: Condvar,
+    capacity: usize,
+    dropped: AtomicUsize,
+}
+
+struct QueueState<T> {
+    items: VecDeque<(T, bool)>,
+    closed: bool,
+}
+
+impl<T> InputQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState { items: VecDeque::new(), closed: false }),
+            ready: Condvar::new(),
+            capacity,
+            dropped: AtomicUsize::new(0),
+        }
+    }
+    
+    // Never blocks beyond the brief lock, so it's safe to call from a hook proc
+    pub fn push(&self, item: T, droppable: bool) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if state.closed {
+            return;
+        }
+        
+        if state.items.len() >= self.capacity {
+            match state.items.iter().position(|(_, droppable)| *droppable) {
+                Some(oldest) => {
+                    state.items.remove(oldest);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                None if droppable => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                None => {}
+            }
+        }
+        
+        state.items.push_back((item, droppable));
+        self.ready.notify_one();
+    }
+    
+    // Waits for the next item; None once the queue is closed and drained
+    pub fn pop_wait(&self) -> Option<T> {
+        let mut state = self.state.lock().ok()?;
+        loop {
+            if let Some((item, _)) = state.items.pop_front() {
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.ready.wait(state).ok()?;
+        }
+    }
+    
+    // Stops accepting items; what's already queued is still handed out
+    pub fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+        }
+        self.ready.notify_all();
+    }
+    
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    
+    #[test]
+    fn a_flood_of_moves_never_costs_a_key() {
+        const PRODUCERS: usize = 4;
+        const PER_PRODUCER: usize = 5000;
+        let queue = Arc::new(InputQueue::new(64));
+        
+        let consumer = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                let mut received = Vec::new();
+                while let Some(item) = queue.pop_wait() {
+                    received.push(item);
+                }
+                received
+            })
+        };
+        
+        // Every tenth item is a key, the rest are moves
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|producer| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        queue.push((producer, i), i % 10 != 0);
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        queue.close();
+        let received = consumer.join().unwrap();
+        
+        let keys = received.iter().filter(|(_, i)| i % 10 == 0).count();
+        assert_eq!(keys, PRODUCERS * PER_PRODUCER / 10);
+        assert_eq!(received.len() + queue.dropped(), PRODUCERS * PER_PRODUCER);
+        // Each producer's items stay in the order it pushed them
+        for producer in 0..PRODUCERS {
+            let sent: Vec<_> = received.iter().filter(|(p, _)| *p == producer).map(|(_, i)| *i).collect();
+            assert!(sent.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+    
+    #[test]
+    fn a_full_queue_drops_the_oldest_move() {
+        let queue = InputQueue::new(2);
+        queue.push("move 1", true);
+        queue.push("key", false);
+        queue.push("move 2", true);
+        queue.push("move 3", true);
+        queue.close();
+        
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(std::iter::from_fn(|| queue.pop_wait()).collect::<Vec<_>>(), vec!["key", "move 3"]);
+        // Nothing is accepted once closed
+        queue.push("late", false);
+        assert_eq!(queue.pop_wait(), None);
+    }
+}
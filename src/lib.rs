@@ -0,0 +1,76 @@
+//! Record global mouse and keyboard input on Windows and play it back.
+//!
+//! [`MacroRecorder`] collects [`MacroEvent`]s fed to it by [`GlobalHooks`], and
+//! [`MacroPlayer`] replays them. Both read and write the `.mcr` text format.
+//!
+//! ```ignore
+//! use macro_recorder_rust::{GlobalHooks, MacroPlayer, MacroRecorder};
+//! use std::sync::{Arc, Mutex};
+//!
+//! let recorder = Arc::new(Mutex::new(MacroRecorder::new()));
+//! let mut hooks = GlobalHooks::new();
+//! let sink = recorder.clone();
+//! hooks.set_callback(Box::new(move |event| sink.lock().unwrap().add_event(event)));
+//!
+//! recorder.lock().unwrap().start();
+//! hooks.install().unwrap();
+//! // ... the hook procs only fire while this thread pumps window messages ...
+//! hooks.uninstall();
+//! recorder.lock().unwrap().stop();
+//! recorder.lock().unwrap().save_to_file("out.mcr").unwrap();
+//!
+//! let mut player = MacroPlayer::new();
+//! player.load_from_file("out.mcr").unwrap();
+//! player.start();
+//! player.wait();
+//! ```
+
+pub mod backend;
+pub mod cli;
+pub mod events;
+pub mod export;
+#[cfg(windows)]
+pub mod hooks;
+pub mod hotkeys;
+pub mod humanize;
+pub mod player;
+pub mod portable;
+pub mod preflight;
+pub mod recorder;
+pub mod stats;
+#[cfg(windows)]
+pub mod target;
+
+mod history;
+mod shortcuts;
+mod hover;
+mod monitors;
+// Portable, so its tests run anywhere
+#[cfg(any(windows, test))]
+mod input_queue;
+#[cfg(windows)]
+mod emergency;
+
+// The front-end and what only it uses; it drives the Windows hooks directly
+#[cfg(all(windows, feature = "gui"))]
+mod app;
+#[cfg(all(windows, feature = "gui"))]
+mod tray;
+#[cfg(all(windows, feature = "gui"))]
+mod calibration;
+#[cfg(all(windows, feature = "gui"))]
+mod library;
+#[cfg(all(windows, feature = "gui"))]
+mod recovery;
+#[cfg(all(windows, feature = "gui"))]
+mod settings;
+
+pub use events::{EventType, MacroEvent};
+#[cfg(windows)]
+pub use hooks::GlobalHooks;
+pub use player::MacroPlayer;
+pub use recorder::MacroRecorder;
+
+// The egui front-end; blocks until its window is closed
+#[cfg(all(windows, feature = "gui"))]
+pub use app::run as run_gui;
@@ -0,0 +1,83 @@
+use crate::events::parse_mcr_line;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    pub event_count: usize,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub summary: FileSummary,
+}
+
+// Parse a macro file just far enough to describe it, without loading it into a player
+pub fn validate_file(path: &Path) -> Result<FileSummary, Box<dyn std::error::Error>> {
+    let content = crate::events::read_mcr_text(&path.to_string_lossy())?;
+    let mut event_count = 0;
+    let mut duration: f64 = 0.0;
+    
+    for event in content.lines().flat_map(parse_mcr_line) {
+        event_count += 1;
+        duration = duration.max(event.timestamp);
+    }
+    
+    Ok(FileSummary { event_count, duration })
+}
+
+pub fn scan_folder(dir: &Path) -> std::io::Result<Vec<LibraryEntry>> {
+    let mut entries = Vec::new();
+    
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_macro = path.extension()
+            .map(|ext| ext.eq_ignore_ascii_case("mcr"))
+            .unwrap_or(false) || crate::events::is_gzip_path(&path.to_string_lossy());
+        if !path.is_file() || !is_macro {
+            continue;
+        }
+        
+        match validate_file(&path) {
+            Ok(summary) => entries.push(LibraryEntry {
+                name: path.file_stem().unwrap_or_default().to_string_lossy().trim_end_matches(".mcr").to_string(),
+                path,
+                summary,
+            }),
+            Err(e) => log::warn!("Skipping unreadable macro {}: {}", path.display(), e),
+        }
+    }
+    
+    entries.sort_by_key(|entry| entry.name.to_lowercase());
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{mcr_lines, EventType, MacroEvent};
+    use std::fs;
+    
+    #[test]
+    fn scanning_lists_macros_by_name_with_their_summary() {
+        let dir = std::env::temp_dir().join(format!("macro-library-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested.mcr")).unwrap();
+        let events = [MacroEvent::new(0.0, EventType::MouseMove), MacroEvent::new(2.5, EventType::MouseMove)];
+        let content = mcr_lines(&events, false).join("\n");
+        fs::write(dir.join("b.mcr"), &content).unwrap();
+        fs::write(dir.join("A.MCR"), "").unwrap();
+        fs::write(dir.join("notes.txt"), &content).unwrap();
+        
+        let entries = scan_folder(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let entries = entries.unwrap();
+        
+        let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "b"]);
+        assert_eq!(entries[0].summary, FileSummary { event_count: 0, duration: 0.0 });
+        assert_eq!(entries[1].summary, FileSummary { event_count: 2, duration: 2.5 });
+        assert_eq!(entries[1].path, dir.join("b.mcr"));
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(windows)]
+use windows::Win32::{
+    Foundation::{BOOL, LPARAM, POINT, RECT},
+    Graphics::Gdi::*,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    // Device name such as \\.\DISPLAY2; stable across reboots as long as the cabling is
+    pub name: String,
+    pub left: i32,
+    pub top: i32,
+}
+
+#[cfg(windows)]
+fn monitor_info(hmonitor: HMONITOR) -> Option<Monitor> {
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    
+    unsafe {
+        if !GetMonitorInfoW(hmonitor, &mut info.monitorInfo).as_bool() {
+            return None;
+        }
+    }
+    
+    let len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(info.szDevice.len());
+    Some(Monitor {
+        name: String::from_utf16_lossy(&info.szDevice[..len]),
+        left: info.monitorInfo.rcMonitor.left,
+        top: info.monitorInfo.rcMonitor.top,
+    })
+}
+
+// The monitor a virtual-desktop point falls on (or the nearest one)
+#[cfg(windows)]
+pub fn monitor_at(x: i32, y: i32) -> Option<Monitor> {
+    let hmonitor = unsafe { MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST) };
+    monitor_info(hmonitor)
+}
+
+#[cfg(windows)]
+pub fn all_monitors() -> Vec<Monitor> {
+    unsafe extern "system" fn collect(hmonitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+        let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+        if let Some(monitor) = monitor_info(hmonitor) {
+            monitors.push(monitor);
+        }
+        BOOL(1)
+    }
+    
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(HDC(0), None, Some(collect), LPARAM(&mut monitors as *mut _ as isize));
+    }
+    monitors
+}
+
+// Keep a point at the same offset within its monitor after that monitor moved on the desktop
+pub fn remap_point(x: i32, y: i32, recorded_origin: (i32, i32), current_origin: (i32, i32)) -> (i32, i32) {
+    (
+        x - recorded_origin.0 + current_origin.0,
+        y - recorded_origin.1 + current_origin.1,
+    )
+}
@@ -1,7 +1,17 @@
+use crate::backend::InputBackend;
 use crate::events::{MacroEvent, EventType};
+use crate::export::{self, ExportFormat};
+use crate::preflight::{self, Warning};
+#[cfg(windows)]
+use crate::emergency::EmergencyStop;
+use crate::hotkeys::Hotkey;
+use crate::humanize::{HumanizeSettings, Humanizer};
+use crate::monitors::{remap_point, Monitor};
+use std::collections::HashSet;
+use std::fmt;
 use std::time::{Duration, Instant};
 use std::thread;
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering}};
 use windows::{
     Win32::{
         UI::Input::KeyboardAndMouse::*,
@@ -9,6 +19,278 @@ use windows::{
     },
 };
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum SendError {
+    Blocked,
+    Partial { sent: usize, expected: usize },
+    // The input backend can't inject on this platform
+    Unsupported,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendError::Blocked => write!(f, "SendInput was blocked (no inputs injected)"),
+            SendError::Partial { sent, expected } => {
+                write!(f, "SendInput injected only {} of {} inputs", sent, expected)
+            }
+            SendError::Unsupported => write!(f, "input injection isn't supported on this platform"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    MissingField(&'static str),
+    UnmappedKey(String),
+    Send(SendError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::MissingField(field) => write!(f, "missing required field '{}'", field),
+            ReplayError::UnmappedKey(key) => write!(f, "unknown key '{}'", key),
+            ReplayError::Send(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<SendError> for ReplayError {
+    fn from(e: SendError) -> Self {
+        ReplayError::Send(e)
+    }
+}
+
+// What to do when an event lacks the data needed to replay it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissingFieldPolicy {
+    Skip,
+    Abort,
+}
+
+// An event that failed while pause-on-error was enabled, waiting for the user to decide
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackFault {
+    pub index: usize,
+    pub description: String,
+    pub error: ReplayError,
+}
+
+// A press or release without its counterpart, found by MacroPlayer::validate
+#[derive(Debug, Clone, PartialEq)]
+pub struct DanglingInput {
+    pub index: usize,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    // Pressed and never released; replaying leaves these stuck down
+    pub unreleased: Vec<DanglingInput>,
+    // Released without a press before them; harmless but usually a sign of a cut-up file
+    pub unmatched_releases: Vec<DanglingInput>,
+}
+
+impl ValidationReport {
+    pub fn is_clean(&self) -> bool {
+        self.unreleased.is_empty() && self.unmatched_releases.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FaultAction {
+    Retry,
+    Skip,
+}
+
+// How the playback thread finished when nobody stopped it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaybackEnd {
+    // Every loop ran to the last event
+    Completed,
+    // Cut short by a malformed event under the abort policy, or by the failsafe
+    Aborted,
+}
+
+// How the scheduler spaces events: as recorded (scaled by speed) or a constant gap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingMode {
+    Recorded,
+    FixedInterval(Duration),
+}
+
+// How replay positions the cursor. SendInput-absolute goes through the input stream like a real
+// mouse, which some remote-desktop setups and games need; SetCursorPos just warps the pointer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorMethod {
+    SetCursorPos,
+    SendInputAbsolute,
+}
+
+// Point `progress` (0-1) of the way from one move to the next, eased in and out like a hand would
+pub(crate) fn interpolate_point(from: (i32, i32), to: (i32, i32), progress: f64) -> (i32, i32) {
+    let t = progress.clamp(0.0, 1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+    let lerp = |a: i32, b: i32| (a as f64 + (b - a) as f64 * eased).round() as i32;
+    (lerp(from.0, to.0), lerp(from.1, to.1))
+}
+
+// lParam for WM_KEYDOWN/WM_KEYUP: repeat count 1, scan code, extended bit and key-up transition bits
+pub(crate) fn key_lparam(scan_code: u32, extended: bool, is_up: bool) -> isize {
+    let mut lparam: u32 = 1 | ((scan_code & 0xFF) << 16);
+    if extended {
+        lparam |= 1 << 24;
+    }
+    if is_up {
+        lparam |= (1 << 30) | (1 << 31);
+    }
+    lparam as i32 as isize
+}
+
+// Characters typed with Shift on a US layout, and the key that types each
+const SHIFTED_KEYS: [(&str, &str); 21] = [
+    ("!", "1"), ("@", "2"), ("#", "3"), ("$", "4"), ("%", "5"), ("^", "6"), ("&", "7"),
+    ("*", "8"), ("(", "9"), (")", "0"), ("_", "-"), ("+", "="), ("{", "["), ("}", "]"),
+    ("|", "\\"), (":", ";"), ("\"", "'"), ("<", ","), (">", "."), ("?", "/"), ("~", "`"),
+];
+
+// Unshifted key name for a key name that needs Shift held, like `!` (Shift+1) or `H` (Shift+h)
+pub(crate) fn shifted_key_base(key_name: &str) -> Option<String> {
+    let mut chars = key_name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c.to_ascii_lowercase().to_string()),
+        _ => SHIFTED_KEYS.iter()
+            .find(|(shifted, _)| *shifted == key_name)
+            .map(|(_, base)| base.to_string()),
+    }
+}
+
+// A key-down named by a shifted character (hand-written files, old recordings without a vk)
+// gets its own Shift press around it, unless Shift is already down from recorded shift events.
+// The hook records real Shift presses as separate events in order, so those need nothing extra.
+pub(crate) fn needs_synthetic_shift(event: &MacroEvent, shift_held: bool) -> bool {
+    matches!(event.event_type, EventType::KeyDown)
+        && !shift_held
+        && event.data.get("vk_code").is_none()
+        && event.data.get("key_name").and_then(|k| k.as_str()).and_then(shifted_key_base).is_some()
+}
+
+// Left Shift's set-1 scan code, for the Shift press wrapped around shifted key names
+const SHIFT_SCAN_CODE: u16 = 0x2A;
+
+// Whether a Shift key is among the presses the replay hasn't released yet. Decided from the
+// replayed events alone, so what the user happens to hold doesn't change the output.
+fn shift_held(held: &[MacroEvent]) -> bool {
+    held.iter().any(|press| {
+        matches!(press.event_type, EventType::KeyDown) && match press.data.get("vk_code").and_then(|vk| vk.as_u64()) {
+            Some(vk) => [0x10, 0xA0, 0xA1].contains(&vk),
+            None => press.data.get("key_name").and_then(|k| k.as_str()) == Some("shift"),
+        }
+    })
+}
+
+// MAKELPARAM(x, y) for mouse messages
+pub(crate) fn mouse_lparam(x: i32, y: i32) -> isize {
+    (((y as u32 & 0xFFFF) << 16) | (x as u32 & 0xFFFF)) as i32 as isize
+}
+
+// (down message, up message, MK_* flag held while down, XBUTTON high word for both) for a recorded button code
+pub(crate) fn button_messages(button: u32) -> Option<(u32, u32, usize, usize)> {
+    match button {
+        1 => Some((WM_LBUTTONDOWN, WM_LBUTTONUP, 0x0001, 0)),
+        2 => Some((WM_RBUTTONDOWN, WM_RBUTTONUP, 0x0002, 0)),
+        3 => Some((WM_MBUTTONDOWN, WM_MBUTTONUP, 0x0010, 0)),
+        4 => Some((WM_XBUTTONDOWN, WM_XBUTTONUP, 0x0020, (XBUTTON1 as usize) << 16)),
+        5 => Some((WM_XBUTTONDOWN, WM_XBUTTONUP, 0x0040, (XBUTTON2 as usize) << 16)),
+        _ => None,
+    }
+}
+
+// The message a window gets for the second press of a double-click (with CS_DBLCLKS)
+pub(crate) fn double_click_message(button: u32) -> Option<u32> {
+    match button {
+        1 => Some(WM_LBUTTONDBLCLK),
+        2 => Some(WM_RBUTTONDBLCLK),
+        3 => Some(WM_MBUTTONDBLCLK),
+        4 | 5 => Some(WM_XBUTTONDBLCLK),
+        _ => None,
+    }
+}
+
+// Whether `event` is the second press of a double-click: flagged when recorded, or (for files
+// from before the flag) the same button pressed again within `window` of `previous_press`,
+// given as (button, timestamp)
+fn completes_double_click(event: &MacroEvent, previous_press: Option<(i64, f64)>, window: Duration) -> bool {
+    if !matches!(event.event_type, EventType::MouseDown) {
+        return false;
+    }
+    if event.is_double_click() {
+        return true;
+    }
+    let button = event.data.get("button").and_then(|b| b.as_i64());
+    match (previous_press, button) {
+        (Some((previous_button, at)), Some(button)) => {
+            previous_button == button && event.timestamp - at <= window.as_secs_f64()
+        }
+        _ => false,
+    }
+}
+
+// Settings and shared counters handed to the playback thread
+struct PlaybackContext {
+    backend: Arc<dyn InputBackend>,
+    // f32 bits, so set_speed reaches a running playback
+    speed: Arc<AtomicU32>,
+    stop_signal: Arc<AtomicBool>,
+    pause_signal: Arc<AtomicBool>,
+    missing_field_policy: MissingFieldPolicy,
+    skipped_events: Arc<AtomicUsize>,
+    muted_tracks: HashSet<String>,
+    seek_request: Arc<Mutex<Option<usize>>>,
+    activation_settle: Duration,
+    double_clicks: bool,
+    timing_mode: TimingMode,
+    target_window: Option<isize>,
+    pause_on_error: bool,
+    fault: Arc<Mutex<Option<PlaybackFault>>>,
+    fault_action: Arc<Mutex<Option<FaultAction>>>,
+    click_hold: Duration,
+    cursor_method: CursorMethod,
+    smooth_motion_step: Option<Duration>,
+    humanize: Option<HumanizeSettings>,
+    position: Arc<AtomicUsize>,
+    repeat_count: usize,
+    loop_delay: Duration,
+    current_loop: Arc<AtomicUsize>,
+    failsafe_threshold: Option<i32>,
+    failsafe_tripped: Arc<AtomicBool>,
+    playback_end: Arc<Mutex<Option<PlaybackEnd>>>,
+    // Where the first loop begins; later loops start from the top
+    start_index: usize,
+}
+
+pub const DEFAULT_FAILSAFE_THRESHOLD_PX: i32 = 25;
+
+// When the speed changes mid-run, re-anchor the schedule at the present moment: returns the
+// macro timestamp reached so far at `old_speed` (the new base) and the settle delay still owed.
+// Only the time after that point is stretched or compressed by the new speed.
+fn rebase_for_speed(base_timestamp: f64, elapsed: Duration, settle_delay: Duration, old_speed: f32) -> (f64, Duration) {
+    let played = elapsed.saturating_sub(settle_delay);
+    (
+        base_timestamp + played.as_secs_f64() * old_speed as f64,
+        settle_delay.saturating_sub(elapsed),
+    )
+}
+
+fn cursor_diverged(expected: (i32, i32), actual: (i32, i32), threshold_px: i32) -> bool {
+    let dx = (actual.0 - expected.0) as f64;
+    let dy = (actual.1 - expected.1) as f64;
+    dx.hypot(dy) > threshold_px as f64
+}
+
 #[derive(Debug, Clone)]
 pub enum PlayerState {
     Idle,
@@ -18,100 +300,481 @@ pub enum PlayerState {
 }
 
 pub struct MacroPlayer {
+    // Injects the replayed input; the OS's own unless replaced with set_backend
+    backend: Arc<dyn InputBackend>,
     events: Vec<MacroEvent>,
     state: PlayerState,
-    current_position: usize,
+    // Shared with the playback thread, which advances it as events are replayed
+    current_position: Arc<AtomicUsize>,
     start_time: Option<Instant>,
     pause_start: Option<Instant>,
     total_pause_time: Duration,
-    playback_speed: f32,
+    playback_speed: Arc<AtomicU32>,
     stop_signal: Arc<AtomicBool>,
+    pause_signal: Arc<AtomicBool>,
     playback_thread: Option<thread::JoinHandle<()>>,
+    muted_tracks: HashSet<String>,
+    missing_field_policy: MissingFieldPolicy,
+    skipped_events: Arc<AtomicUsize>,
+    click_hold: Duration,
+    seek_request: Arc<Mutex<Option<usize>>>,
+    activation_settle: Duration,
+    double_clicks: bool,
+    timing_mode: TimingMode,
+    target_window: Option<isize>,
+    pause_on_error: bool,
+    fault: Arc<Mutex<Option<PlaybackFault>>>,
+    fault_action: Arc<Mutex<Option<FaultAction>>>,
+    // How many times start() plays the events; 0 repeats until stopped
+    repeat_count: usize,
+    loop_delay: Duration,
+    // 1-based iteration the playback thread is on
+    current_loop: Arc<AtomicUsize>,
+    // Abort when the real cursor strays this many pixels from where playback put it
+    failsafe_threshold: Option<i32>,
+    failsafe_tripped: Arc<AtomicBool>,
+    // Set by the playback thread when it ends on its own, until taken
+    playback_end: Arc<Mutex<Option<PlaybackEnd>>>,
+    cursor_method: CursorMethod,
+    // Time between synthesized cursor steps when gliding between recorded moves; None jumps
+    smooth_motion_step: Option<Duration>,
+    // Random jitter on timing and click positions; None replays exactly as recorded
+    humanize: Option<HumanizeSettings>,
+    // Registered system-wide only while the playback thread runs
+    emergency_hotkey: Option<Hotkey>,
+    emergency_triggered: Arc<AtomicBool>,
+    // .mcr version of the loaded file (1 when it had no version header)
+    format_version: u32,
+}
+
+impl Default for MacroPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MacroPlayer {
+    /// An idle player with no events, playing once at normal speed
     pub fn new() -> Self {
         Self {
+            backend: crate::backend::native_backend(),
             events: Vec::new(),
             state: PlayerState::Idle,
-            current_position: 0,
+            current_position: Arc::new(AtomicUsize::new(0)),
             start_time: None,
             pause_start: None,
             total_pause_time: Duration::ZERO,
-            playback_speed: 1.0,
+            playback_speed: Arc::new(AtomicU32::new(1.0f32.to_bits())),
             stop_signal: Arc::new(AtomicBool::new(false)),
+            pause_signal: Arc::new(AtomicBool::new(false)),
             playback_thread: None,
+            muted_tracks: HashSet::new(),
+            missing_field_policy: MissingFieldPolicy::Skip,
+            skipped_events: Arc::new(AtomicUsize::new(0)),
+            click_hold: Duration::from_millis(50),
+            seek_request: Arc::new(Mutex::new(None)),
+            activation_settle: Duration::ZERO,
+            double_clicks: true,
+            timing_mode: TimingMode::Recorded,
+            target_window: None,
+            pause_on_error: false,
+            fault: Arc::new(Mutex::new(None)),
+            fault_action: Arc::new(Mutex::new(None)),
+            repeat_count: 1,
+            loop_delay: Duration::ZERO,
+            current_loop: Arc::new(AtomicUsize::new(0)),
+            failsafe_threshold: None,
+            failsafe_tripped: Arc::new(AtomicBool::new(false)),
+            playback_end: Arc::new(Mutex::new(None)),
+            cursor_method: CursorMethod::SetCursorPos,
+            smooth_motion_step: None,
+            humanize: None,
+            emergency_hotkey: None,
+            emergency_triggered: Arc::new(AtomicBool::new(false)),
+            format_version: crate::events::MCR_FORMAT_VERSION,
         }
     }
     
+    /// Loads a .mcr or .mcr.gz file, replacing the current events
     pub fn load_from_file(&mut self, path: &str) -> std::result::Result<usize, Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(path)?;
-        self.events.clear();
+        let content = crate::events::read_mcr_text(path)?;
+        let (version, mut events) = crate::events::parse_mcr(&content);
         
-        for line in content.lines() {
-            if let Some(event) = MacroEvent::from_mcr_line(line) {
-                self.events.push(event);
-            }
+        if let Some(offset) = crate::events::normalize_timestamps(&mut events) {
+            log::warn!("{} had negative timestamps; shifted the timeline by {:.3}s", path, offset);
         }
         
-        self.events.sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap());
+        let count = self.load_events(events);
+        self.format_version = version;
+        log::info!("Loaded {} events from {} (.mcr v{})", count, path, version);
+        Ok(count)
+    }
+    
+    /// .mcr version of the loaded file (1 for files without a version header)
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+    
+    /// Loads events saved with `MacroRecorder::save_to_json`, or a portable JSON export
+    pub fn load_from_json(&mut self, path: &str) -> std::result::Result<usize, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path)?;
+        let document: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))?;
+        let mut events: Vec<MacroEvent> = if crate::portable::is_portable(&document) {
+            crate::portable::from_portable(&document)?
+        } else {
+            serde_json::from_value(document)?
+        };
+        
+        if let Some(offset) = crate::events::normalize_timestamps(&mut events) {
+            log::warn!("{} had negative timestamps; shifted the timeline by {:.3}s", path, offset);
+        }
         
-        self.current_position = 0;
-        self.state = PlayerState::Idle;
+        let count = self.load_events(events);
+        log::info!("Loaded {} events from {}", count, path);
+        Ok(count)
+    }
+    
+    /// Writes the events as a script for another automation tool
+    pub fn export(&self, format: ExportFormat, path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        export::export_file(&self.events, format, path)
+    }
+    
+    /// Writes the events as an AutoHotkey v2 script
+    pub fn export_ahk(&self, path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        self.export(ExportFormat::Ahk, path)
+    }
+    
+    /// Writes one CSV row per event: index,timestamp,type,x,y,button,key,delta
+    pub fn export_csv(&self, path: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        self.export(ExportFormat::Csv, path)
+    }
+    
+    /// Scan the loaded macro for problems worth confirming before it runs
+    pub fn preflight(&self) -> Vec<Warning> {
+        preflight::check(&self.events, self.backend.screen_bounds(), |key| {
+            Self::key_name_to_vk_code(key).is_some()
+        })
+    }
+    
+    /// Checks that every key and button press in the loaded macro has a matching release
+    pub fn validate(&self) -> ValidationReport {
+        let (unreleased, unmatched_releases) = crate::events::unbalanced_inputs(&self.events);
+        let describe = |indices: Vec<usize>| -> Vec<DanglingInput> {
+            indices.into_iter()
+                .map(|index| DanglingInput { index, description: self.events[index].get_description() })
+                .collect()
+        };
+        ValidationReport {
+            unreleased: describe(unreleased),
+            unmatched_releases: describe(unmatched_releases),
+        }
+    }
+    
+    /// Appends a release at the end of the macro for every press that lacks one; returns how
+    /// many were added
+    pub fn repair(&mut self) -> usize {
+        let held = crate::events::held_inputs(&self.events);
+        let end = self.events.last().map(|event| event.timestamp).unwrap_or(0.0);
+        let releases = crate::events::releases_after(&self.events, held.iter().rev(), end);
+        self.events.extend(releases);
+        held.len()
+    }
+    
+    /// Moves the whole macro so its first event is at t=0, dropping any wait before it (left
+    /// behind by merges and edits). Returns the seconds cut, or None if it already started at 0.
+    pub fn rebase(&mut self) -> Option<f64> {
+        crate::events::rebase_timestamps(&mut self.events)
+    }
+    
+    /// Replace the loaded macro with events already in memory (e.g. a fresh recording)
+    pub fn load_events(&mut self, events: Vec<MacroEvent>) -> usize {
+        self.events = events;
+        self.format_version = crate::events::MCR_FORMAT_VERSION;
+        crate::events::sort_events(&mut self.events);
         
-        log::info!("Loaded {} events from {}", self.events.len(), path);
-        Ok(self.events.len())
+        self.current_position.store(0, Ordering::Relaxed);
+        self.state = PlayerState::Idle;
+        self.events.len()
     }
     
+    /// Playback speed multiplier, clamped to 0.1..=10. Also applies to a running playback,
+    /// from the next wait between events.
+    #[allow(clippy::manual_clamp)]
     pub fn set_speed(&mut self, speed: f32) {
-        self.playback_speed = speed.max(0.1).min(10.0);
+        // Not clamp(): that passes NaN through, and a NaN speed can't be turned into a wait
+        self.playback_speed.store(speed.max(0.1).min(10.0).to_bits(), Ordering::Relaxed);
+    }
+    
+    pub fn get_speed(&self) -> f32 {
+        f32::from_bits(self.playback_speed.load(Ordering::Relaxed))
+    }
+    
+    pub fn set_missing_field_policy(&mut self, policy: MissingFieldPolicy) {
+        self.missing_field_policy = policy;
+    }
+    
+    /// How long synthetic clicks keep the button pressed between down and up
+    pub fn set_click_hold_ms(&mut self, hold_ms: u64) {
+        self.click_hold = Duration::from_millis(hold_ms);
+    }
+    
+    /// Press and release a button at a point, e.g. for hand-made or autoclicker clicks
+    pub fn click_at(&self, x: i32, y: i32, button: u32) -> Result<(), SendError> {
+        Self::send_click(&*self.backend, x, y, button, self.click_hold, self.cursor_method)
+    }
+    
+    /// Replay into one window with posted messages instead of global SendInput (None = SendInput)
+    pub fn set_target_window(&mut self, hwnd: Option<isize>) {
+        self.target_window = hwnd;
+    }
+    
+    /// Halt on a failed event until the user retries or skips it, instead of moving on
+    pub fn set_pause_on_error(&mut self, enabled: bool) {
+        self.pause_on_error = enabled;
+    }
+    
+    /// The event playback is currently halted on, if any
+    pub fn get_fault(&self) -> Option<PlaybackFault> {
+        self.fault.lock().ok().and_then(|fault| fault.clone())
+    }
+    
+    pub fn resolve_fault(&mut self, action: FaultAction) {
+        if self.get_fault().is_none() {
+            return;
+        }
+        if let Ok(mut pending) = self.fault_action.lock() {
+            *pending = Some(action);
+        }
+        self.resume();
+    }
+    
+    /// Only applies to SendInput playback; posted messages never move the real cursor
+    pub fn set_failsafe(&mut self, threshold_px: Option<i32>) {
+        self.failsafe_threshold = threshold_px;
+    }
+    
+    /// True once after playback aborted because the user moved the mouse
+    pub fn take_failsafe_tripped(&self) -> bool {
+        self.failsafe_tripped.swap(false, Ordering::Relaxed)
+    }
+    
+    /// Replays through another input backend from the next start
+    pub fn set_backend(&mut self, backend: Arc<dyn InputBackend>) {
+        self.backend = backend;
+    }
+    
+    pub fn set_cursor_method(&mut self, method: CursorMethod) {
+        self.cursor_method = method;
+    }
+    
+    /// Glide the cursor between consecutive moves at `rate_hz` steps per second; the stored
+    /// events are untouched. None replays the recorded points only.
+    pub fn set_smooth_motion(&mut self, rate_hz: Option<u32>) {
+        self.smooth_motion_step = rate_hz
+            .filter(|rate| *rate > 0)
+            .map(|rate| Duration::from_secs_f64(1.0 / rate as f64));
+    }
+    
+    /// Jitters waits and click positions within `settings` on every playback from now on;
+    /// None replays exactly. The loaded events are never changed.
+    pub fn set_humanize(&mut self, settings: Option<HumanizeSettings>) {
+        self.humanize = settings;
+    }
+    
+    pub fn set_emergency_hotkey(&mut self, hotkey: Option<Hotkey>) {
+        self.emergency_hotkey = hotkey;
+    }
+    
+    /// True once after the emergency stop hotkey halted playback
+    pub fn take_emergency_stop(&self) -> bool {
+        self.emergency_triggered.swap(false, Ordering::SeqCst)
+    }
+    
+    /// 0 loops forever (until stopped)
+    pub fn set_repeat_count(&mut self, count: usize) {
+        self.repeat_count = count;
+    }
+    
+    /// Idle time between the end of one iteration and the start of the next
+    pub fn set_loop_delay(&mut self, delay: Duration) {
+        self.loop_delay = delay;
+    }
+    
+    pub fn get_current_loop(&self) -> usize {
+        self.current_loop.load(Ordering::Relaxed)
+    }
+    
+    pub fn set_timing_mode(&mut self, mode: TimingMode) {
+        self.timing_mode = mode;
+    }
+    
+    /// Extra wait after a window switch so the newly focused app is ready for input
+    pub fn set_activation_settle(&mut self, settle: Duration) {
+        self.activation_settle = settle;
+    }
+    
+    /// Replay double-clicks as double-clicks even when slowed down: the second press is sent
+    /// within the system double-click time, and posted replay sends the DBLCLK message
+    pub fn set_double_clicks(&mut self, enabled: bool) {
+        self.double_clicks = enabled;
+    }
+    
+    /// Number of events skipped in the current/last run because they were malformed
+    pub fn get_skipped_events(&self) -> usize {
+        self.skipped_events.load(Ordering::Relaxed)
+    }
+    
+    pub fn set_track_muted(&mut self, track: &str, muted: bool) {
+        if muted {
+            self.muted_tracks.insert(track.to_string());
+        } else {
+            self.muted_tracks.remove(track);
+        }
+    }
+    
+    pub fn is_track_muted(&self, track: &str) -> bool {
+        self.muted_tracks.contains(track)
     }
     
+    pub fn get_tracks(&self) -> Vec<String> {
+        crate::events::collect_tracks(&self.events)
+    }
+    
+    /// Plays the events on a background thread; returns immediately
     pub fn start(&mut self) {
+        self.start_from(0);
+    }
+    
+    /// Like `start`, but the first loop begins at event `index` with the timing from there on
+    /// unchanged. Keys and buttons that are down at that point in the macro are pressed first.
+    pub fn start_from(&mut self, index: usize) {
         if !self.events.is_empty() {
             self.stop();
+            let start_index = index.min(self.events.len() - 1);
             
             self.state = PlayerState::Playing;
-            self.current_position = 0;
+            self.current_position.store(start_index, Ordering::Relaxed);
+            self.current_loop.store(0, Ordering::Relaxed);
+            self.failsafe_tripped.store(false, Ordering::Relaxed);
+            self.emergency_triggered.store(false, Ordering::SeqCst);
             self.start_time = Some(Instant::now());
             self.pause_start = None;
             self.total_pause_time = Duration::ZERO;
             self.stop_signal.store(false, Ordering::Relaxed);
+            self.pause_signal.store(false, Ordering::Relaxed);
+            self.skipped_events.store(0, Ordering::Relaxed);
+            
+            if let Ok(mut request) = self.seek_request.lock() {
+                *request = None;
+            }
+            if let Ok(mut fault) = self.fault.lock() {
+                *fault = None;
+            }
+            if let Ok(mut action) = self.fault_action.lock() {
+                *action = None;
+            }
+            if let Ok(mut end) = self.playback_end.lock() {
+                *end = None;
+            }
             
             let events = self.events.clone();
-            let speed = self.playback_speed;
-            let stop_signal = self.stop_signal.clone();
+            let context = PlaybackContext {
+                backend: self.backend.clone(),
+                speed: self.playback_speed.clone(),
+                stop_signal: self.stop_signal.clone(),
+                pause_signal: self.pause_signal.clone(),
+                missing_field_policy: self.missing_field_policy,
+                skipped_events: self.skipped_events.clone(),
+                muted_tracks: self.muted_tracks.clone(),
+                seek_request: self.seek_request.clone(),
+                activation_settle: self.activation_settle,
+                double_clicks: self.double_clicks,
+                timing_mode: self.timing_mode,
+                target_window: self.target_window,
+                pause_on_error: self.pause_on_error,
+                fault: self.fault.clone(),
+                fault_action: self.fault_action.clone(),
+                click_hold: self.click_hold,
+                cursor_method: self.cursor_method,
+                smooth_motion_step: self.smooth_motion_step,
+                humanize: self.humanize,
+                position: self.current_position.clone(),
+                repeat_count: self.repeat_count,
+                loop_delay: self.loop_delay,
+                current_loop: self.current_loop.clone(),
+                failsafe_threshold: self.failsafe_threshold.filter(|_| self.target_window.is_none()),
+                failsafe_tripped: self.failsafe_tripped.clone(),
+                playback_end: self.playback_end.clone(),
+                start_index,
+            };
+            
+            #[cfg(windows)]
+            let emergency_stop = self.emergency_hotkey.and_then(|hotkey| {
+                match EmergencyStop::register(hotkey, self.stop_signal.clone(), self.emergency_triggered.clone()) {
+                    Ok(emergency_stop) => Some(emergency_stop),
+                    Err(e) => {
+                        log::warn!("Emergency stop hotkey unavailable: {}", e);
+                        None
+                    }
+                }
+            });
+            // Global hotkeys are Win32-only so far
+            #[cfg(not(windows))]
+            if self.emergency_hotkey.is_some() {
+                log::warn!("Emergency stop hotkey isn't supported on this platform yet");
+            }
             
             let handle = thread::spawn(move || {
-                Self::play_events(events, speed, stop_signal);
+                // Held for the whole run; dropping it unregisters the hotkey
+                #[cfg(windows)]
+                let _emergency_stop = emergency_stop;
+                Self::play_events(events, context);
             });
             
             self.playback_thread = Some(handle);
             
-            log::info!("Playback started with {} events at {}x speed", self.events.len(), self.playback_speed);
+            log::info!("Playback started with {} events at {}x speed", self.events.len(), self.get_speed());
         }
     }
     
+    /// Starts from the first event at or after `seconds` into the macro
+    pub fn start_from_time(&mut self, seconds: f64) {
+        let index = self.events.partition_point(|event| event.timestamp < seconds);
+        self.start_from(index);
+    }
+    
     pub fn pause(&mut self) {
         if matches!(self.state, PlayerState::Playing) {
             self.state = PlayerState::Paused;
             self.pause_start = Some(Instant::now());
+            self.pause_signal.store(true, Ordering::Relaxed);
             log::info!("Playback paused");
         }
     }
     
     pub fn resume(&mut self) {
-        if matches!(self.state, PlayerState::Paused) {
+        if matches!(self.get_state(), PlayerState::Paused) {
             self.state = PlayerState::Playing;
             if let Some(pause_start) = self.pause_start.take() {
                 self.total_pause_time += pause_start.elapsed();
             }
+            self.pause_signal.store(false, Ordering::Relaxed);
             log::info!("Playback resumed");
         }
     }
     
+    /// Block until the playback thread finishes on its own
+    pub fn wait(&mut self) {
+        if let Some(handle) = self.playback_thread.take() {
+            let _ = handle.join();
+        }
+        self.state = PlayerState::Stopped;
+    }
+    
+    /// Stops playback and waits for the playback thread to exit
     pub fn stop(&mut self) {
         self.stop_signal.store(true, Ordering::Relaxed);
+        self.pause_signal.store(false, Ordering::Relaxed);
         
         if let Some(handle) = self.playback_thread.take() {
             let _ = handle.join();
@@ -121,209 +784,758 @@ impl MacroPlayer {
         if let Some(pause_start) = self.pause_start.take() {
             self.total_pause_time += pause_start.elapsed();
         }
+        // Whoever called stop() doesn't need telling that playback ended
+        if let Ok(mut end) = self.playback_end.lock() {
+            *end = None;
+        }
         log::info!("Playback stopped");
     }
     
+    /// How playback ended, once, after the thread finished by itself rather than through
+    /// `stop`. The thread is joined and the player is Stopped when this returns Some.
+    pub fn take_playback_end(&mut self) -> Option<PlaybackEnd> {
+        let end = self.playback_end.lock().ok()?.take()?;
+        if let Some(handle) = self.playback_thread.take() {
+            let _ = handle.join();
+        }
+        self.state = PlayerState::Stopped;
+        Some(end)
+    }
+    
+    /// Also Paused while a failed event waits on `resolve_fault`, a pause the playback thread
+    /// enters on its own
     pub fn get_state(&self) -> PlayerState {
-        self.state.clone()
+        match self.state {
+            PlayerState::Playing if self.pause_signal.load(Ordering::Relaxed) => PlayerState::Paused,
+            ref state => state.clone(),
+        }
     }
     
+    /// Index of the next event to play within the current loop
     pub fn get_current_position(&self) -> usize {
-        self.current_position
+        self.current_position.load(Ordering::Relaxed)
     }
     
     pub fn get_total_events(&self) -> usize {
         self.events.len()
     }
     
-    fn play_events(events: Vec<MacroEvent>, speed: f32, stop_signal: Arc<AtomicBool>) {
-        if events.is_empty() {
-            return;
+    pub fn current_event_index(&self) -> usize {
+        self.current_position.load(Ordering::Relaxed)
+    }
+    
+    /// Runs just the event at the current position, right away, and advances past it. Returns
+    /// its index, the event and how sending it went; None at the end of the macro or while the
+    /// playback thread is running.
+    pub fn step(&mut self) -> Option<(usize, MacroEvent, Result<(), ReplayError>)> {
+        if self.playback_thread.as_ref().is_some_and(|thread| !thread.is_finished()) {
+            return None;
         }
+        let index = self.current_position.load(Ordering::Relaxed);
+        let event = self.events.get(index)?.clone();
         
-        let start_time = Instant::now();
-        let mut _last_event_time = 0.0;
-        
-        for (_index, event) in events.iter().enumerate() {
-            if stop_signal.load(Ordering::Relaxed) {
-                log::info!("Playback interrupted by stop signal");
-                return;
-            }
-            
-            let target_time = Duration::from_secs_f64(event.timestamp / speed as f64);
-            let current_time = start_time.elapsed();
-            
-            if target_time > current_time {
-                let wait_time = target_time - current_time;
-                let sleep_interval = Duration::from_millis(10);
-                let mut remaining = wait_time;
-                
-                while remaining > Duration::ZERO && !stop_signal.load(Ordering::Relaxed) {
-                    let sleep_duration = remaining.min(sleep_interval);
-                    thread::sleep(sleep_duration);
-                    remaining = remaining.saturating_sub(sleep_duration);
-                }
-                
-                if stop_signal.load(Ordering::Relaxed) {
-                    log::info!("Playback interrupted during wait");
-                    return;
-                }
+        let result = if self.muted_tracks.contains(event.get_source()) {
+            Ok(())
+        } else {
+            match self.target_window {
+                Some(hwnd) => Self::post_event(&*self.backend, hwnd, &event),
+                None => Self::execute_event(&*self.backend, &event, self.click_hold, self.cursor_method, shift_held(&crate::events::held_inputs(&self.events[..index]))),
             }
-            
-            Self::execute_event(event);
-            
-            _last_event_time = event.timestamp;
-        }
-        
-        log::info!("Playback completed");
+        };
+        self.current_position.store(index + 1, Ordering::Relaxed);
+        Some((index, event, result))
     }
     
-    fn execute_event(event: &MacroEvent) {
-        unsafe {
-            match event.event_type {
-                EventType::KeyDown => {
-                    if let Some(key_name) = event.data.get("key_name") {
-                        if let Some(key_str) = key_name.as_str() {
-                            if let Some(vk_code) = Self::key_name_to_vk_code(key_str) {
-                                Self::send_key_input(vk_code, true);
-                            }
-                        }
-                    }
-                }
-                EventType::KeyUp => {
-                    if let Some(key_name) = event.data.get("key_name") {
-                        if let Some(key_str) = key_name.as_str() {
-                            if let Some(vk_code) = Self::key_name_to_vk_code(key_str) {
-                                Self::send_key_input(vk_code, false);
-                            }
-                        }
-                    }
-                }
-                EventType::MouseMove => {
-                    if let (Some(x), Some(y)) = (event.data.get("x"), event.data.get("y")) {
-                        if let (Some(x_val), Some(y_val)) = (x.as_i64(), y.as_i64()) {
-                            Self::send_mouse_move(x_val as i32, y_val as i32);
-                        }
-                    }
-                }
-                EventType::MouseDown => {
-                    if let (Some(x), Some(y), Some(button)) = (
-                        event.data.get("x"),
-                        event.data.get("y"),
-                        event.data.get("button")
-                    ) {
-                        if let (Some(x_val), Some(y_val), Some(btn_val)) = (
-                            x.as_i64(),
-                            y.as_i64(),
-                            button.as_u64()
-                        ) {
-                            Self::send_mouse_click(x_val as i32, y_val as i32, btn_val as u32, true);
-                        }
-                    }
-                }
-                EventType::MouseUp => {
-                    if let (Some(x), Some(y), Some(button)) = (
-                        event.data.get("x"),
-                        event.data.get("y"),
-                        event.data.get("button")
-                    ) {
-                        if let (Some(x_val), Some(y_val), Some(btn_val)) = (
-                            x.as_i64(),
-                            y.as_i64(),
-                            button.as_u64()
-                        ) {
-                            Self::send_mouse_click(x_val as i32, y_val as i32, btn_val as u32, false);
-                        }
-                    }
-                }
-                EventType::MouseScroll => {
-                    if let (Some(x), Some(y), Some(delta)) = (
-                        event.data.get("x"),
-                        event.data.get("y"),
-                        event.data.get("delta")
-                    ) {
-                        if let (Some(x_val), Some(y_val), Some(delta_val)) = (
-                            x.as_i64(),
-                            y.as_i64(),
-                            delta.as_i64()
-                        ) {
-                            Self::send_mouse_scroll(x_val as i32, y_val as i32, delta_val as i32);
-                        }
-                    }
-                }
+    /// Move the cursor to an event; while playing, the scheduler jumps there too
+    pub fn seek(&mut self, index: usize) -> usize {
+        let clamped = index.min(self.events.len().saturating_sub(1));
+        self.current_position.store(clamped, Ordering::Relaxed);
+        
+        if matches!(self.state, PlayerState::Playing | PlayerState::Paused) && self.playback_thread.is_some() {
+            if let Ok(mut request) = self.seek_request.lock() {
+                *request = Some(clamped);
             }
         }
+        
+        clamped
     }
     
-    unsafe fn send_key_input(vk_code: u16, is_down: bool) {
-        let input = INPUT {
-            r#type: INPUT_KEYBOARD,
-            Anonymous: INPUT_0 {
-                ki: KEYBDINPUT {
-                    wVk: VIRTUAL_KEY(vk_code),
-                    wScan: 0,
-                    dwFlags: if is_down { KEYBD_EVENT_FLAGS(0) } else { KEYEVENTF_KEYUP },
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
-            },
-        };
-        
-        SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+    fn take_seek(seek_request: &Mutex<Option<usize>>) -> Option<usize> {
+        seek_request.lock().ok().and_then(|mut request| request.take())
     }
     
-    unsafe fn send_mouse_move(x: i32, y: i32) {
-        SetCursorPos(x, y);
+    fn seek_pending(seek_request: &Mutex<Option<usize>>) -> bool {
+        seek_request.lock().map(|request| request.is_some()).unwrap_or(false)
     }
     
-    unsafe fn send_mouse_click(x: i32, y: i32, button: u32, is_down: bool) {
-        SetCursorPos(x, y);
-        
-        let flags = match (button, is_down) {
-            (1, true) => MOUSEEVENTF_LEFTDOWN,
-            (1, false) => MOUSEEVENTF_LEFTUP,
-            (2, true) => MOUSEEVENTF_RIGHTDOWN,
-            (2, false) => MOUSEEVENTF_RIGHTUP,
-            (3, true) => MOUSEEVENTF_MIDDLEDOWN,
-            (3, false) => MOUSEEVENTF_MIDDLEUP,
-            _ => return,
-        };
+    fn play_events(events: Vec<MacroEvent>, context: PlaybackContext) {
+        let mut held = Vec::new();
+        let completed = Self::play_loops(&events, &context, &mut held);
+        Self::release_held(&held, &context);
         
-        let input = INPUT {
-            r#type: INPUT_MOUSE,
-            Anonymous: INPUT_0 {
-                mi: MOUSEINPUT {
-                    dx: 0,
-                    dy: 0,
-                    mouseData: 0,
-                    dwFlags: flags,
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
-            },
-        };
-        
-        SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+        if !context.stop_signal.load(Ordering::Relaxed) {
+            if let Ok(mut end) = context.playback_end.lock() {
+                *end = Some(if completed { PlaybackEnd::Completed } else { PlaybackEnd::Aborted });
+            }
+        }
     }
     
-    unsafe fn send_mouse_scroll(x: i32, y: i32, delta: i32) {
-        SetCursorPos(x, y);
+    // True when every loop played to the end
+    fn play_loops(events: &[MacroEvent], context: &PlaybackContext, held: &mut Vec<MacroEvent>) -> bool {
+        let mut start_index = context.start_index;
+        if start_index > 0 {
+            Self::press_held_before(events, start_index, context, held);
+        }
         
-        let input = INPUT {
-            r#type: INPUT_MOUSE,
-            Anonymous: INPUT_0 {
-                mi: MOUSEINPUT {
-                    dx: 0,
-                    dy: 0,
-                    mouseData: (delta * 120) as i32,
-                    dwFlags: MOUSEEVENTF_WHEEL,
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
-            },
-        };
+        // One generator for the whole run, so each loop gets fresh jitter but a seed still
+        // reproduces the run as a whole
+        let mut humanizer = context.humanize.map(Humanizer::new);
+        let mut completed_loops = 0;
+        loop {
+            context.current_loop.store(completed_loops + 1, Ordering::Relaxed);
+            if !Self::play_pass(events, context, held, &mut humanizer, start_index) {
+                return false;
+            }
+            start_index = 0;
+            completed_loops += 1;
+            
+            if context.repeat_count != 0 && completed_loops >= context.repeat_count {
+                break;
+            }
+            if !Self::wait_loop_delay(context) {
+                log::info!("Playback stopped between loops");
+                return false;
+            }
+            context.position.store(0, Ordering::Relaxed);
+            log::info!("Starting loop {}", completed_loops + 1);
+        }
         
-        SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+        log::info!("Playback completed");
+        true
+    }
+    
+    // Pause between iterations, still honoring pause and stop; false if stopped
+    fn wait_loop_delay(context: &PlaybackContext) -> bool {
+        let mut remaining = context.loop_delay;
+        loop {
+            if context.stop_signal.load(Ordering::Relaxed) {
+                return false;
+            }
+            if context.pause_signal.load(Ordering::Relaxed) && Self::hold_while_paused(context).is_none() {
+                return false;
+            }
+            if remaining.is_zero() {
+                return true;
+            }
+            
+            let sleep_duration = remaining.min(Duration::from_millis(10));
+            thread::sleep(sleep_duration);
+            remaining -= sleep_duration;
+        }
+    }
+    
+    // Whatever playback pressed and never let go of (stopped, aborted or just an unbalanced
+    // macro) is released, so no key or button stays stuck down system-wide
+    fn release_held(held: &[MacroEvent], context: &PlaybackContext) {
+        if held.is_empty() {
+            return;
+        }
+        
+        for press in held.iter().rev() {
+            let mut release = crate::events::release_for(press, press.timestamp);
+            // Let go where the cursor is now rather than jumping back to where it was pressed
+            if let (EventType::MouseUp, None, Some((x, y))) = (&release.event_type, context.target_window, context.backend.cursor_position()) {
+                release.data["x"] = serde_json::Value::from(x);
+                release.data["y"] = serde_json::Value::from(y);
+            }
+            let result = match context.target_window {
+                Some(hwnd) => Self::post_event(&*context.backend, hwnd, &release),
+                None => Self::execute_event(&*context.backend, &release, context.click_hold, context.cursor_method, false),
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to release '{}': {}", press.get_description(), e);
+            }
+        }
+        log::info!("Released {} key(s)/button(s) still held at the end of playback", held.len());
+    }
+    
+    // Starting part-way through, keys the macro would be holding by then (say Shift for a
+    // drag-select) are pressed so what follows behaves as recorded
+    fn press_held_before(events: &[MacroEvent], start_index: usize, context: &PlaybackContext, held: &mut Vec<MacroEvent>) {
+        for press in crate::events::held_inputs(&events[..start_index]) {
+            let result = match context.target_window {
+                Some(hwnd) => Self::post_event(&*context.backend, hwnd, &press),
+                None => Self::execute_event(&*context.backend, &press, context.click_hold, context.cursor_method, shift_held(held)),
+            };
+            match result {
+                Ok(()) => crate::events::track_held(held, &press),
+                Err(e) => log::warn!("Failed to pre-press '{}': {}", press.get_description(), e),
+            }
+        }
+    }
+    
+    // One run through the events from `start_index`; false when playback was stopped or
+    // aborted part-way. `held` tracks the presses sent so far that haven't been released.
+    fn play_pass(events: &[MacroEvent], context: &PlaybackContext, held: &mut Vec<MacroEvent>, humanizer: &mut Option<Humanizer>, start_index: usize) -> bool {
+        if events.is_empty() {
+            return false;
+        }
+        
+        let mut speed = f32::from_bits(context.speed.load(Ordering::Relaxed));
+        let stop_signal = context.stop_signal.clone();
+        let mut start_time = Instant::now();
+        // Starting part-way, the clock starts at that event so the gaps after it keep their length
+        let mut base_timestamp = if start_index > 0 { events[start_index].timestamp } else { 0.0 };
+        let mut base_index = start_index;
+        let mut settle_delay = Duration::ZERO;
+        let mut index = start_index;
+        // Where the cursor should be if nobody but us touched it
+        let mut expected_cursor = context.backend.cursor_position();
+        let monitors = context.backend.monitors();
+        let mut missing_monitors = HashSet::new();
+        // Set only right after a move, so gliding happens between consecutive moves
+        let mut last_move_point: Option<(i32, i32)> = None;
+        let double_click_time = context.backend.double_click_time();
+        // (button, recorded timestamp) of the last press that didn't complete a double-click
+        let mut last_press: Option<(i64, f64)> = None;
+        // When the previous event went out, on the start_time clock
+        let mut last_sent: Option<Duration> = None;
+        // Humanizer drift from the schedule so far, in seconds; each wait adds its own share
+        let mut jitter = 0.0;
+        // Scatter given to the last press, so its release lands on the same spot
+        let mut press_offset: Option<(i32, i32)> = None;
+        
+        while index < events.len() {
+            if stop_signal.load(Ordering::Relaxed) {
+                log::info!("Playback interrupted by stop signal");
+                return false;
+            }
+            
+            if context.pause_signal.load(Ordering::Relaxed) {
+                match Self::hold_while_paused(context) {
+                    // The schedule picks up where it left off rather than rushing to catch up
+                    Some(paused) => {
+                        start_time += paused;
+                        expected_cursor = context.backend.cursor_position();
+                    }
+                    None => {
+                        log::info!("Playback stopped while paused");
+                        return false;
+                    }
+                }
+            }
+            
+            // A seek restarts the clock so the target event fires immediately
+            if let Some(target) = Self::take_seek(&context.seek_request) {
+                index = target.min(events.len() - 1);
+                start_time = Instant::now();
+                base_timestamp = events[index].timestamp;
+                base_index = index;
+                settle_delay = Duration::ZERO;
+                jitter = 0.0;
+                last_move_point = None;
+                context.position.store(index, Ordering::Relaxed);
+                log::info!("Playback jumped to event #{}", index);
+            }
+            
+            let event = &events[index];
+            if context.muted_tracks.contains(event.get_source()) {
+                index += 1;
+                context.position.store(index, Ordering::Relaxed);
+                continue;
+            }
+            
+            let remapped = Self::remap_to_monitor(event, &monitors, &mut missing_monitors);
+            let event = remapped.as_ref().unwrap_or(event);
+            
+            let double_click = context.double_clicks && completes_double_click(event, last_press, double_click_time);
+            // Posted replay needs the flag to send DBLCLK; SendInput leaves that to Windows
+            let flagged = (double_click && !event.is_double_click()).then(|| {
+                let mut flagged = event.clone();
+                flagged.data["double"] = serde_json::Value::Bool(true);
+                flagged
+            });
+            let event = flagged.as_ref().unwrap_or(event);
+            if let EventType::MouseDown = event.event_type {
+                last_press = if double_click { None } else { event.data.get("button").and_then(|b| b.as_i64()).map(|b| (b, event.timestamp)) };
+            }
+            
+            let scattered = humanizer.as_mut().and_then(|humanizer| {
+                let (dx, dy) = match event.event_type {
+                    EventType::MouseDown => *press_offset.insert(humanizer.click_offset()),
+                    EventType::MouseUp => press_offset.take()?,
+                    EventType::Click => humanizer.click_offset(),
+                    _ => return None,
+                };
+                let (x, y) = (event.data.get("x")?.as_i64()?, event.data.get("y")?.as_i64()?);
+                let mut scattered = event.clone();
+                scattered.data["x"] = serde_json::Value::from(x + dx as i64);
+                scattered.data["y"] = serde_json::Value::from(y + dy as i64);
+                Some(scattered)
+            });
+            let event = scattered.as_ref().unwrap_or(event);
+            
+            let glide = match (context.smooth_motion_step, last_move_point, Self::move_target(event)) {
+                (Some(step), Some(from), Some(to)) if context.target_window.is_none() => Some((step, from, to)),
+                _ => None,
+            };
+            
+            let current_speed = f32::from_bits(context.speed.load(Ordering::Relaxed));
+            if current_speed != speed {
+                if context.timing_mode == TimingMode::Recorded {
+                    (base_timestamp, settle_delay) = rebase_for_speed(base_timestamp, start_time.elapsed(), settle_delay, speed);
+                    start_time = Instant::now();
+                    jitter = 0.0;
+                }
+                log::info!("Playback speed changed to {}x", current_speed);
+                speed = current_speed;
+            }
+            
+            let scheduled = match context.timing_mode {
+                TimingMode::Recorded => {
+                    Duration::from_secs_f64(((event.timestamp - base_timestamp) / speed as f64).max(0.0))
+                }
+                TimingMode::FixedInterval(interval) => interval * (index - base_index) as u32,
+            };
+            if let (Some(humanizer), true) = (humanizer.as_mut(), index > base_index) {
+                let gap = match context.timing_mode {
+                    TimingMode::Recorded => {
+                        Duration::from_secs_f64(((event.timestamp - events[index - 1].timestamp) / speed as f64).max(0.0))
+                    }
+                    TimingMode::FixedInterval(interval) => interval,
+                };
+                jitter += humanizer.timing_offset(gap);
+            }
+            let scheduled = Duration::from_secs_f64((scheduled.as_secs_f64() + jitter).max(0.0));
+            let mut target_time = scheduled + settle_delay;
+            // Slowed down, the second press could land outside the double-click time and turn
+            // into two single clicks; send it early instead (the schedule after it is unchanged)
+            if let (true, Some(sent)) = (double_click, last_sent) {
+                target_time = target_time.min(sent + double_click_time / 2);
+            }
+            let current_time = start_time.elapsed();
+            
+            if target_time > current_time {
+                let wait_time = target_time - current_time;
+                let sleep_interval = match glide {
+                    Some((step, _, _)) => step.min(Duration::from_millis(10)),
+                    None => Duration::from_millis(10),
+                };
+                let mut remaining = wait_time;
+                
+                while remaining > Duration::ZERO
+                    && !stop_signal.load(Ordering::Relaxed)
+                    && !Self::seek_pending(&context.seek_request)
+                {
+                    if context.pause_signal.load(Ordering::Relaxed) {
+                        if let Some(paused) = Self::hold_while_paused(context) {
+                            start_time += paused;
+                            expected_cursor = context.backend.cursor_position();
+                        }
+                        continue;
+                    }
+                    
+                    if Self::check_failsafe(context, expected_cursor) {
+                        return false;
+                    }
+                    
+                    let sleep_duration = remaining.min(sleep_interval);
+                    thread::sleep(sleep_duration);
+                    remaining = remaining.saturating_sub(sleep_duration);
+                    
+                    if let Some((_, from, to)) = glide {
+                        let progress = 1.0 - remaining.as_secs_f64() / wait_time.as_secs_f64();
+                        let (x, y) = interpolate_point(from, to, progress);
+                        if context.backend.move_mouse(x, y, context.cursor_method).is_ok()
+                            && context.failsafe_threshold.is_some()
+                        {
+                            expected_cursor = context.backend.cursor_position();
+                        }
+                    }
+                }
+                
+                if stop_signal.load(Ordering::Relaxed) {
+                    log::info!("Playback interrupted during wait");
+                    return false;
+                }
+                
+                if Self::seek_pending(&context.seek_request) {
+                    continue;
+                }
+            }
+            
+            if Self::check_failsafe(context, expected_cursor) {
+                return false;
+            }
+            
+            let result = match context.target_window {
+                Some(hwnd) => Self::post_event(&*context.backend, hwnd, event),
+                None => Self::execute_event(&*context.backend, event, context.click_hold, context.cursor_method, shift_held(held)),
+            };
+            if result.is_ok() {
+                crate::events::track_held(held, event);
+            }
+            last_sent = Some(start_time.elapsed());
+            if context.failsafe_threshold.is_some() && event.data.get("x").is_some() {
+                expected_cursor = context.backend.cursor_position();
+            }
+            last_move_point = Self::move_target(event);
+            
+            if let Err(error) = &result {
+                if context.pause_on_error {
+                    let fault = PlaybackFault {
+                        index,
+                        description: event.get_description(),
+                        error: error.clone(),
+                    };
+                    log::warn!("Playback halted at event #{}: {}", index, error);
+                    
+                    let action = match Self::wait_for_fault_action(fault, context) {
+                        Some(action) => action,
+                        None => {
+                            log::info!("Playback stopped while halted on an error");
+                            return false;
+                        }
+                    };
+                    
+                    // Restart the clock here so the retried (or next) event doesn't try to catch up
+                    start_time = Instant::now();
+                    base_timestamp = event.timestamp;
+                    base_index = index;
+                    settle_delay = Duration::ZERO;
+                    jitter = 0.0;
+                    if action == FaultAction::Skip {
+                        index += 1;
+                        context.position.store(index, Ordering::Relaxed);
+                    }
+                    continue;
+                }
+            }
+            
+            match result {
+                Ok(()) => {}
+                Err(ReplayError::MissingField(field)) => {
+                    let skipped = context.skipped_events.fetch_add(1, Ordering::Relaxed) + 1;
+                    log::warn!(
+                        "Event #{} ({}) is missing '{}' ({} malformed so far)",
+                        index, event.event_type, field, skipped
+                    );
+                    
+                    if context.missing_field_policy == MissingFieldPolicy::Abort {
+                        log::error!("Playback aborted at event #{} due to malformed event", index);
+                        return false;
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to replay '{}': {}", event.get_description(), e);
+                }
+            }
+            
+            // Everything after a window switch is pushed back by the settle delay
+            if matches!(event.event_type, EventType::FocusChange) {
+                settle_delay += context.activation_settle;
+            }
+            
+            // Recorded timing already waits out the dwell before the next event; fixed
+            // intervals would cut it short, so hold the cursor there explicitly
+            if let (EventType::Hover, TimingMode::FixedInterval(_)) = (&event.event_type, context.timing_mode) {
+                let dwell = event.data.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(0);
+                settle_delay += Duration::from_millis(dwell);
+            }
+            
+            // A Delay is a minimum, not an extra: timestamps count toward it (insert_delay already
+            // spaced the following events), so only a shortfall is added. It isn't scaled by speed.
+            if let EventType::Delay = event.event_type {
+                let wait = Duration::from_millis(event.data.get("ms").and_then(|ms| ms.as_u64()).unwrap_or(0));
+                let gap = match (context.timing_mode, events.get(index + 1)) {
+                    (TimingMode::Recorded, Some(next)) => {
+                        Duration::from_secs_f64(((next.timestamp - event.timestamp) / speed as f64).max(0.0))
+                    }
+                    (TimingMode::FixedInterval(interval), Some(_)) => interval,
+                    (_, None) => Duration::ZERO,
+                };
+                settle_delay += wait.saturating_sub(gap);
+            }
+            
+            index += 1;
+            context.position.store(index, Ordering::Relaxed);
+        }
+        
+        true
+    }
+    
+    fn move_target(event: &MacroEvent) -> Option<(i32, i32)> {
+        if !matches!(event.event_type, EventType::MouseMove) {
+            return None;
+        }
+        Some((
+            event.data.get("x")?.as_i64()? as i32,
+            event.data.get("y")?.as_i64()? as i32,
+        ))
+    }
+    
+    // A copy of a mouse event moved onto where its recorded monitor sits now, or None if nothing moved.
+    // Events from a monitor that's no longer attached land on the primary one.
+    fn remap_to_monitor(event: &MacroEvent, monitors: &[Monitor], missing: &mut HashSet<String>) -> Option<MacroEvent> {
+        let name = event.data.get("monitor")?.as_str()?;
+        let recorded = (
+            event.data.get("monitor_left")?.as_i64()? as i32,
+            event.data.get("monitor_top")?.as_i64()? as i32,
+        );
+        let current = match monitors.iter().find(|monitor| monitor.name == name) {
+            Some(monitor) => (monitor.left, monitor.top),
+            None => {
+                if missing.insert(name.to_string()) {
+                    log::warn!("Monitor {} is not connected; replaying its events on the primary monitor", name);
+                }
+                (0, 0)
+            }
+        };
+        if current == recorded {
+            return None;
+        }
+        
+        let x = event.data.get("x")?.as_i64()? as i32;
+        let y = event.data.get("y")?.as_i64()? as i32;
+        let (x, y) = remap_point(x, y, recorded, current);
+        
+        let mut remapped = event.clone();
+        remapped.data["x"] = serde_json::Value::from(x);
+        remapped.data["y"] = serde_json::Value::from(y);
+        Some(remapped)
+    }
+    
+    // Flags and logs a failsafe abort when the real cursor has left where playback last put it
+    fn check_failsafe(context: &PlaybackContext, expected: Option<(i32, i32)>) -> bool {
+        let (threshold, expected) = match (context.failsafe_threshold, expected) {
+            (Some(threshold), Some(expected)) => (threshold, expected),
+            _ => return false,
+        };
+        let actual = match context.backend.cursor_position() {
+            Some(actual) => actual,
+            None => return false,
+        };
+        
+        if !cursor_diverged(expected, actual, threshold) {
+            return false;
+        }
+        log::warn!("Failsafe: cursor moved to {:?}, expected {:?}; aborting playback", actual, expected);
+        context.failsafe_tripped.store(true, Ordering::Relaxed);
+        true
+    }
+    
+    // Block while paused; returns how long playback was held, or None if it was stopped instead
+    fn hold_while_paused(context: &PlaybackContext) -> Option<Duration> {
+        let paused_at = Instant::now();
+        while context.pause_signal.load(Ordering::Relaxed) {
+            if context.stop_signal.load(Ordering::Relaxed) {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Some(paused_at.elapsed())
+    }
+    
+    // Publish the fault and block until the UI picks an action; None means playback was stopped
+    fn wait_for_fault_action(fault: PlaybackFault, context: &PlaybackContext) -> Option<FaultAction> {
+        if let Ok(mut action) = context.fault_action.lock() {
+            *action = None;
+        }
+        // The player reports Paused until resolve_fault resumes it
+        context.pause_signal.store(true, Ordering::Relaxed);
+        if let Ok(mut slot) = context.fault.lock() {
+            *slot = Some(fault);
+        }
+        
+        let action = loop {
+            if context.stop_signal.load(Ordering::Relaxed) {
+                break None;
+            }
+            if let Some(action) = context.fault_action.lock().ok().and_then(|mut action| action.take()) {
+                break Some(action);
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        
+        if let Ok(mut slot) = context.fault.lock() {
+            *slot = None;
+        }
+        action
+    }
+    
+    // `shift_held` is whether the replay itself is holding Shift at this point
+    fn execute_event(backend: &dyn InputBackend, event: &MacroEvent, click_hold: Duration, cursor_method: CursorMethod, shift_held: bool) -> Result<(), ReplayError> {
+        match event.event_type {
+            EventType::KeyDown | EventType::KeyUp => {
+                let vk_code = Self::event_vk_code(event)?;
+                let extended = Self::event_extended(event) || Self::is_extended_key(vk_code);
+                let scan_code = Self::event_scan_code(backend, event, vk_code);
+                let is_down = matches!(event.event_type, EventType::KeyDown);
+                if needs_synthetic_shift(event, shift_held) {
+                    backend.send_key(VK_SHIFT.0, SHIFT_SCAN_CODE, true, false)?;
+                    let sent = backend.send_key(vk_code, scan_code, is_down, extended);
+                    // Let go of Shift even if the key itself didn't go through
+                    backend.send_key(VK_SHIFT.0, SHIFT_SCAN_CODE, false, false)?;
+                    sent?;
+                } else {
+                    backend.send_key(vk_code, scan_code, is_down, extended)?;
+                }
+            }
+            EventType::MouseMove | EventType::Hover => {
+                let x = Self::field_i64(event, "x")?;
+                let y = Self::field_i64(event, "y")?;
+                backend.move_mouse(x as i32, y as i32, cursor_method)?;
+            }
+            EventType::MouseDown | EventType::MouseUp => {
+                let x = Self::field_i64(event, "x")?;
+                let y = Self::field_i64(event, "y")?;
+                let button = Self::field_i64(event, "button")?;
+                let is_down = matches!(event.event_type, EventType::MouseDown);
+                Self::send_mouse_button(backend, x as i32, y as i32, button as u32, is_down, cursor_method)?;
+            }
+            EventType::Click => {
+                let x = Self::field_i64(event, "x")?;
+                let y = Self::field_i64(event, "y")?;
+                let button = Self::field_i64(event, "button")?;
+                Self::send_click(backend, x as i32, y as i32, button as u32, click_hold, cursor_method)?;
+            }
+            EventType::MouseScroll | EventType::MouseHScroll => {
+                let x = Self::field_i64(event, "x")?;
+                let y = Self::field_i64(event, "y")?;
+                let delta = Self::field_i64(event, "delta")?;
+                let horizontal = matches!(event.event_type, EventType::MouseHScroll);
+                backend.move_mouse(x as i32, y as i32, cursor_method)?;
+                backend.send_scroll(delta as i32, horizontal)?;
+            }
+            EventType::TextInput => {
+                backend.send_text(Self::field_str(event, "text")?)?;
+            }
+            EventType::FocusChange | EventType::Delay => {}
+        }
+        
+        Ok(())
+    }
+    
+    // Background replay: messages go straight to the window's queue, so it needn't be focused.
+    // Apps that read raw input or poll key state won't see these.
+    fn post_event(backend: &dyn InputBackend, window: isize, event: &MacroEvent) -> Result<(), ReplayError> {
+        let post = |msg: u32, wparam: usize, lparam: isize| backend.post_message(window, msg, wparam, lparam);
+        let to_client = |x: i64, y: i64| backend.screen_to_client(window, x as i32, y as i32);
+        
+        match event.event_type {
+            EventType::KeyDown | EventType::KeyUp => {
+                let extended = Self::event_extended(event);
+                let vk_code = Self::event_vk_code(event)?;
+                let is_up = matches!(event.event_type, EventType::KeyUp);
+                let scan_code = Self::event_scan_code(backend, event, vk_code) as u32;
+                let msg = if is_up { WM_KEYUP } else { WM_KEYDOWN };
+                post(msg, vk_code as usize, key_lparam(scan_code, extended || Self::is_extended_key(vk_code), is_up))?;
+            }
+            EventType::MouseMove | EventType::Hover => {
+                let (x, y) = to_client(Self::field_i64(event, "x")?, Self::field_i64(event, "y")?);
+                post(WM_MOUSEMOVE, 0, mouse_lparam(x, y))?;
+            }
+            EventType::MouseDown | EventType::MouseUp => {
+                let (x, y) = to_client(Self::field_i64(event, "x")?, Self::field_i64(event, "y")?);
+                let button = Self::field_i64(event, "button")?;
+                if let Some((down, up, held_flag, xbutton)) = button_messages(button as u32) {
+                    if matches!(event.event_type, EventType::MouseDown) {
+                        let down = if event.is_double_click() {
+                            double_click_message(button as u32).unwrap_or(down)
+                        } else {
+                            down
+                        };
+                        post(down, held_flag | xbutton, mouse_lparam(x, y))?;
+                    } else {
+                        post(up, xbutton, mouse_lparam(x, y))?;
+                    }
+                }
+            }
+            EventType::Click => {
+                let (x, y) = to_client(Self::field_i64(event, "x")?, Self::field_i64(event, "y")?);
+                let button = Self::field_i64(event, "button")?;
+                if let Some((down, up, held_flag, xbutton)) = button_messages(button as u32) {
+                    post(down, held_flag | xbutton, mouse_lparam(x, y))?;
+                    post(up, xbutton, mouse_lparam(x, y))?;
+                }
+            }
+            EventType::MouseScroll | EventType::MouseHScroll => {
+                // Wheel messages carry screen coordinates
+                let x = Self::field_i64(event, "x")?;
+                let y = Self::field_i64(event, "y")?;
+                let delta = Self::field_i64(event, "delta")?;
+                let wparam = (delta as i16 as u16 as usize) << 16;
+                let msg = if matches!(event.event_type, EventType::MouseHScroll) { WM_MOUSEHWHEEL } else { WM_MOUSEWHEEL };
+                post(msg, wparam, mouse_lparam(x as i32, y as i32))?;
+            }
+            EventType::TextInput => {
+                for unit in Self::field_str(event, "text")?.encode_utf16() {
+                    post(WM_CHAR, unit as usize, 1)?;
+                }
+            }
+            EventType::FocusChange | EventType::Delay => {}
+        }
+        
+        Ok(())
+    }
+    
+    fn field_str<'a>(event: &'a MacroEvent, name: &'static str) -> Result<&'a str, ReplayError> {
+        event.data.get(name)
+            .and_then(|value| value.as_str())
+            .ok_or(ReplayError::MissingField(name))
+    }
+    
+    fn field_i64(event: &MacroEvent, name: &'static str) -> Result<i64, ReplayError> {
+        event.data.get(name)
+            .and_then(|value| value.as_i64())
+            .ok_or(ReplayError::MissingField(name))
+    }
+    
+    fn key_vk_code(key_name: &str) -> Result<u16, ReplayError> {
+        Self::key_name_to_vk_code(key_name).ok_or_else(|| ReplayError::UnmappedKey(key_name.to_string()))
+    }
+    
+    // Prefer the virtual key captured by the hook; files that predate it only carry the name
+    fn event_vk_code(event: &MacroEvent) -> Result<u16, ReplayError> {
+        match event.data.get("vk_code").and_then(|vk| vk.as_u64()) {
+            Some(vk_code) => Ok(vk_code as u16),
+            None => Self::key_vk_code(Self::field_str(event, "key_name")?),
+        }
+    }
+    
+    // Numpad Enter is only told apart from Enter by this flag, so a bare `char=numenter` implies it
+    fn event_extended(event: &MacroEvent) -> bool {
+        event.data.get("extended").and_then(|e| e.as_bool()).unwrap_or(false)
+            || event.data.get("key_name").and_then(|k| k.as_str()) == Some(crate::events::NUMPAD_ENTER_NAME)
+    }
+    
+    fn event_scan_code(backend: &dyn InputBackend, event: &MacroEvent, vk_code: u16) -> u16 {
+        match event.data.get("scan_code").and_then(|sc| sc.as_u64()) {
+            Some(scan_code) => scan_code as u16,
+            None => backend.scan_code(vk_code),
+        }
+    }
+    
+    fn send_click(backend: &dyn InputBackend, x: i32, y: i32, button: u32, hold: Duration, method: CursorMethod) -> Result<(), SendError> {
+        Self::send_mouse_button(backend, x, y, button, true, method)?;
+        if !hold.is_zero() {
+            thread::sleep(hold);
+        }
+        Self::send_mouse_button(backend, x, y, button, false, method)
+    }
+    
+    fn send_mouse_button(backend: &dyn InputBackend, x: i32, y: i32, button: u32, is_down: bool, method: CursorMethod) -> Result<(), SendError> {
+        backend.move_mouse(x, y, method)?;
+        backend.send_mouse_button(button, is_down)
+    }
+    
+    // Keys on the E0-prefixed part of the keyboard need KEYEVENTF_EXTENDEDKEY to replay faithfully
+    fn is_extended_key(vk_code: u16) -> bool {
+        [
+            VK_APPS.0, VK_LEFT.0, VK_UP.0, VK_RIGHT.0, VK_DOWN.0,
+            VK_INSERT.0, VK_DELETE.0, VK_HOME.0, VK_END.0, VK_PRIOR.0, VK_NEXT.0,
+            VK_LWIN.0, VK_RWIN.0, VK_RCONTROL.0, VK_RMENU.0, VK_DIVIDE.0, VK_NUMLOCK.0,
+        ].contains(&vk_code)
     }
     
     fn key_name_to_vk_code(key_name: &str) -> Option<u16> {
@@ -336,7 +1548,7 @@ impl MacroPlayer {
             }
             key if key.starts_with('f') && key.len() <= 3 => {
                 if let Ok(num) = key[1..].parse::<u16>() {
-                    if num >= 1 && num <= 12 {
+                    if (1..=12).contains(&num) {
                         Some(0x70 + num - 1)
                     } else {
                         None
@@ -347,6 +1559,7 @@ impl MacroPlayer {
             }
             "space" => Some(VK_SPACE.0),
             "enter" => Some(VK_RETURN.0),
+            key if key == crate::events::NUMPAD_ENTER_NAME => Some(VK_RETURN.0),
             "backspace" => Some(VK_BACK.0),
             "tab" => Some(VK_TAB.0),
             "shift" => Some(VK_SHIFT.0),
@@ -363,6 +1576,8 @@ impl MacroPlayer {
             "end" => Some(VK_END.0),
             "page_up" => Some(VK_PRIOR.0),
             "page_down" => Some(VK_NEXT.0),
+            "apps" | "menu" => Some(VK_APPS.0),
+            "sleep" => Some(VK_SLEEP.0),
             ";" => Some(0xBA),
             "=" => Some(0xBB),
             "," => Some(0xBC),
@@ -375,10 +1590,14 @@ impl MacroPlayer {
             "]" => Some(0xDD),
             "'" => Some(0xDE),
             _ => {
-                if key_name.starts_with("vk_") {
-                    key_name[3..].parse::<u16>().ok()
+                if let Some(vk) = key_name.strip_prefix("vk_") {
+                    vk.parse::<u16>().ok()
+                } else if let Some(base) = shifted_key_base(key_name) {
+                    Self::key_name_to_vk_code(&base)
                 } else {
-                    None
+                    crate::events::NUMPAD_KEY_NAMES.iter()
+                        .find(|(_, name)| *name == key_name)
+                        .map(|(vk, _)| *vk as u16)
                 }
             }
         }
@@ -390,3 +1609,740 @@ impl Drop for MacroPlayer {
         self.stop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::EventSink;
+    use crate::events::{TRACK_KEYBOARD, TRACK_MOUSE};
+    use crate::monitors::Monitor;
+    use crate::preflight::ScreenBounds;
+    use serde_json::json;
+    
+    // One call playback made on the backend
+    #[derive(Debug, Clone, PartialEq)]
+    enum Input {
+        Key { vk_code: u16, is_down: bool },
+        Move { x: i32, y: i32 },
+        Button { button: u32, is_down: bool },
+        Scroll { delta: i32, horizontal: bool },
+        Text(String),
+        Post { msg: u32, wparam: usize, lparam: isize },
+    }
+    
+    // Records what playback sends, and when, instead of injecting it
+    #[derive(Default)]
+    struct MockBackend {
+        sent: Mutex<Vec<(Instant, Input)>>,
+        // While set, every send fails with this
+        failure: Mutex<Option<SendError>>,
+        // Set while hooks are installed
+        sink: Mutex<Option<EventSink>>,
+        // Follows playback's moves, unless a test moves it the way a user would
+        cursor: Mutex<Option<(i32, i32)>>,
+    }
+    
+    impl MockBackend {
+        fn send(&self, input: Input) -> Result<(), SendError> {
+            if let Some(error) = self.failure.lock().unwrap().clone() {
+                return Err(error);
+            }
+            self.sent.lock().unwrap().push((Instant::now(), input));
+            Ok(())
+        }
+        
+        fn inputs(&self) -> Vec<Input> {
+            self.sent.lock().unwrap().iter().map(|(_, input)| input.clone()).collect()
+        }
+        
+        fn times(&self) -> Vec<Instant> {
+            self.sent.lock().unwrap().iter().map(|(at, _)| *at).collect()
+        }
+        
+        fn fail_with(&self, error: Option<SendError>) {
+            *self.failure.lock().unwrap() = error;
+        }
+        
+        // Stands in for the user grabbing the mouse
+        fn move_cursor(&self, x: i32, y: i32) {
+            *self.cursor.lock().unwrap() = Some((x, y));
+        }
+        
+        // Stands in for the user's input reaching the hooks; lost unless they're installed
+        fn capture(&self, event: MacroEvent) {
+            if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+                sink(event);
+            }
+        }
+    }
+    
+    impl InputBackend for MockBackend {
+        fn install_hooks(&self, sink: EventSink) -> Result<(), String> {
+            *self.sink.lock().unwrap() = Some(sink);
+            Ok(())
+        }
+        
+        fn uninstall_hooks(&self) {
+            *self.sink.lock().unwrap() = None;
+        }
+        
+        fn send_key(&self, vk_code: u16, _scan_code: u16, is_down: bool, _extended: bool) -> Result<(), SendError> {
+            self.send(Input::Key { vk_code, is_down })
+        }
+        
+        fn send_text(&self, text: &str) -> Result<(), SendError> {
+            self.send(Input::Text(text.to_string()))
+        }
+        
+        fn move_mouse(&self, x: i32, y: i32, _method: CursorMethod) -> Result<(), SendError> {
+            self.send(Input::Move { x, y })?;
+            self.move_cursor(x, y);
+            Ok(())
+        }
+        
+        fn send_mouse_button(&self, button: u32, is_down: bool) -> Result<(), SendError> {
+            self.send(Input::Button { button, is_down })
+        }
+        
+        fn send_scroll(&self, delta: i32, horizontal: bool) -> Result<(), SendError> {
+            self.send(Input::Scroll { delta, horizontal })
+        }
+        
+        fn cursor_position(&self) -> Option<(i32, i32)> {
+            *self.cursor.lock().unwrap()
+        }
+        
+        fn scan_code(&self, _vk_code: u16) -> u16 {
+            0
+        }
+        
+        fn double_click_time(&self) -> Duration {
+            Duration::from_millis(500)
+        }
+        
+        fn screen_bounds(&self) -> ScreenBounds {
+            ScreenBounds { left: 0, top: 0, right: 1920, bottom: 1080 }
+        }
+        
+        fn monitors(&self) -> Vec<Monitor> {
+            Vec::new()
+        }
+        
+        fn post_message(&self, _window: isize, msg: u32, wparam: usize, lparam: isize) -> Result<(), SendError> {
+            self.send(Input::Post { msg, wparam, lparam })
+        }
+        
+        fn screen_to_client(&self, _window: isize, x: i32, y: i32) -> (i32, i32) {
+            (x - 100, y - 100)
+        }
+    }
+    
+    fn player_with(events: Vec<MacroEvent>) -> (MacroPlayer, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::default());
+        let mut player = MacroPlayer::new();
+        player.set_backend(backend.clone());
+        player.load_events(events);
+        (player, backend)
+    }
+    
+    fn key(timestamp: f64, key_name: &str, is_down: bool) -> MacroEvent {
+        let event_type = if is_down { EventType::KeyDown } else { EventType::KeyUp };
+        MacroEvent::with_data(timestamp, event_type, json!({ "key_name": key_name }))
+    }
+    
+    fn mouse_move(timestamp: f64, x: i32, y: i32) -> MacroEvent {
+        MacroEvent::with_data(timestamp, EventType::MouseMove, json!({ "x": x, "y": y }))
+    }
+    
+    // A player that loaded `content` from a .mcr file named after `test`
+    fn player_loading(test: &str, content: &str) -> (MacroPlayer, Result<usize, String>) {
+        let path = std::env::temp_dir().join(format!("{}-{}.mcr", test, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let (mut player, _) = player_with(Vec::new());
+        let loaded = player.load_from_file(&path.to_string_lossy()).map_err(|e| e.to_string());
+        std::fs::remove_file(&path).unwrap();
+        (player, loaded)
+    }
+    
+    #[test]
+    fn muted_tracks_are_not_replayed() {
+        let mut events = vec![key(0.0, "a", true), mouse_move(0.01, 10, 20), key(0.02, "a", false)];
+        for event in &mut events {
+            let track = if event.is_mouse_move() { TRACK_MOUSE } else { TRACK_KEYBOARD };
+            event.source = Some(track.to_string());
+        }
+        let (mut player, backend) = player_with(events);
+        
+        player.set_track_muted(TRACK_MOUSE, true);
+        player.start();
+        player.wait();
+        
+        assert_eq!(backend.inputs(), vec![
+            Input::Key { vk_code: 0x41, is_down: true },
+            Input::Key { vk_code: 0x41, is_down: false },
+        ]);
+        
+        player.set_track_muted(TRACK_MOUSE, false);
+        player.set_track_muted(TRACK_KEYBOARD, true);
+        backend.sent.lock().unwrap().clear();
+        player.start();
+        player.wait();
+        
+        assert_eq!(backend.inputs(), vec![Input::Move { x: 10, y: 20 }]);
+    }
+    
+    #[test]
+    fn partial_sends_are_reported() {
+        let (mut player, backend) = player_with(vec![key(0.0, "a", true)]);
+        backend.fail_with(Some(SendError::Partial { sent: 1, expected: 2 }));
+        
+        let (index, _, result) = player.step().unwrap();
+        
+        assert_eq!(index, 0);
+        assert_eq!(result, Err(ReplayError::Send(SendError::Partial { sent: 1, expected: 2 })));
+    }
+    
+    #[test]
+    fn a_stopped_recording_loads_straight_into_the_player() {
+        let mut recorder = crate::recorder::MacroRecorder::new();
+        recorder.start();
+        for event in [key(0.0, "a", true), key(0.0, "a", false)] {
+            recorder.add_event(MacroEvent { timestamp: crate::events::capture_clock(), ..event });
+        }
+        recorder.stop();
+        
+        let (mut player, _) = player_with(Vec::new());
+        assert_eq!(player.load_events(recorder.get_events().clone()), 2);
+        
+        let replayed: Vec<_> = std::iter::from_fn(|| player.step()).map(|(_, event, _)| event.event_type).collect();
+        assert_eq!(replayed, vec![EventType::KeyDown, EventType::KeyUp]);
+    }
+    
+    #[test]
+    fn events_missing_fields_are_counted_and_skipped() {
+        let events = vec![
+            MacroEvent::with_data(0.0, EventType::MouseDown, json!({ "x": 1, "y": 2 })),
+            MacroEvent::new(0.01, EventType::KeyDown),
+            key(0.02, "a", true),
+            key(0.03, "a", false),
+        ];
+        let (mut player, backend) = player_with(events);
+        
+        player.start();
+        player.wait();
+        
+        assert_eq!(player.get_skipped_events(), 2);
+        assert_eq!(player.take_playback_end(), Some(PlaybackEnd::Completed));
+        assert_eq!(backend.inputs().len(), 2);
+        assert_eq!(
+            MacroPlayer::execute_event(&*backend, &MacroEvent::new(0.0, EventType::KeyDown), Duration::ZERO, CursorMethod::SetCursorPos, false),
+            Err(ReplayError::MissingField("key_name"))
+        );
+    }
+    
+    #[test]
+    fn the_abort_policy_stops_at_the_first_malformed_event() {
+        let events = vec![
+            MacroEvent::with_data(0.0, EventType::MouseDown, json!({ "x": 1, "y": 2 })),
+            key(0.01, "a", true),
+        ];
+        let (mut player, backend) = player_with(events);
+        player.set_missing_field_policy(MissingFieldPolicy::Abort);
+        
+        player.start();
+        player.wait();
+        
+        assert_eq!(player.take_playback_end(), Some(PlaybackEnd::Aborted));
+        assert_eq!(player.get_skipped_events(), 1);
+        assert!(backend.inputs().is_empty());
+    }
+    
+    #[test]
+    fn clicks_hold_the_button_for_the_hold_time() {
+        let (mut player, backend) = player_with(Vec::new());
+        player.set_click_hold_ms(80);
+        
+        player.click_at(5, 6, 1).unwrap();
+        
+        assert_eq!(backend.inputs(), vec![
+            Input::Move { x: 5, y: 6 },
+            Input::Button { button: 1, is_down: true },
+            Input::Move { x: 5, y: 6 },
+            Input::Button { button: 1, is_down: false },
+        ]);
+        let times = backend.times();
+        assert!(times[3] - times[1] >= Duration::from_millis(80));
+    }
+    
+    #[test]
+    fn seek_clamps_and_step_continues_from_there() {
+        let events = vec![key(0.0, "a", true), key(0.1, "b", true), key(0.2, "c", true)];
+        let (mut player, backend) = player_with(events);
+        
+        assert_eq!(player.seek(10), 2);
+        assert_eq!(player.current_event_index(), 2);
+        assert_eq!(player.get_total_events(), 3);
+        
+        assert_eq!(player.seek(1), 1);
+        let (index, _, result) = player.step().unwrap();
+        assert_eq!((index, result), (1, Ok(())));
+        assert_eq!(player.current_event_index(), 2);
+        assert_eq!(backend.inputs(), vec![Input::Key { vk_code: 0x42, is_down: true }]);
+        
+        player.step();
+        assert!(player.step().is_none());
+        assert_eq!(MacroPlayer::new().seek(5), 0);
+    }
+    
+    #[test]
+    fn the_context_menu_key_replays() {
+        assert_eq!(MacroPlayer::key_name_to_vk_code("apps"), Some(VK_APPS.0));
+        assert_eq!(MacroPlayer::key_name_to_vk_code("menu"), Some(VK_APPS.0));
+        assert_eq!(MacroPlayer::key_name_to_vk_code("sleep"), Some(VK_SLEEP.0));
+        assert!(MacroPlayer::is_extended_key(VK_APPS.0));
+        
+        let (mut player, backend) = player_with(vec![key(0.0, "apps", true)]);
+        player.step();
+        assert_eq!(backend.inputs(), vec![Input::Key { vk_code: VK_APPS.0, is_down: true }]);
+    }
+    
+    #[test]
+    fn focus_changes_are_followed_by_the_settle_delay() {
+        let events = vec![
+            MacroEvent::with_data(0.0, EventType::FocusChange, json!({ "window": "Notepad" })),
+            key(0.01, "a", true),
+            key(0.02, "a", false),
+        ];
+        let (mut player, backend) = player_with(events);
+        player.set_activation_settle(Duration::from_millis(150));
+        
+        let started = Instant::now();
+        player.start();
+        player.wait();
+        
+        let times = backend.times();
+        assert_eq!(times.len(), 2);
+        assert!(times[0] - started >= Duration::from_millis(150));
+        // The delay shifts what follows rather than stretching each gap
+        assert!(times[1] - times[0] < Duration::from_millis(100));
+    }
+    
+    #[test]
+    fn fixed_intervals_ignore_the_recorded_timestamps() {
+        let events = vec![mouse_move(0.0, 1, 1), mouse_move(5.0, 2, 2), mouse_move(5.001, 3, 3)];
+        let (mut player, backend) = player_with(events);
+        player.set_timing_mode(TimingMode::FixedInterval(Duration::from_millis(50)));
+        
+        let started = Instant::now();
+        player.start();
+        player.wait();
+        
+        let offsets: Vec<_> = backend.times().iter().map(|at| (*at - started).as_millis()).collect();
+        assert_eq!(offsets.len(), 3);
+        for (offset, expected) in offsets.into_iter().zip([0, 50, 100]) {
+            assert!((expected..expected + 40).contains(&offset), "sent at {}ms, expected ~{}ms", offset, expected);
+        }
+    }
+    
+    #[test]
+    fn posted_clicks_carry_client_coordinates() {
+        assert_eq!(mouse_lparam(300, 200), (200 << 16) | 300);
+        // Negative client coordinates keep their sign in each word
+        assert_eq!(mouse_lparam(-1, 2) as u32, 0x0002_FFFF);
+        assert_eq!(button_messages(2), Some((WM_RBUTTONDOWN, WM_RBUTTONUP, 0x0002, 0)));
+        
+        let click = MacroEvent::with_data(0.0, EventType::Click, json!({ "x": 400, "y": 300, "button": 1 }));
+        let (_, backend) = player_with(Vec::new());
+        MacroPlayer::post_event(&*backend, 1, &click).unwrap();
+        
+        // The mock puts the window's client area at (100, 100)
+        assert_eq!(backend.inputs(), vec![
+            Input::Post { msg: WM_LBUTTONDOWN, wparam: 0x0001, lparam: mouse_lparam(300, 200) },
+            Input::Post { msg: WM_LBUTTONUP, wparam: 0, lparam: mouse_lparam(300, 200) },
+        ]);
+    }
+    
+    #[test]
+    fn posted_keys_carry_the_scan_code_and_transition_bits() {
+        assert_eq!(key_lparam(0x1E, false, false), 0x001E_0001);
+        assert_eq!(key_lparam(0x4D, true, true) as u32, 0xC14D_0001);
+    }
+    
+    #[test]
+    fn a_failed_event_pauses_until_it_is_retried() {
+        let (mut player, backend) = player_with(vec![mouse_move(0.0, 1, 1), mouse_move(0.01, 2, 2)]);
+        player.set_pause_on_error(true);
+        backend.fail_with(Some(SendError::Blocked));
+        
+        player.start();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while player.get_fault().is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        
+        let fault = player.get_fault().expect("playback should halt on the failed event");
+        assert_eq!((fault.index, fault.error), (0, ReplayError::Send(SendError::Blocked)));
+        assert!(matches!(player.get_state(), PlayerState::Paused));
+        
+        backend.fail_with(None);
+        player.resolve_fault(FaultAction::Retry);
+        player.wait();
+        
+        assert_eq!(player.get_fault(), None);
+        assert_eq!(backend.inputs(), vec![Input::Move { x: 1, y: 1 }, Input::Move { x: 2, y: 2 }]);
+    }
+    
+    #[test]
+    fn a_captured_press_and_release_replays_as_one_click() {
+        let button = |event_type| MacroEvent::with_data(0.0, event_type, json!({ "x": 10, "y": 20, "button": 1 }));
+        let mut recorder = crate::recorder::MacroRecorder::new();
+        recorder.set_capture_mode(crate::recorder::CaptureMode::ClicksOnly);
+        recorder.start();
+        for event in [mouse_move(0.0, 5, 5), button(EventType::MouseDown), button(EventType::MouseUp)] {
+            recorder.add_event(MacroEvent { timestamp: crate::events::capture_clock(), ..event });
+        }
+        recorder.stop();
+        
+        let events = recorder.get_events().clone();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].event_type, EventType::Click));
+        assert_eq!((events[0].data["x"].as_i64(), events[0].data["y"].as_i64()), (Some(10), Some(20)));
+        
+        let (mut player, backend) = player_with(events);
+        player.step();
+        assert_eq!(backend.inputs(), vec![
+            Input::Move { x: 10, y: 20 },
+            Input::Button { button: 1, is_down: true },
+            Input::Move { x: 10, y: 20 },
+            Input::Button { button: 1, is_down: false },
+        ]);
+    }
+    
+    #[test]
+    fn negative_timestamps_are_shifted_to_zero_on_load() {
+        let events = [mouse_move(-0.3, 1, 1), mouse_move(0.2, 2, 2), mouse_move(1.2, 3, 3)];
+        let content = crate::events::mcr_lines(&events, false).join("\n");
+        
+        let (player, loaded) = player_loading("negative-timestamps", &content);
+        
+        assert_eq!(loaded, Ok(3));
+        let timestamps: Vec<f64> = player.events.iter().map(|event| event.timestamp).collect();
+        assert_eq!(timestamps[0], 0.0);
+        for (timestamp, expected) in timestamps.iter().zip([0.0, 0.5, 1.5]) {
+            assert!((timestamp - expected).abs() < 1e-9, "{:?}", timestamps);
+        }
+    }
+    
+    #[test]
+    fn stop_interrupts_a_long_wait() {
+        let (mut player, backend) = player_with(vec![mouse_move(0.0, 1, 1), mouse_move(60.0, 2, 2)]);
+        player.start();
+        thread::sleep(Duration::from_millis(50));
+        
+        let stopping = Instant::now();
+        player.stop();
+        
+        assert!(stopping.elapsed() < Duration::from_millis(200), "stop took {:?}", stopping.elapsed());
+        assert!(matches!(player.get_state(), PlayerState::Stopped));
+        assert_eq!(backend.inputs(), vec![Input::Move { x: 1, y: 1 }]);
+    }
+    
+    #[test]
+    fn the_full_wheel_delta_survives_save_and_replay() {
+        let scroll = MacroEvent::with_data(0.0, EventType::MouseScroll, json!({ "x": 5, "y": 5, "delta": 360 }));
+        let line = scroll.to_mcr_line();
+        let loaded = crate::events::parse_mcr_line(&line);
+        assert_eq!(loaded[0].data["delta"], json!(360));
+        
+        let (mut player, backend) = player_with(loaded);
+        player.step();
+        assert_eq!(backend.inputs().last(), Some(&Input::Scroll { delta: 360, horizontal: false }));
+    }
+    
+    #[test]
+    fn a_file_without_a_version_header_loads_as_v1() {
+        let content = "0.000000;KDOWN;char=a\n0.050000;KUP;char=a\n0.100000;MSCROLL;dx=0;dy=-1;x=5;y=5\n";
+        
+        let (player, loaded) = player_loading("v1-file", content);
+        
+        assert_eq!(loaded, Ok(3));
+        assert_eq!(player.format_version(), 1);
+        // v1 only recorded the wheel direction
+        assert_eq!(player.events[2].data["delta"], json!(-120));
+    }
+    
+    #[test]
+    fn held_keys_are_released_when_playback_stops() {
+        let events = vec![key(0.0, "ctrl", true), key(0.01, "shift", true), key(60.0, "a", true)];
+        let (mut player, backend) = player_with(events);
+        
+        player.start();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while backend.inputs().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        player.stop();
+        
+        let mut released: Vec<_> = backend.inputs().into_iter()
+            .filter_map(|input| match input {
+                Input::Key { vk_code, is_down: false } => Some(vk_code),
+                _ => None,
+            })
+            .collect();
+        released.sort_unstable();
+        assert_eq!(released, vec![VK_SHIFT.0, VK_CONTROL.0]);
+        assert!(!backend.inputs().contains(&Input::Key { vk_code: 0x41, is_down: true }));
+    }
+    
+    #[test]
+    fn lowering_the_speed_mid_run_lengthens_the_remaining_waits() {
+        let events = vec![mouse_move(0.0, 0, 0), mouse_move(0.1, 1, 1), mouse_move(0.2, 2, 2), mouse_move(0.3, 3, 3)];
+        let (mut player, backend) = player_with(events);
+        
+        player.start();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while backend.inputs().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(2));
+        }
+        player.set_speed(0.5);
+        player.wait();
+        
+        let times = backend.times();
+        assert_eq!(times.len(), 4);
+        assert!(times[1] - times[0] < Duration::from_millis(150));
+        // The 100ms gap after the change plays at half speed
+        assert!(times[3] - times[2] >= Duration::from_millis(180), "{:?}", times[3] - times[2]);
+    }
+    
+    #[test]
+    fn a_non_finite_timestamp_is_skipped_without_panicking() {
+        let content = "0.000000;KDOWN;char=b\nnan;KDOWN;char=a\ninf;KUP;char=a\n0.050000;KUP;char=b\n";
+        
+        let (player, loaded) = player_loading("nan-timestamp", content);
+        
+        assert_eq!(loaded, Ok(2));
+        assert!(player.events.iter().all(|event| event.timestamp.is_finite()));
+        assert!(MacroEvent::from_mcr_line("nan;KDOWN;char=a").is_none());
+    }
+    
+    #[test]
+    fn numpad_names_replay_as_the_numpad_keys() {
+        for (vk, name) in crate::events::NUMPAD_KEY_NAMES {
+            assert_eq!(MacroPlayer::key_name_to_vk_code(name), Some(vk as u16), "{}", name);
+        }
+        
+        // Both Enters send VK_RETURN; only the numpad one is extended
+        assert_eq!(MacroPlayer::key_name_to_vk_code("numenter"), Some(VK_RETURN.0));
+        assert!(MacroPlayer::event_extended(&key(0.0, "numenter", true)));
+        assert!(!MacroPlayer::event_extended(&key(0.0, "enter", true)));
+    }
+    
+    #[test]
+    fn hello_replays_with_shift_around_the_capitals() {
+        let down = |vk_code| Input::Key { vk_code, is_down: true };
+        let up = |vk_code| Input::Key { vk_code, is_down: false };
+        // `^` marks a release
+        let typed = |names: &[&str]| -> Vec<MacroEvent> {
+            names.iter().enumerate()
+                .map(|(i, name)| match name.strip_prefix('^') {
+                    Some(name) => key(i as f64 * 0.001, name, false),
+                    None => key(i as f64 * 0.001, name, true),
+                })
+                .collect()
+        };
+        let replay = |events| {
+            let (mut player, backend) = player_with(events);
+            player.start();
+            player.wait();
+            backend.inputs()
+        };
+        let ello = [down(0x45), up(0x45), down(0x4C), up(0x4C), down(0x4C), up(0x4C), down(0x4F), up(0x4F)];
+        
+        // As the hooks record it: Shift is its own key, pressed before and released after
+        let recorded = typed(&["shift", "h", "^h", "^shift", "e", "^e", "l", "^l", "l", "^l", "o", "^o", "shift", "1", "^1", "^shift"]);
+        let mut expected = vec![down(VK_SHIFT.0), down(0x48), up(0x48), up(VK_SHIFT.0)];
+        expected.extend(ello.clone());
+        expected.extend([down(VK_SHIFT.0), down(0x31), up(0x31), up(VK_SHIFT.0)]);
+        assert_eq!(replay(recorded), expected);
+        
+        // Written by hand as characters: each shifted press gets a Shift of its own
+        let written = typed(&["H", "^H", "e", "^e", "l", "^l", "l", "^l", "o", "^o", "!", "^!"]);
+        let mut expected = vec![down(VK_SHIFT.0), down(0x48), up(VK_SHIFT.0), up(0x48)];
+        expected.extend(ello);
+        expected.extend([down(VK_SHIFT.0), down(0x31), up(VK_SHIFT.0), up(0x31)]);
+        assert_eq!(replay(written), expected);
+    }
+    
+    #[test]
+    fn rebasing_moves_the_first_event_to_zero() {
+        let (mut player, _) = player_with(vec![mouse_move(5.0, 1, 1), mouse_move(5.5, 2, 2), mouse_move(7.0, 3, 3)]);
+        
+        assert_eq!(player.rebase(), Some(5.0));
+        
+        let timestamps: Vec<f64> = player.events.iter().map(|event| event.timestamp).collect();
+        assert_eq!(timestamps, vec![0.0, 0.5, 2.0]);
+        // Opt-in: nothing left to cut the second time
+        assert_eq!(player.rebase(), None);
+    }
+    
+    #[test]
+    fn an_inserted_delay_waits_after_the_event_it_follows() {
+        let mut recorder = crate::recorder::MacroRecorder::new();
+        for (seq, event) in [key(0.0, "a", true), key(0.1, "b", true)].into_iter().enumerate() {
+            recorder.insert_event(usize::MAX, MacroEvent { seq: seq as u64 + 1, ..event });
+        }
+        recorder.insert_delay(1, 300);
+        
+        let started = Instant::now();
+        let (mut player, backend) = player_with(recorder.get_events().clone());
+        let order: Vec<_> = player.events.iter().map(|event| event.event_type.clone()).collect();
+        assert_eq!(order, vec![EventType::KeyDown, EventType::Delay, EventType::KeyDown]);
+        
+        player.start();
+        player.wait();
+        
+        let presses: Vec<_> = backend.sent.lock().unwrap().iter()
+            .filter(|(_, input)| matches!(input, Input::Key { is_down: true, .. }))
+            .map(|(at, input)| ((*at - started).as_millis(), input.clone()))
+            .collect();
+        assert_eq!(presses.len(), 2);
+        assert_eq!(presses[0].1, Input::Key { vk_code: 0x41, is_down: true });
+        assert!(presses[0].0 < 50, "a sent at {}ms", presses[0].0);
+        // The 300ms delay stretches the 100ms gap once, not on top of it
+        assert!((380..480).contains(&presses[1].0), "b sent at {}ms", presses[1].0);
+    }
+    
+    
+    #[test]
+    fn recording_captures_through_the_backend() {
+        let backend = MockBackend::default();
+        let recorder = Arc::new(Mutex::new(crate::recorder::MacroRecorder::new()));
+        let captured = || key(crate::events::capture_clock(), "a", true);
+        
+        backend.capture(captured());
+        crate::recorder::MacroRecorder::start_capture(&recorder, &backend).unwrap();
+        backend.capture(captured());
+        backend.capture(captured());
+        crate::recorder::MacroRecorder::stop_capture(&recorder, &backend);
+        backend.capture(captured());
+        
+        // Only what arrived between install and uninstall is kept
+        let recorder = recorder.lock().unwrap();
+        assert_eq!(recorder.get_events().len(), 2);
+        assert!(matches!(recorder.get_state(), crate::recorder::RecorderState::Idle));
+        assert!(backend.sink.lock().unwrap().is_none());
+    }
+    
+    
+    #[test]
+    fn cursor_divergence_is_measured_as_a_distance() {
+        assert!(!cursor_diverged((100, 100), (130, 140), 50));
+        assert!(cursor_diverged((100, 100), (131, 141), 50));
+        assert!(cursor_diverged((100, 100), (40, 100), 50));
+    }
+    
+    #[test]
+    fn grabbing_the_mouse_trips_the_failsafe() {
+        let events = vec![mouse_move(0.0, 100, 100), mouse_move(0.5, 110, 100), mouse_move(1.0, 120, 100)];
+        
+        // Nudged within the threshold: playback carries on
+        let (mut player, backend) = player_with(events.clone());
+        player.set_failsafe(Some(50));
+        player.start();
+        thread::sleep(Duration::from_millis(200));
+        backend.move_cursor(120, 120);
+        player.wait();
+        assert!(!player.take_failsafe_tripped());
+        assert_eq!(backend.inputs().len(), 3);
+        
+        // Dragged well past it: the rest of the macro is dropped
+        let (mut player, backend) = player_with(events);
+        player.set_failsafe(Some(50));
+        player.start();
+        thread::sleep(Duration::from_millis(200));
+        backend.move_cursor(400, 400);
+        player.wait();
+        assert!(player.take_failsafe_tripped());
+        assert!(!player.take_failsafe_tripped(), "reported once");
+        assert_eq!(backend.inputs(), vec![Input::Move { x: 100, y: 100 }]);
+    }
+    
+    
+    #[test]
+    fn a_seeded_humanizer_replays_the_same_inputs() {
+        let click = |timestamp: f64, x: i32, is_down: bool| {
+            let event_type = if is_down { EventType::MouseDown } else { EventType::MouseUp };
+            MacroEvent::with_data(timestamp, event_type, json!({ "x": x, "y": 300, "button": 1 }))
+        };
+        let events = vec![click(0.0, 100, true), click(0.05, 100, false), click(0.1, 700, true), click(0.15, 700, false)];
+        let settings = HumanizeSettings { timing_variance_pct: 50, click_scatter_px: 20, seed: 42 };
+        let run = || {
+            let (mut player, backend) = player_with(events.clone());
+            player.set_humanize(Some(settings));
+            player.start();
+            player.wait();
+            backend.inputs()
+        };
+        
+        let first = run();
+        assert_eq!(first, run());
+        
+        // Each press and its release land on the same scattered point
+        let points: Vec<(i32, i32)> = first.iter().filter_map(|input| match input {
+            Input::Move { x, y } => Some((*x, *y)),
+            _ => None,
+        }).collect();
+        assert_eq!(points.len(), 4, "{:?}", first);
+        assert_eq!(points[0], points[1]);
+        assert_eq!(points[2], points[3]);
+        assert_ne!(points[0], (100, 300), "scattered");
+        for ((x, y), recorded) in points.iter().zip([100, 100, 700, 700]) {
+            assert!((x - recorded).abs() <= 20 && (y - 300).abs() <= 20, "{:?}", points);
+        }
+        
+        // The waits are jittered the same way too
+        let offsets = |seed| {
+            let mut humanizer = Humanizer::new(HumanizeSettings { seed, ..settings });
+            (0..4).map(|_| humanizer.timing_offset(Duration::from_millis(100))).collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(42), offsets(42));
+        assert_ne!(offsets(42), offsets(43));
+    }
+    
+    
+    #[test]
+    fn loops_repeat_the_macro_with_the_delay_between_them() {
+        let events = vec![key(0.0, "a", true), key(0.05, "a", false)];
+        let (mut player, backend) = player_with(events.clone());
+        player.set_repeat_count(3);
+        player.set_loop_delay(Duration::from_millis(200));
+        let started = Instant::now();
+        player.start();
+        player.wait();
+        
+        let press = Input::Key { vk_code: 0x41, is_down: true };
+        let release = Input::Key { vk_code: 0x41, is_down: false };
+        let three_loops: Vec<Input> = [press, release.clone()].iter().cycle().take(6).cloned().collect();
+        assert_eq!(backend.inputs(), three_loops);
+        assert_eq!(player.get_current_loop(), 3);
+        // Each loop starts 50ms of macro plus the 200ms delay after the one before
+        let presses: Vec<u128> = backend.times().iter().step_by(2)
+            .map(|at| at.duration_since(started).as_millis())
+            .collect();
+        assert!(presses[1] - presses[0] >= 250 && presses[1] - presses[0] < 350, "{:?}", presses);
+        assert!(presses[2] - presses[1] >= 250 && presses[2] - presses[1] < 350, "{:?}", presses);
+        
+        // 0 loops until stopped
+        let (mut player, backend) = player_with(events);
+        player.set_repeat_count(0);
+        player.set_loop_delay(Duration::from_millis(100));
+        player.start();
+        thread::sleep(Duration::from_millis(400));
+        player.stop();
+        let sent = backend.inputs().len();
+        assert!(sent >= 4, "only {} inputs", sent);
+        assert!(player.get_current_loop() >= 2);
+        // Nothing is left held down
+        assert_eq!(backend.inputs().last(), Some(&release));
+    }
+}
@@ -0,0 +1,177 @@
+use serde_json::{json, Map, Value};
+use crate::events::{EventType, MacroEvent};
+
+// Bumped whenever the exported document changes shape; readers compare it before anything else
+pub const PORTABLE_SCHEMA_VERSION: u64 = 1;
+
+// Internal data field -> exported name, for the fields copied over unchanged. The exported names
+// are the contract; internal ones can be renamed freely as long as this table follows.
+const FIELDS: [(&str, &str); 17] = [
+    ("key_name", "key"),
+    ("vk_code", "vk"),
+    ("scan_code", "scan"),
+    ("extended", "extended"),
+    ("injected", "injected"),
+    ("double", "double"),
+    ("action", "shortcut"),
+    ("text", "text"),
+    ("window", "window"),
+    ("monitor", "monitor"),
+    ("monitor_left", "monitor_x"),
+    ("monitor_top", "monitor_y"),
+    ("x", "x"),
+    ("y", "y"),
+    ("delta", "delta"),
+    ("duration_ms", "duration_ms"),
+    ("ms", "ms"),
+];
+
+const BUTTONS: [(u64, &str); 5] = [(1, "left"), (2, "right"), (3, "middle"), (4, "x1"), (5, "x2")];
+
+const TYPES: [(EventType, &str); 12] = [
+    (EventType::KeyDown, "key_down"),
+    (EventType::KeyUp, "key_up"),
+    (EventType::TextInput, "text"),
+    (EventType::MouseMove, "mouse_move"),
+    (EventType::MouseDown, "mouse_down"),
+    (EventType::MouseUp, "mouse_up"),
+    (EventType::Click, "click"),
+    (EventType::MouseScroll, "scroll"),
+    (EventType::MouseHScroll, "hscroll"),
+    (EventType::Hover, "hover"),
+    (EventType::FocusChange, "focus"),
+    (EventType::Delay, "delay"),
+];
+
+fn type_name(event_type: &EventType) -> &'static str {
+    TYPES.iter()
+        .find(|(known, _)| known == event_type)
+        .map(|(_, name)| *name)
+        .unwrap_or("unknown")
+}
+
+// JSON Schema (draft 2020-12) for the exported document; it travels inside every export
+pub fn schema() -> Value {
+    let type_names: Vec<&str> = TYPES.iter().map(|(_, name)| *name).collect();
+    let button_names: Vec<&str> = BUTTONS.iter().map(|(_, name)| *name).collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Macro Recorder portable recording",
+        "type": "object",
+        "required": ["schema_version", "events"],
+        "properties": {
+            "schema_version": { "const": PORTABLE_SCHEMA_VERSION },
+            "schema": { "type": "object" },
+            "events": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["t", "type"],
+                    "additionalProperties": false,
+                    "properties": {
+                        "t": { "type": "number", "minimum": 0, "description": "Seconds since recording started" },
+                        "type": { "enum": type_names },
+                        "seq": { "type": "integer", "minimum": 0, "description": "Capture order for events with equal t" },
+                        "track": { "type": "string" },
+                        "key": { "type": "string" },
+                        "vk": { "type": "integer", "description": "Windows virtual key code" },
+                        "scan": { "type": "integer" },
+                        "extended": { "type": "boolean" },
+                        "injected": { "type": "boolean" },
+                        "shortcut": { "type": "string", "description": "Recognized chord such as Ctrl+C" },
+                        "text": { "type": "string" },
+                        "window": { "type": "string", "description": "Foreground window title" },
+                        "monitor": { "type": "string" },
+                        "monitor_x": { "type": "integer" },
+                        "monitor_y": { "type": "integer" },
+                        "x": { "type": "integer", "description": "Screen pixels" },
+                        "y": { "type": "integer", "description": "Screen pixels" },
+                        "button": { "enum": button_names },
+                        "double": { "type": "boolean", "description": "Second press of a double-click" },
+                        "delta": { "type": "integer", "description": "Wheel movement, 120 per notch" },
+                        "duration_ms": { "type": "integer" },
+                        "ms": { "type": "integer", "description": "Length of a delay" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+pub fn to_portable_event(event: &MacroEvent) -> Value {
+    let mut out = Map::new();
+    out.insert("t".to_string(), json!(event.timestamp));
+    out.insert("type".to_string(), json!(type_name(&event.event_type)));
+    if event.seq > 0 {
+        out.insert("seq".to_string(), json!(event.seq));
+    }
+    if let Some(track) = &event.source {
+        out.insert("track".to_string(), json!(track));
+    }
+    
+    for (internal, exported) in FIELDS {
+        if let Some(value) = event.data.get(internal) {
+            out.insert(exported.to_string(), value.clone());
+        }
+    }
+    let button = event.data.get("button").and_then(|b| b.as_u64())
+        .and_then(|button| BUTTONS.iter().find(|(number, _)| *number == button));
+    if let Some((_, name)) = button {
+        out.insert("button".to_string(), json!(name));
+    }
+    Value::Object(out)
+}
+
+// The whole export: version, schema and events. Data fields outside the schema are left out.
+pub fn to_portable(events: &[MacroEvent]) -> Value {
+    json!({
+        "schema_version": PORTABLE_SCHEMA_VERSION,
+        "schema": schema(),
+        "events": events.iter().map(to_portable_event).collect::<Vec<_>>(),
+    })
+}
+
+// Whether a parsed JSON file is a portable export rather than the internal event array
+pub fn is_portable(document: &Value) -> bool {
+    document.get("schema_version").is_some()
+}
+
+pub fn from_portable_event(value: &Value) -> Option<MacroEvent> {
+    let timestamp = value.get("t").and_then(|t| t.as_f64()).filter(|t| t.is_finite())?;
+    let name = value.get("type").and_then(|t| t.as_str())?;
+    let event_type = TYPES.iter().find(|(_, known)| *known == name)?.0.clone();
+    
+    let mut data = Map::new();
+    for (internal, exported) in FIELDS {
+        if let Some(field) = value.get(exported) {
+            data.insert(internal.to_string(), field.clone());
+        }
+    }
+    let button = value.get("button").and_then(|b| b.as_str())
+        .and_then(|button| BUTTONS.iter().find(|(_, name)| *name == button));
+    if let Some((number, _)) = button {
+        data.insert("button".to_string(), json!(number));
+    }
+    
+    let mut event = MacroEvent::with_data(timestamp, event_type, Value::Object(data));
+    event.source = value.get("track").and_then(|t| t.as_str()).map(|t| t.to_string());
+    event.seq = value.get("seq").and_then(|s| s.as_u64()).unwrap_or(0);
+    Some(event)
+}
+
+// Events of a portable export. Events that don't parse are skipped and logged, like bad .mcr lines.
+pub fn from_portable(document: &Value) -> Result<Vec<MacroEvent>, String> {
+    let version = document.get("schema_version").and_then(|v| v.as_u64())
+        .ok_or("schema_version is missing or not a number")?;
+    if version > PORTABLE_SCHEMA_VERSION {
+        log::warn!("Portable JSON is schema v{} but this build reads up to v{}; unknown fields will be ignored",
+            version, PORTABLE_SCHEMA_VERSION);
+    }
+    
+    let items = document.get("events").and_then(|e| e.as_array()).ok_or("events is missing or not an array")?;
+    let events: Vec<MacroEvent> = items.iter().filter_map(from_portable_event).collect();
+    if events.len() < items.len() {
+        log::warn!("Skipped {} unreadable event(s) in portable JSON", items.len() - events.len());
+    }
+    Ok(events)
+}
@@ -0,0 +1,183 @@
+use crate::events::{MacroEvent, EventType};
+use std::fmt;
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+// Gaps longer than this are probably forgotten idle time rather than intentional waits
+pub const LONG_IDLE_GAP_SECS: f64 = 30.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    DanglingKeyDown { key: String },
+    ClickOutOfBounds { index: usize, x: i64, y: i64 },
+    LongIdleGap { index: usize, seconds: f64 },
+    InvalidTimestamp { index: usize },
+    UnmappedKey { index: usize, key: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::DanglingKeyDown { key } => write!(f, "Key '{}' is pressed but never released", key),
+            Warning::ClickOutOfBounds { index, x, y } => {
+                write!(f, "Event #{} clicks at ({}, {}), outside the screen", index, x, y)
+            }
+            Warning::LongIdleGap { index, seconds } => {
+                write!(f, "Event #{} follows an idle gap of {:.1}s", index, seconds)
+            }
+            Warning::InvalidTimestamp { index } => write!(f, "Event #{} has a negative or invalid timestamp", index),
+            Warning::UnmappedKey { index, key } => write!(f, "Event #{} uses unknown key '{}'", index, key),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenBounds {
+    pub left: i64,
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+}
+
+impl ScreenBounds {
+    // Bounding box of all monitors
+    #[cfg(windows)]
+    pub fn virtual_screen() -> Self {
+        unsafe {
+            let left = GetSystemMetrics(SM_XVIRTUALSCREEN) as i64;
+            let top = GetSystemMetrics(SM_YVIRTUALSCREEN) as i64;
+            Self {
+                left,
+                top,
+                right: left + GetSystemMetrics(SM_CXVIRTUALSCREEN) as i64,
+                bottom: top + GetSystemMetrics(SM_CYVIRTUALSCREEN) as i64,
+            }
+        }
+    }
+    
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        x >= self.left && x < self.right && y >= self.top && y < self.bottom
+    }
+}
+
+pub fn check<F>(events: &[MacroEvent], bounds: ScreenBounds, is_mapped_key: F) -> Vec<Warning>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut warnings = Vec::new();
+    let mut held_keys: Vec<String> = Vec::new();
+    let mut previous_timestamp: Option<f64> = None;
+    
+    for (index, event) in events.iter().enumerate() {
+        if !event.timestamp.is_finite() || event.timestamp < 0.0 {
+            warnings.push(Warning::InvalidTimestamp { index });
+        } else {
+            if let Some(previous) = previous_timestamp {
+                let gap = event.timestamp - previous;
+                if gap > LONG_IDLE_GAP_SECS {
+                    warnings.push(Warning::LongIdleGap { index, seconds: gap });
+                }
+            }
+            previous_timestamp = Some(event.timestamp);
+        }
+        
+        match event.event_type {
+            EventType::KeyDown | EventType::KeyUp => {
+                let key = match event.data.get("key_name").and_then(|k| k.as_str()) {
+                    Some(key) => key.to_string(),
+                    None => continue,
+                };
+                
+                // A stored virtual key replays as-is even when the name is unknown
+                let has_vk = event.data.get("vk_code").is_some();
+                if !has_vk && !is_mapped_key(&key) {
+                    warnings.push(Warning::UnmappedKey { index, key: key.clone() });
+                }
+                
+                if matches!(event.event_type, EventType::KeyDown) {
+                    if !held_keys.contains(&key) {
+                        held_keys.push(key);
+                    }
+                } else {
+                    held_keys.retain(|held| held != &key);
+                }
+            }
+            EventType::MouseDown | EventType::Click => {
+                let x = event.data.get("x").and_then(|x| x.as_i64());
+                let y = event.data.get("y").and_then(|y| y.as_i64());
+                if let (Some(x), Some(y)) = (x, y) {
+                    if !bounds.contains(x, y) {
+                        warnings.push(Warning::ClickOutOfBounds { index, x, y });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    for key in held_keys {
+        warnings.push(Warning::DanglingKeyDown { key });
+    }
+    
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    const SCREEN: ScreenBounds = ScreenBounds { left: 0, top: 0, right: 1920, bottom: 1080 };
+    
+    fn event(timestamp: f64, event_type: EventType, data: serde_json::Value) -> MacroEvent {
+        MacroEvent::with_data(timestamp, event_type, data)
+    }
+    
+    fn check_all(events: &[MacroEvent]) -> Vec<Warning> {
+        check(events, SCREEN, |key| key != "nosuchkey")
+    }
+    
+    #[test]
+    fn a_clean_macro_has_no_warnings() {
+        let events = [
+            event(0.0, EventType::KeyDown, json!({ "key_name": "a" })),
+            event(0.1, EventType::KeyUp, json!({ "key_name": "a" })),
+            event(0.2, EventType::Click, json!({ "x": 100, "y": 100, "button": "left" })),
+        ];
+        
+        assert!(check_all(&events).is_empty());
+    }
+    
+    #[test]
+    fn each_problem_gets_its_own_warning() {
+        let events = [
+            event(0.0, EventType::KeyDown, json!({ "key_name": "shift" })),
+            event(0.1, EventType::Click, json!({ "x": 2500, "y": 100, "button": "left" })),
+            event(45.0, EventType::MouseMove, json!({ "x": 10, "y": 10 })),
+            event(-1.0, EventType::MouseMove, json!({ "x": 10, "y": 10 })),
+            event(f64::NAN, EventType::MouseMove, json!({ "x": 10, "y": 10 })),
+            event(46.0, EventType::KeyDown, json!({ "key_name": "nosuchkey" })),
+            event(46.1, EventType::KeyUp, json!({ "key_name": "nosuchkey" })),
+        ];
+        
+        assert_eq!(check_all(&events), vec![
+            Warning::ClickOutOfBounds { index: 1, x: 2500, y: 100 },
+            Warning::LongIdleGap { index: 2, seconds: 44.9 },
+            Warning::InvalidTimestamp { index: 3 },
+            Warning::InvalidTimestamp { index: 4 },
+            Warning::UnmappedKey { index: 5, key: "nosuchkey".to_string() },
+            Warning::UnmappedKey { index: 6, key: "nosuchkey".to_string() },
+            Warning::DanglingKeyDown { key: "shift".to_string() },
+        ]);
+    }
+    
+    #[test]
+    fn a_stored_virtual_key_is_not_unmapped() {
+        let events = [
+            event(0.0, EventType::KeyDown, json!({ "key_name": "nosuchkey", "vk_code": 0xE8 })),
+            event(0.1, EventType::KeyUp, json!({ "key_name": "nosuchkey", "vk_code": 0xE8 })),
+        ];
+        
+        assert!(check_all(&events).is_empty());
+    }
+}
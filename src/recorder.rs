@@ -1,6 +1,44 @@
+use crate::backend::{EventSink, InputBackend};
 use crate::events::MacroEvent;
-// use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use crate::history::{EditHistory, EditOp, DEFAULT_HISTORY_DEPTH};
+use crate::shortcuts::ShortcutTracker;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Full keeps every event; ClicksOnly keeps one Click per press/release and drops the rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureMode {
+    Full,
+    ClicksOnly,
+}
+
+// Recordings only stop on their own once the user sets a limit
+pub const DEFAULT_MAX_EVENTS: usize = 0;
+pub const DEFAULT_MAX_DURATION_SECS: u64 = 0;
+pub const DEFAULT_IDLE_PAUSE_SECS: u64 = 10;
+
+// Why a recording stopped on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordingLimit {
+    Events(usize),
+    DurationSecs(u64),
+}
+
+impl RecordingLimit {
+    pub fn describe(&self) -> String {
+        match self {
+            RecordingLimit::Events(max) => format!("reached the limit of {} events", max),
+            RecordingLimit::DurationSecs(max) => format!("reached the limit of {}s", max),
+        }
+    }
+}
+
+// Reported by check_idle and add_event when idle detection pauses or resumes the recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleChange {
+    Paused,
+    Resumed,
+}
 
 #[derive(Debug, Clone)]
 pub enum RecorderState {
@@ -15,9 +53,46 @@ pub struct MacroRecorder {
     start_time: Option<Instant>,
     pause_start: Option<Instant>,
     total_pause_time: std::time::Duration,
+    // Input captured before this happened during the last pause, even if it arrives after it
+    resumed_at: Option<Instant>,
+    history: EditHistory,
+    // Labels recognized chords on their key-down; raw key events are kept either way
+    shortcuts: Option<ShortcutTracker>,
+    // Minimum dwell (seconds) turned into a Hover event when recording stops; None disables it
+    hover_threshold: Option<f64>,
+    // Bumped on every change to the event list, so callers can tell if it changed since a save
+    revision: u64,
+    capture_mode: CaptureMode,
+    // Press waiting for its release while capturing clicks only
+    pending_press: Option<MacroEvent>,
+    // .mcr version of the file last loaded; new recordings are always the current version
+    format_version: u32,
+    // Wall clock (Unix ms) when recording started, for reconstructing absolute event times
+    epoch_ms: Option<i64>,
+    // Recording stops by itself past either limit; 0 means no limit
+    max_events: usize,
+    max_duration_secs: u64,
+    // Set when a limit stopped the recording, until the caller takes it
+    limit_reached: Option<RecordingLimit>,
+    // Save runs of mouse moves as MPATH lines rather than one line per move
+    compact_paths: bool,
+    // Pause by itself after this long without input; None never does
+    idle_pause_after: Option<Duration>,
+    last_input: Option<Instant>,
+    // Paused by idle detection rather than by the user, so the next input resumes it
+    auto_paused: bool,
+    // Idle pauses and resumes since the caller last took them
+    idle_changes: Vec<IdleChange>,
+}
+
+impl Default for MacroRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MacroRecorder {
+    /// An idle recorder with no events
     pub fn new() -> Self {
         Self {
             events: Vec::new(),
@@ -25,18 +100,50 @@ impl MacroRecorder {
             start_time: None,
             pause_start: None,
             total_pause_time: std::time::Duration::ZERO,
+            resumed_at: None,
+            history: EditHistory::new(DEFAULT_HISTORY_DEPTH),
+            shortcuts: None,
+            hover_threshold: None,
+            revision: 0,
+            capture_mode: CaptureMode::Full,
+            pending_press: None,
+            format_version: crate::events::MCR_FORMAT_VERSION,
+            epoch_ms: None,
+            max_events: DEFAULT_MAX_EVENTS,
+            max_duration_secs: DEFAULT_MAX_DURATION_SECS,
+            limit_reached: None,
+            compact_paths: true,
+            idle_pause_after: None,
+            last_input: None,
+            auto_paused: false,
+            idle_changes: Vec::new(),
         }
     }
     
+    /// Clears any previous events and starts accepting them through `add_event`
     pub fn start(&mut self) {
+        self.revision += 1;
         self.events.clear();
+        self.history.clear();
+        self.format_version = crate::events::MCR_FORMAT_VERSION;
         self.state = RecorderState::Recording;
         self.start_time = Some(Instant::now());
+        self.epoch_ms = Some(chrono::Utc::now().timestamp_millis());
+        self.limit_reached = None;
         self.pause_start = None;
         self.total_pause_time = std::time::Duration::ZERO;
+        self.resumed_at = None;
+        self.pending_press = None;
+        self.last_input = self.start_time;
+        self.auto_paused = false;
+        self.idle_changes.clear();
+        if let Some(tracker) = &mut self.shortcuts {
+            tracker.reset();
+        }
         log::info!("Recording started");
     }
     
+    /// Events added while paused are dropped and the paused time is left out of timestamps
     pub fn pause(&mut self) {
         if matches!(self.state, RecorderState::Recording) {
             self.state = RecorderState::Paused;
@@ -48,49 +155,427 @@ impl MacroRecorder {
     pub fn resume(&mut self) {
         if matches!(self.state, RecorderState::Paused) {
             self.state = RecorderState::Recording;
+            self.auto_paused = false;
+            self.last_input = Some(Instant::now());
             if let Some(pause_start) = self.pause_start.take() {
                 self.total_pause_time += pause_start.elapsed();
             }
+            self.resumed_at = Some(Instant::now());
             log::info!("Recording resumed");
         }
     }
     
+    /// Stops accepting events; the recording stays available for editing and saving
     pub fn stop(&mut self) {
+        // Already stopped by a limit, or never started
+        if matches!(self.state, RecorderState::Idle) {
+            return;
+        }
         self.state = RecorderState::Idle;
+        self.auto_paused = false;
         if let Some(pause_start) = self.pause_start.take() {
             self.total_pause_time += pause_start.elapsed();
         }
+        
+        if let Some(threshold) = self.hover_threshold {
+            let hovers = crate::hover::insert_hovers(&mut self.events, threshold);
+            if hovers > 0 {
+                self.revision += 1;
+                log::info!("Detected {} hover(s) of at least {:.2}s", hovers, threshold);
+            }
+        }
         log::info!("Recording stopped with {} events", self.events.len());
     }
     
+    /// Drops all events and edit history
     pub fn clear(&mut self) {
+        self.revision += 1;
         self.events.clear();
+        self.history.clear();
         self.state = RecorderState::Idle;
         self.start_time = None;
+        self.epoch_ms = None;
         self.pause_start = None;
         self.total_pause_time = std::time::Duration::ZERO;
+        self.resumed_at = None;
     }
     
+    /// Appends a captured event; ignored unless recording. `event.timestamp` is when it was
+    /// captured, on `events::capture_clock`, so a delay before it got here doesn't shift it.
     pub fn add_event(&mut self, mut event: MacroEvent) {
+        if self.auto_paused && matches!(self.state, RecorderState::Paused) {
+            self.resume();
+            // This very input ended the pause, so it counts
+            self.resumed_at = Some(crate::events::capture_instant(event.timestamp));
+            self.idle_changes.push(IdleChange::Resumed);
+        }
         if matches!(self.state, RecorderState::Recording) {
-            // Adjust timestamp to account for pause time
+            self.last_input = Some(Instant::now());
+            
+            // Rebase the capture time onto the recording, leaving out pause time
             if let Some(start_time) = self.start_time {
-                let adjusted_time = start_time.elapsed() - self.total_pause_time;
+                let captured_at = crate::events::capture_instant(event.timestamp);
+                if self.resumed_at.is_some_and(|resumed_at| captured_at < resumed_at) {
+                    return;
+                }
+                let adjusted_time = captured_at.saturating_duration_since(start_time)
+                    .saturating_sub(self.total_pause_time);
                 event.timestamp = adjusted_time.as_secs_f64();
             }
             
+            if let Some(tracker) = &mut self.shortcuts {
+                if let Some(action) = tracker.observe(&event) {
+                    event.data["action"] = serde_json::Value::String(action.to_string());
+                }
+            }
+            
+            if self.capture_mode == CaptureMode::ClicksOnly {
+                event = match collapse_click(&mut self.pending_press, event) {
+                    Some(click) => click,
+                    None => return,
+                };
+            }
+            
             self.events.push(event);
+            self.revision += 1;
+            self.check_limits();
+        }
+    }
+    
+    /// A sink for `InputBackend::install_hooks` that feeds `recorder`. `on_added` sees each event
+    /// as it was kept (timestamped, clicks collapsed), while the recorder is still locked
+    pub fn capture_sink<F>(recorder: Arc<Mutex<MacroRecorder>>, on_added: F) -> EventSink
+    where
+        F: Fn(&MacroEvent) + Send + Sync + 'static,
+    {
+        Box::new(move |event| {
+            if let Ok(mut recorder) = recorder.lock() {
+                let count = recorder.events.len();
+                recorder.add_event(event);
+                if let Some(added) = recorder.events.get(count) {
+                    on_added(added);
+                }
+            }
+        })
+    }
+    
+    /// Starts recording and has `backend` capture into it until `stop_capture`
+    pub fn start_capture(recorder: &Arc<Mutex<MacroRecorder>>, backend: &dyn InputBackend) -> Result<(), String> {
+        recorder.lock().map_err(|_| "Recorder state is poisoned".to_string())?.start();
+        let installed = backend.install_hooks(Self::capture_sink(recorder.clone(), |_| {}));
+        if installed.is_err() {
+            if let Ok(mut recorder) = recorder.lock() {
+                recorder.stop();
+            }
+        }
+        installed
+    }
+    
+    /// Ends the capture first, so nothing arrives after the recording stops
+    pub fn stop_capture(recorder: &Arc<Mutex<MacroRecorder>>, backend: &dyn InputBackend) {
+        backend.uninstall_hooks();
+        if let Ok(mut recorder) = recorder.lock() {
+            recorder.stop();
+        }
+    }
+    
+    /// Caps on how long and how big a recording may grow; 0 disables either one
+    pub fn set_limits(&mut self, max_events: usize, max_duration_secs: u64) {
+        self.max_events = max_events;
+        self.max_duration_secs = max_duration_secs;
+    }
+    
+    /// Pause the recording once no input has arrived for `after`, and resume on the next input.
+    /// The idle stretch is cut down to `after` in the recording; None turns this off.
+    pub fn set_idle_pause(&mut self, after: Option<Duration>) {
+        self.idle_pause_after = after;
+    }
+    
+    /// Pauses the recording if it has gone without input for the idle threshold. Needs polling,
+    /// since by definition nothing is arriving to trigger it.
+    pub fn check_idle(&mut self) -> Option<IdleChange> {
+        let after = self.idle_pause_after?;
+        let idle_for = self.last_input?.elapsed();
+        if !matches!(self.state, RecorderState::Recording) || idle_for < after {
+            return None;
+        }
+        
+        self.pause();
+        self.auto_paused = true;
+        log::info!("No input for {:.0}s; recording paused until the next input", idle_for.as_secs_f64());
+        self.idle_changes.push(IdleChange::Paused);
+        Some(IdleChange::Paused)
+    }
+    
+    /// Idle pauses and resumes since the last call, oldest first
+    pub fn take_idle_changes(&mut self) -> Vec<IdleChange> {
+        std::mem::take(&mut self.idle_changes)
+    }
+    
+    /// Whether .mcr saves write runs of mouse moves as MPATH lines. Off gives the classic
+    /// one-line-per-event format that builds before v3 can read.
+    pub fn set_compact_paths(&mut self, compact_paths: bool) {
+        self.compact_paths = compact_paths;
+    }
+    
+    /// Stops the recording if it has outgrown a limit. Also called for every added event, but
+    /// the duration limit needs polling too since nothing may be arriving.
+    pub fn check_limits(&mut self) -> Option<RecordingLimit> {
+        if !matches!(self.state, RecorderState::Recording) {
+            return None;
+        }
+        
+        let limit = if self.max_events > 0 && self.events.len() >= self.max_events {
+            RecordingLimit::Events(self.max_events)
+        } else if self.max_duration_secs > 0 && self.get_duration() as f64 >= self.max_duration_secs as f64 {
+            RecordingLimit::DurationSecs(self.max_duration_secs)
+        } else {
+            return None;
+        };
+        
+        log::warn!("Recording stopped automatically: {}", limit.describe());
+        self.stop();
+        self.limit_reached = Some(limit);
+        Some(limit)
+    }
+    
+    /// The limit that stopped the last recording, reported once
+    pub fn take_limit_reached(&mut self) -> Option<RecordingLimit> {
+        self.limit_reached.take()
+    }
+    
+    pub fn set_recognize_shortcuts(&mut self, enabled: bool) {
+        if enabled != self.shortcuts.is_some() {
+            self.shortcuts = if enabled { Some(ShortcutTracker::new()) } else { None };
+        }
+    }
+    
+    pub fn set_capture_mode(&mut self, mode: CaptureMode) {
+        self.capture_mode = mode;
+        self.pending_press = None;
+    }
+    
+    pub fn set_hover_threshold(&mut self, threshold: Option<f64>) {
+        self.hover_threshold = threshold;
+    }
+    
+    /// Insert at `index` (clamped to the end); returns where the event landed
+    pub fn insert_event(&mut self, index: usize, event: MacroEvent) -> usize {
+        let index = index.min(self.events.len());
+        self.apply_edit(EditOp::Insert { index, event });
+        index
+    }
+    
+    pub fn remove_event(&mut self, index: usize) -> Option<MacroEvent> {
+        let event = self.events.get(index)?.clone();
+        self.apply_edit(EditOp::Remove { index, event: event.clone() });
+        Some(event)
+    }
+    
+    /// Replace the event at `index`. A changed timestamp moves it to its place in time order;
+    /// returns where it ended up.
+    pub fn update_event(&mut self, index: usize, event: MacroEvent) -> Option<usize> {
+        let before = self.events.get(index)?.clone();
+        if before.timestamp == event.timestamp {
+            self.apply_edit(EditOp::Update { index, before, after: event });
+            return Some(index);
+        }
+        
+        let before = self.events.clone();
+        let mut after = before.clone();
+        after.remove(index);
+        let position = after.partition_point(|other| {
+            other.timestamp < event.timestamp || (other.timestamp == event.timestamp && other.seq <= event.seq)
+        });
+        after.insert(position, event);
+        self.apply_edit(EditOp::ReplaceAll { before, after });
+        Some(position)
+    }
+    
+    /// Exchange two events' places in the sequence; each row keeps its time slot
+    pub fn swap_events(&mut self, a: usize, b: usize) -> bool {
+        if a == b || a >= self.events.len() || b >= self.events.len() {
+            return false;
+        }
+        self.apply_edit(EditOp::Swap { a, b });
+        true
+    }
+    
+    fn apply_edit(&mut self, op: EditOp) {
+        op.apply(&mut self.events);
+        self.history.record(op);
+        self.revision += 1;
+    }
+    
+    /// Reverts the last edit; false when there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let undone = self.history.undo(&mut self.events);
+        if undone {
+            self.revision += 1;
+        }
+        undone
+    }
+    
+    pub fn redo(&mut self) -> bool {
+        let redone = self.history.redo(&mut self.events);
+        if redone {
+            self.revision += 1;
+        }
+        redone
+    }
+    
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+    
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+    
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+    
+    /// Stretch or compress the whole recording uniformly; negative or NaN factors are ignored
+    pub fn rescale_time(&mut self, factor: f64) -> bool {
+        if !factor.is_finite() || factor < 0.0 {
+            return false;
         }
+        self.retime(|timestamp| timestamp * factor)
+    }
+    
+    pub fn shift_time(&mut self, offset: f64) -> bool {
+        if !offset.is_finite() {
+            return false;
+        }
+        self.retime(|timestamp| timestamp + offset)
+    }
+    
+    fn retime<F>(&mut self, adjust: F) -> bool
+    where
+        F: Fn(f64) -> f64,
+    {
+        if self.events.is_empty() {
+            return false;
+        }
+        
+        let before = self.events.clone();
+        let mut after = before.clone();
+        for event in &mut after {
+            event.timestamp = adjust(event.timestamp).max(0.0);
+        }
+        crate::events::sort_events(&mut after);
+        
+        self.apply_edit(EditOp::ReplaceAll { before, after });
+        true
+    }
+    
+    /// Cuts the dead air at both ends: the first event moves to t=0 and mouse moves, hovers and
+    /// waits after the last real action are dropped. Returns whether anything changed.
+    pub fn trim(&mut self) -> bool {
+        let trimmed = crate::events::trim_dead_time(&self.events);
+        let unchanged = trimmed.len() == self.events.len()
+            && trimmed.first().map(|event| event.timestamp) == self.events.first().map(|event| event.timestamp);
+        if unchanged {
+            return false;
+        }
+        
+        let before = self.events.clone();
+        self.apply_edit(EditOp::ReplaceAll { before, after: trimmed });
+        true
+    }
+    
+    /// Merge bursts of wheel events; returns how many events were folded away
+    pub fn coalesce_scrolls(&mut self, window: f64) -> usize {
+        let merged = crate::events::merge_scrolls(&self.events, window);
+        let removed = self.events.len() - merged.len();
+        if removed > 0 {
+            let before = self.events.clone();
+            self.apply_edit(EditOp::ReplaceAll { before, after: merged });
+        }
+        removed
+    }
+    
+    /// Hand-authored move that takes the timestamp of the event before it so ordering holds
+    pub fn insert_mouse_move(&mut self, index: usize, x: i32, y: i32) -> usize {
+        let index = index.min(self.events.len());
+        
+        let mut event = MacroEvent::with_data(
+            0.0,
+            crate::events::EventType::MouseMove,
+            serde_json::json!({ "x": x, "y": y }),
+        );
+        event.source = Some(crate::events::TRACK_MOUSE.to_string());
+        
+        let before = self.events.clone();
+        let mut after = before.clone();
+        insert_after_previous(&mut after, index, event);
+        self.apply_edit(EditOp::ReplaceAll { before, after });
+        index
+    }
+    
+    /// Hand-authored pause before the event at `index`; everything from there on moves back by
+    /// `ms`, so the recorded gaps alone already account for the wait
+    pub fn insert_delay(&mut self, index: usize, ms: u64) -> usize {
+        let index = index.min(self.events.len());
+        
+        let before = self.events.clone();
+        let mut after = before.clone();
+        for event in &mut after[index..] {
+            event.timestamp += ms as f64 / 1000.0;
+        }
+        insert_after_previous(&mut after, index, MacroEvent::with_data(
+            0.0,
+            crate::events::EventType::Delay,
+            serde_json::json!({ "ms": ms }),
+        ));
+        
+        self.apply_edit(EditOp::ReplaceAll { before, after });
+        index
+    }
+    
+    /// Adds the events of another .mcr file after this recording, `gap` seconds after its last
+    /// event. Keys still held at the end of this recording are released first. Returns how many
+    /// events the file had.
+    pub fn append_from_file(&mut self, path: &str, gap: f64) -> Result<usize, Box<dyn std::error::Error>> {
+        let content = crate::events::read_mcr_text(path)?;
+        let (_, mut appended) = crate::events::parse_mcr(&content);
+        crate::events::normalize_timestamps(&mut appended);
+        if appended.is_empty() {
+            return Ok(0);
+        }
+        
+        let before = self.events.clone();
+        let after = crate::events::append_events(&before, &appended, gap);
+        self.apply_edit(EditOp::ReplaceAll { before, after });
+        log::info!("Appended {} events from {}", appended.len(), path);
+        Ok(appended.len())
+    }
+    
+    /// .mcr version of the file last loaded, or the current version for new recordings
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+    
+    /// Wall clock (Unix ms) when this recording started, if known. Files saved before the
+    /// header existed don't have it.
+    pub fn epoch_ms(&self) -> Option<i64> {
+        self.epoch_ms
     }
     
     pub fn get_events(&self) -> &Vec<MacroEvent> {
         &self.events
     }
     
+    pub fn get_tracks(&self) -> Vec<String> {
+        crate::events::collect_tracks(&self.events)
+    }
+    
     pub fn get_state(&self) -> RecorderState {
         self.state.clone()
     }
     
+    /// Recording time so far in seconds, not counting pauses
     pub fn get_duration(&self) -> f32 {
         if let Some(start_time) = self.start_time {
             let total_elapsed = start_time.elapsed();
@@ -106,25 +591,65 @@ impl MacroRecorder {
         }
     }
     
+    /// Writes the .mcr text format (gzip-compressed for .mcr.gz paths)
     pub fn save_to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        use std::io::Write;
-        
-        let mut file = std::fs::File::create(path)?;
+        write_mcr_file(path, &self.events, self.epoch_ms, self.compact_paths)?;
+        log::info!("Saved {} events to {}", self.events.len(), path);
+        Ok(())
+    }
+    
+    /// Writes the events before `at` seconds to `first_path` and the rest, rebased to start at
+    /// zero, to `second_path`. Returns the event count of each file.
+    pub fn save_split(&self, at: f64, first_path: &str, second_path: &str) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let (first, second) = crate::events::split_events(&self.events, at);
+        // The second half is rebased to zero, so its start moves forward by `at`
+        let second_epoch = self.epoch_ms.map(|epoch| epoch + (at * 1000.0).round() as i64);
+        write_mcr_file(first_path, &first, self.epoch_ms, self.compact_paths)?;
+        write_mcr_file(second_path, &second, second_epoch, self.compact_paths)?;
+        log::info!("Split at {:.3}s into {} ({} events) and {} ({} events)",
+            at, first_path, first.len(), second_path, second.len());
+        Ok((first.len(), second.len()))
+    }
+    
+    /// Writes every event with all of its data fields as a JSON array
+    pub fn save_to_json(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &self.events)?;
         
-        // Write header comment
-        writeln!(file, "# Macro Recording File")?;
-        writeln!(file, "# Generated by Rust Macro Recorder")?;
-        writeln!(file, "# Format: timestamp;event_type;parameters")?;
-        writeln!(file, "# Total events: {}", self.events.len())?;
-        writeln!(file, "")?;
+        log::info!("Saved {} events to {} as JSON", self.events.len(), path);
+        Ok(())
+    }
+    
+    /// Writes the recording as an AutoHotkey v2 script
+    pub fn export_ahk(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        crate::export::export_file(&self.events, crate::export::ExportFormat::Ahk, path)
+    }
+    
+    /// Writes one CSV row per event: index,timestamp,type,x,y,button,key,delta
+    pub fn export_csv(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        crate::export::export_file(&self.events, crate::export::ExportFormat::Csv, path)
+    }
+    
+    /// Bring a previously saved recording back for editing or saving elsewhere
+    pub fn load_from_file(&mut self, path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let content = crate::events::read_mcr_text(path)?;
+        let (version, mut events) = crate::events::parse_mcr(&content);
         
-        // Write events
-        for event in &self.events {
-            writeln!(file, "{}", event.to_mcr_line())?;
+        if let Some(offset) = crate::events::normalize_timestamps(&mut events) {
+            log::warn!("{} had negative timestamps; shifted the timeline by {:.3}s", path, offset);
         }
         
-        log::info!("Saved {} events to {}", self.events.len(), path);
-        Ok(())
+        self.clear();
+        self.events = events;
+        self.format_version = version;
+        self.epoch_ms = crate::events::parse_mcr_epoch(&content);
+        log::info!("Loaded {} events into recorder from {} (.mcr v{})", self.events.len(), path, version);
+        Ok(self.events.len())
+    }
+    
+    /// Event counts, mouse travel, clicks per button and average spacing of the recording
+    pub fn stats(&self) -> crate::stats::MacroStats {
+        crate::stats::compute_stats(&self.events)
     }
     
     pub fn get_event_summary(&self) -> String {
@@ -135,18 +660,23 @@ impl MacroRecorder {
         
         for event in &self.events {
             match event.event_type {
-                crate::events::EventType::KeyDown | crate::events::EventType::KeyUp => {
+                crate::events::EventType::KeyDown
+                | crate::events::EventType::KeyUp
+                | crate::events::EventType::TextInput => {
                     keyboard_events += 1;
                 }
-                crate::events::EventType::MouseDown | crate::events::EventType::MouseUp => {
+                crate::events::EventType::MouseDown
+                | crate::events::EventType::MouseUp
+                | crate::events::EventType::Click => {
                     mouse_clicks += 1;
                 }
-                crate::events::EventType::MouseMove => {
+                crate::events::EventType::MouseMove | crate::events::EventType::Hover => {
                     mouse_moves += 1;
                 }
-                crate::events::EventType::MouseScroll => {
+                crate::events::EventType::MouseScroll | crate::events::EventType::MouseHScroll => {
                     mouse_scrolls += 1;
                 }
+                crate::events::EventType::FocusChange | crate::events::EventType::Delay => {}
             }
         }
         
@@ -160,3 +690,209 @@ impl MacroRecorder {
         )
     }
 }
+
+fn write_mcr_file(path: &str, events: &[MacroEvent], epoch_ms: Option<i64>, compact_paths: bool) -> Result<(), Box<dyn std::error::Error>> {
+    use std::fmt::Write;
+    
+    let mut content = String::new();
+    let lines = crate::events::mcr_lines(events, compact_paths);
+    
+    // Write header comment; the version line must stay first
+    writeln!(content, "{}", crate::events::mcr_header(&lines))?;
+    if let Some(epoch_ms) = epoch_ms {
+        writeln!(content, "{}", crate::events::mcr_epoch_line(epoch_ms))?;
+    }
+    writeln!(content, "# Macro Recording File")?;
+    writeln!(content, "# Generated by Rust Macro Recorder")?;
+    writeln!(content, "# Format: timestamp;event_type;parameters")?;
+    writeln!(content, "# Total events: {}", events.len())?;
+    writeln!(content)?;
+    
+    // Write events
+    for line in &lines {
+        writeln!(content, "{}", line)?;
+    }
+    
+    crate::events::write_mcr_text(path, &content)?;
+    Ok(())
+}
+
+// `name_part1.mcr` and `name_part2.mcr` next to `path`, keeping a .mcr.gz extension
+pub fn split_paths(path: &str) -> (String, String) {
+    let lower = path.to_ascii_lowercase();
+    let stem_len = [".mcr.gz", ".mcr"].iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| path.len() - ext.len())
+        .unwrap_or(path.len());
+    let (stem, ext) = path.split_at(stem_len);
+    let ext = if ext.is_empty() { ".mcr" } else { ext };
+    (format!("{}_part1{}", stem, ext), format!("{}_part2{}", stem, ext))
+}
+
+// Feed events through while capturing clicks only: a press is held back until the matching
+// release arrives, then both come out as one Click at the press position and time
+// Puts a hand-authored event at `index`, sharing the timestamp of the event before it. It is
+// numbered right after that event and later events are renumbered where they'd tie, so sorting
+// (on load, or when handed to the player) leaves it where it was put.
+fn insert_after_previous(events: &mut Vec<MacroEvent>, index: usize, mut event: MacroEvent) {
+    let previous = index.checked_sub(1).map(|previous| &events[previous]);
+    event.timestamp = previous.map_or(0.0, |previous| previous.timestamp);
+    event.seq = previous.map_or(0, |previous| previous.seq + 1);
+    
+    let mut last_seq = event.seq;
+    events.insert(index, event);
+    for later in &mut events[index + 1..] {
+        if later.seq <= last_seq {
+            later.seq = last_seq + 1;
+        }
+        last_seq = later.seq;
+    }
+}
+
+pub(crate) fn collapse_click(pending_press: &mut Option<MacroEvent>, event: MacroEvent) -> Option<MacroEvent> {
+    use crate::events::EventType;
+    
+    let button = |event: &MacroEvent| event.data.get("button").and_then(|b| b.as_u64());
+    
+    match event.event_type {
+        EventType::MouseDown => {
+            *pending_press = Some(event);
+            None
+        }
+        EventType::MouseUp => {
+            let press = pending_press.take()?;
+            if button(&press) != button(&event) {
+                return None;
+            }
+            
+            let mut click = press;
+            click.event_type = EventType::Click;
+            Some(click)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use serde_json::json;
+    
+    fn key(timestamp: f64, key_name: &str) -> MacroEvent {
+        MacroEvent::with_data(timestamp, EventType::KeyDown, json!({ "key_name": key_name }))
+    }
+    
+    // A recorder holding `events` as if they had been recorded
+    fn recorder_with(events: Vec<MacroEvent>) -> MacroRecorder {
+        let mut recorder = MacroRecorder::new();
+        for event in events {
+            recorder.insert_event(usize::MAX, event);
+        }
+        recorder
+    }
+    
+    fn timestamps(recorder: &MacroRecorder) -> Vec<f64> {
+        recorder.get_events().iter().map(|event| event.timestamp).collect()
+    }
+    
+    #[test]
+    fn inserted_moves_land_at_the_index_in_time_order() {
+        let mut recorder = recorder_with(vec![MacroEvent { seq: 1, ..key(0.5, "a") }, MacroEvent { seq: 2, ..key(1.0, "b") }]);
+        
+        assert_eq!(recorder.insert_mouse_move(1, 300, 400), 1);
+        
+        let inserted = &recorder.get_events()[1];
+        assert!(inserted.is_mouse_move());
+        assert_eq!((inserted.data["x"].as_i64(), inserted.data["y"].as_i64()), (Some(300), Some(400)));
+        assert_eq!(timestamps(&recorder), vec![0.5, 0.5, 1.0]);
+        
+        // Sorting, as loading into a player or saving does, keeps it after the press it follows
+        let mut sorted = recorder.get_events().clone();
+        crate::events::sort_events(&mut sorted);
+        assert!(sorted[1].is_mouse_move());
+        
+        // A later event at the same time is renumbered so it stays after the move
+        let mut recorder = recorder_with(vec![MacroEvent { seq: 1, ..key(0.5, "a") }, MacroEvent { seq: 2, ..key(0.5, "b") }]);
+        recorder.insert_mouse_move(1, 300, 400);
+        let mut sorted = recorder.get_events().clone();
+        crate::events::sort_events(&mut sorted);
+        assert!(sorted[1].is_mouse_move());
+        assert_eq!(sorted[2].data["key_name"], "b");
+        
+        assert_eq!(recorder.insert_mouse_move(99, 1, 2), 3);
+        assert_eq!(recorder.insert_mouse_move(0, 1, 2), 0);
+        assert_eq!(recorder.get_events()[0].timestamp, 0.0);
+    }
+    
+    #[test]
+    fn undo_restores_a_deleted_event_and_redo_removes_it_again() {
+        let mut recorder = recorder_with(vec![key(0.1, "a"), key(0.2, "b"), key(0.3, "c")]);
+        assert_eq!(recorder.remove_event(1).map(|event| event.timestamp), Some(0.2));
+        assert_eq!(timestamps(&recorder), vec![0.1, 0.3]);
+        
+        assert!(recorder.undo());
+        assert_eq!(timestamps(&recorder), vec![0.1, 0.2, 0.3]);
+        assert_eq!(recorder.get_events()[1].data["key_name"], "b");
+        assert!(recorder.can_redo());
+        
+        assert!(recorder.redo());
+        assert_eq!(timestamps(&recorder), vec![0.1, 0.3]);
+        assert!(!recorder.can_redo());
+    }
+    
+    #[test]
+    fn rescaling_and_shifting_keep_the_order() {
+        let key_names = |recorder: &MacroRecorder| -> Vec<String> {
+            recorder.get_events().iter().map(|event| event.data["key_name"].as_str().unwrap().to_string()).collect()
+        };
+        let mut recorder = recorder_with(vec![key(0.5, "a"), key(1.0, "b"), key(2.0, "c")]);
+        
+        assert!(recorder.rescale_time(2.0));
+        assert_eq!(timestamps(&recorder), vec![1.0, 2.0, 4.0]);
+        
+        assert!(recorder.shift_time(-1.5));
+        assert_eq!(timestamps(&recorder), vec![0.0, 0.5, 2.5]);
+        assert_eq!(key_names(&recorder), vec!["a", "b", "c"]);
+        
+        assert!(!recorder.rescale_time(-1.0));
+        assert!(!recorder.shift_time(f64::NAN));
+        assert_eq!(timestamps(&recorder), vec![0.0, 0.5, 2.5]);
+    }
+    
+    #[test]
+    fn every_letter_of_a_typed_word_is_recorded() {
+        let mut recorder = MacroRecorder::new();
+        recorder.start();
+        for c in "proper".chars() {
+            for event_type in [EventType::KeyDown, EventType::KeyUp] {
+                let event = MacroEvent::with_data(0.0, event_type, json!({ "key_name": c.to_string() }));
+                recorder.add_event(MacroEvent { timestamp: crate::events::capture_clock(), ..event });
+            }
+        }
+        recorder.stop();
+        
+        let typed: String = recorder.get_events().iter()
+            .filter(|event| matches!(event.event_type, EventType::KeyDown))
+            .filter_map(|event| event.data["key_name"].as_str())
+            .collect();
+        assert_eq!(typed, "proper");
+    }
+    
+    
+    #[test]
+    fn recordings_have_no_limit_until_one_is_set() {
+        let mut recorder = MacroRecorder::new();
+        recorder.start();
+        for _ in 0..3 {
+            recorder.add_event(key(crate::events::capture_clock(), "a"));
+        }
+        assert_eq!(recorder.check_limits(), None);
+        assert!(matches!(recorder.get_state(), RecorderState::Recording));
+        
+        recorder.set_limits(3, 0);
+        assert_eq!(recorder.check_limits(), Some(RecordingLimit::Events(3)));
+        assert!(matches!(recorder.get_state(), RecorderState::Idle));
+        assert_eq!(recorder.take_limit_reached(), Some(RecordingLimit::Events(3)));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::library::{self, FileSummary};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_AUTOSAVE_SECS: u64 = 30;
+
+const RECOVERY_FILE_NAME: &str = "macro_recorder_recovery.mcr";
+
+pub fn recovery_path() -> PathBuf {
+    std::env::temp_dir().join(RECOVERY_FILE_NAME)
+}
+
+// An unfinished recording is only worth offering if it parses and has events in it
+pub fn find_recovery(path: &Path) -> Option<FileSummary> {
+    if !path.is_file() {
+        return None;
+    }
+    
+    match library::validate_file(path) {
+        Ok(summary) if summary.event_count > 0 => Some(summary),
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("Ignoring unreadable recovery file {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn discard_recovery(path: &Path) {
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(path) {
+            log::warn!("Failed to remove recovery file {}: {}", path.display(), e);
+        }
+    }
+}
+
+pub fn autosave_due(last_save: Option<Instant>, now: Instant, interval: Duration) -> bool {
+    match last_save {
+        Some(last) => now.duration_since(last) >= interval,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{mcr_lines, EventType, MacroEvent};
+    use std::fs;
+    
+    #[test]
+    fn only_a_recovery_file_with_events_is_offered() {
+        let path = std::env::temp_dir().join(format!("macro-recovery-test-{}.mcr", std::process::id()));
+        assert_eq!(find_recovery(&path), None);
+        
+        fs::write(&path, "").unwrap();
+        let empty = find_recovery(&path);
+        
+        let events = [MacroEvent::new(0.0, EventType::MouseMove), MacroEvent::new(1.5, EventType::MouseMove)];
+        fs::write(&path, mcr_lines(&events, false).join("\n")).unwrap();
+        let recorded = find_recovery(&path);
+        
+        discard_recovery(&path);
+        assert!(!path.exists());
+        assert_eq!(empty, None);
+        assert_eq!(recorded, Some(FileSummary { event_count: 2, duration: 1.5 }));
+    }
+    
+    #[test]
+    fn autosaves_once_per_interval() {
+        let start = Instant::now();
+        let interval = Duration::from_secs(30);
+        
+        assert!(autosave_due(None, start, interval));
+        assert!(!autosave_due(Some(start), start + Duration::from_secs(29), interval));
+        assert!(autosave_due(Some(start), start + interval, interval));
+    }
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+pub const MAX_RECENT_FILES: usize = 10;
+
+// Preferences from the Settings and View menus. Hotkeys live in their own file (hotkeys.json).
+// Fields missing from the file take their defaults, so older files keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub show_mouse_moves: bool,
+    pub show_keys: bool,
+    pub show_clicks: bool,
+    pub show_scrolls: bool,
+    pub max_log_lines: usize,
+    pub show_library: bool,
+    pub show_event_table: bool,
+    pub library_dir: Option<PathBuf>,
+    pub min_move_interval_ms: u64,
+    pub min_move_distance_px: u32,
+    pub playback_speed: f32,
+    pub abort_on_malformed: bool,
+    pub pause_on_error: bool,
+    pub click_hold_ms: u64,
+    pub activation_settle_ms: u64,
+    pub double_clicks: bool,
+    pub fixed_interval: bool,
+    pub fixed_interval_ms: u64,
+    pub repeat_count: usize,
+    pub infinite_loop: bool,
+    pub loop_delay_ms: u64,
+    // Seconds between pressing Play and the first event; 0 starts right away
+    pub playback_countdown_secs: u64,
+    pub block_input_during_playback: bool,
+    pub repair_on_load: bool,
+    // Off keeps a loaded macro's wait before its first event
+    pub rebase_on_load: bool,
+    pub failsafe_enabled: bool,
+    pub absolute_mouse_input: bool,
+    pub smooth_motion: bool,
+    pub smooth_motion_hz: u32,
+    pub humanize: bool,
+    pub humanize_timing_pct: u32,
+    pub humanize_scatter_px: u32,
+    pub humanize_seed: u64,
+    pub show_recording_overlay: bool,
+    // The window's close button hides it to the tray instead of exiting
+    pub close_to_tray: bool,
+    pub record_toggle_key: Option<u8>,
+    pub recognize_shortcuts: bool,
+    pub capture_clicks_only: bool,
+    pub detect_hovers: bool,
+    pub hover_threshold_ms: u64,
+    pub autosave_enabled: bool,
+    pub autosave_secs: u64,
+    // Recording stops by itself past these; 0 means no limit
+    pub max_events: usize,
+    pub max_duration_secs: u64,
+    // Recording pauses itself after this long without input, until the next input
+    pub idle_pause_enabled: bool,
+    pub idle_pause_secs: u64,
+    pub compact_mouse_paths: bool,
+    // Most recently opened or saved first
+    pub recent_files: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_mouse_moves: true,
+            show_keys: true,
+            show_clicks: true,
+            show_scrolls: true,
+            max_log_lines: 1000,
+            show_library: false,
+            show_event_table: true,
+            library_dir: None,
+            min_move_interval_ms: crate::hooks::DEFAULT_MIN_MOVE_INTERVAL_MS,
+            min_move_distance_px: crate::hooks::DEFAULT_MIN_MOVE_DISTANCE_PX,
+            playback_speed: 1.0,
+            abort_on_malformed: false,
+            pause_on_error: false,
+            click_hold_ms: 50,
+            activation_settle_ms: 0,
+            double_clicks: true,
+            fixed_interval: false,
+            fixed_interval_ms: 100,
+            repeat_count: 1,
+            infinite_loop: false,
+            loop_delay_ms: 0,
+            playback_countdown_secs: 3,
+            block_input_during_playback: false,
+            repair_on_load: true,
+            rebase_on_load: false,
+            failsafe_enabled: false,
+            absolute_mouse_input: false,
+            smooth_motion: false,
+            smooth_motion_hz: 60,
+            humanize: false,
+            humanize_timing_pct: crate::humanize::DEFAULT_TIMING_VARIANCE_PCT,
+            humanize_scatter_px: crate::humanize::DEFAULT_CLICK_SCATTER_PX,
+            humanize_seed: 0,
+            show_recording_overlay: true,
+            close_to_tray: false,
+            record_toggle_key: None,
+            recognize_shortcuts: true,
+            capture_clicks_only: false,
+            detect_hovers: false,
+            hover_threshold_ms: (crate::hover::DEFAULT_HOVER_THRESHOLD_SECS * 1000.0) as u64,
+            autosave_enabled: true,
+            autosave_secs: crate::recovery::DEFAULT_AUTOSAVE_SECS,
+            max_events: crate::recorder::DEFAULT_MAX_EVENTS,
+            max_duration_secs: crate::recorder::DEFAULT_MAX_DURATION_SECS,
+            idle_pause_enabled: false,
+            idle_pause_secs: crate::recorder::DEFAULT_IDLE_PAUSE_SECS,
+            compact_mouse_paths: true,
+            recent_files: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Self {
+        if !path.is_file() {
+            return Self::default();
+        }
+        
+        match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str::<Settings>(&content).map_err(|e| e.to_string()))
+        {
+            Ok(settings) => settings.sanitized(),
+            Err(e) => {
+                log::warn!("Ignoring unreadable settings {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    // A hand-edited file can hold values the sliders would never produce
+    fn sanitized(mut self) -> Self {
+        self.playback_speed = if self.playback_speed.is_finite() { self.playback_speed.clamp(0.1, 10.0) } else { 1.0 };
+        self.max_log_lines = self.max_log_lines.max(1);
+        self.repeat_count = self.repeat_count.max(1);
+        self.record_toggle_key = self.record_toggle_key.filter(|n| (1..=12).contains(n));
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        self
+    }
+}
+
+// Move `path` to the front of the MRU list, dropping any older copy and the overflow
+pub fn push_recent(recent: &mut Vec<String>, path: &str) {
+    recent.retain(|existing| existing != path);
+    recent.insert(0, path.to_string());
+    recent.truncate(MAX_RECENT_FILES);
+}
+
+// Next to hotkeys.json in the user's config dir
+pub fn settings_path() -> PathBuf {
+    crate::hotkeys::hotkey_config_path().with_file_name(SETTINGS_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn settings_file(test: &str, content: Option<&str>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}-settings.json", test, std::process::id()));
+        match content {
+            Some(content) => std::fs::write(&path, content).unwrap(),
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        path
+    }
+    
+    #[test]
+    fn missing_or_corrupt_files_load_the_defaults() {
+        let missing = settings_file("missing", None);
+        assert_eq!(Settings::load(&missing), Settings::default());
+        
+        let corrupt = settings_file("corrupt", Some("{ \"playback_speed\": "));
+        assert_eq!(Settings::load(&corrupt), Settings::default());
+        let wrong_type = settings_file("wrong-type", Some("{ \"repeat_count\": \"twice\" }"));
+        assert_eq!(Settings::load(&wrong_type), Settings::default());
+        let _ = std::fs::remove_file(corrupt);
+        let _ = std::fs::remove_file(wrong_type);
+    }
+    
+    #[test]
+    fn out_of_range_values_are_brought_back_in_range() {
+        let path = settings_file("out-of-range", Some(r#"{
+            "playback_speed": 500.0,
+            "max_log_lines": 0,
+            "repeat_count": 0,
+            "record_toggle_key": 13,
+            "show_keys": false
+        }"#));
+        let loaded = Settings::load(&path);
+        let _ = std::fs::remove_file(path);
+        
+        assert_eq!(loaded.playback_speed, 10.0);
+        assert_eq!(loaded.max_log_lines, 1);
+        assert_eq!(loaded.repeat_count, 1);
+        assert_eq!(loaded.record_toggle_key, None);
+        // Values that were fine are kept, and missing ones take their defaults
+        assert!(!loaded.show_keys);
+        assert_eq!(loaded.loop_delay_ms, Settings::default().loop_delay_ms);
+        
+        // JSON can't say NaN, but sanitized() is what stands between the file and the player
+        let nan = Settings { playback_speed: f32::NAN, ..Settings::default() }.sanitized();
+        assert_eq!(nan.playback_speed, 1.0);
+        let long = Settings { recent_files: vec!["a.mcr".to_string(); 20], ..Settings::default() }.sanitized();
+        assert_eq!(long.recent_files.len(), MAX_RECENT_FILES);
+    }
+}
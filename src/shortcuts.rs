@@ -0,0 +1,108 @@
+use crate::events::{EventType, MacroEvent};
+
+// Common chords and the action they stand for; matched regardless of key order
+const SHORTCUTS: &[(&[&str], &str)] = &[
+    (&["ctrl", "c"], "Copy"),
+    (&["ctrl", "x"], "Cut"),
+    (&["ctrl", "v"], "Paste"),
+    (&["ctrl", "z"], "Undo"),
+    (&["ctrl", "y"], "Redo"),
+    (&["ctrl", "shift", "z"], "Redo"),
+    (&["ctrl", "a"], "Select All"),
+    (&["ctrl", "s"], "Save"),
+    (&["ctrl", "f"], "Find"),
+    (&["alt", "f4"], "Close Window"),
+    (&["alt", "tab"], "Switch Window"),
+];
+
+// Low-level hooks report left/right modifiers by their own VK codes
+fn canonical_key(key: &str) -> &str {
+    match key {
+        "vk_160" | "vk_161" => "shift",
+        "vk_162" | "vk_163" => "ctrl",
+        "vk_164" | "vk_165" => "alt",
+        other => other,
+    }
+}
+
+fn is_modifier(key: &str) -> bool {
+    matches!(canonical_key(key), "shift" | "ctrl" | "alt")
+}
+
+pub fn recognize_shortcut(keys: &[&str]) -> Option<&'static str> {
+    let mut pressed: Vec<&str> = keys.iter().map(|key| canonical_key(key)).collect();
+    pressed.sort_unstable();
+    pressed.dedup();
+    
+    SHORTCUTS.iter()
+        .find(|(chord, _)| {
+            chord.len() == pressed.len() && chord.iter().all(|key| pressed.contains(key))
+        })
+        .map(|(_, action)| *action)
+}
+
+// Follows held keys across a key stream and names the chord completed by each key-down
+#[derive(Debug, Default)]
+pub struct ShortcutTracker {
+    held: Vec<String>,
+}
+
+impl ShortcutTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn observe(&mut self, event: &MacroEvent) -> Option<&'static str> {
+        let key = event.data.get("key_name").and_then(|k| k.as_str())?;
+        
+        match event.event_type {
+            EventType::KeyDown => {
+                if !self.held.iter().any(|held| held == key) {
+                    self.held.push(key.to_string());
+                }
+                if is_modifier(key) {
+                    return None;
+                }
+                
+                let keys: Vec<&str> = self.held.iter().map(|k| k.as_str()).collect();
+                recognize_shortcut(&keys)
+            }
+            EventType::KeyUp => {
+                self.held.retain(|held| held != key);
+                None
+            }
+            _ => None,
+        }
+    }
+    
+    pub fn reset(&mut self) {
+        self.held.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn recognizes_chords_in_any_order() {
+        assert_eq!(recognize_shortcut(&["ctrl", "c"]), Some("Copy"));
+        assert_eq!(recognize_shortcut(&["v", "ctrl"]), Some("Paste"));
+        assert_eq!(recognize_shortcut(&["vk_163", "shift", "z"]), Some("Redo"));
+        assert_eq!(recognize_shortcut(&["ctrl", "shift", "c"]), None);
+        assert_eq!(recognize_shortcut(&["c"]), None);
+    }
+    
+    #[test]
+    fn the_tracker_names_the_key_that_completes_a_chord() {
+        let key = |event_type, key_name| MacroEvent::with_data(0.0, event_type, json!({ "key_name": key_name }));
+        let mut tracker = ShortcutTracker::new();
+        
+        assert_eq!(tracker.observe(&key(EventType::KeyDown, "vk_162")), None);
+        assert_eq!(tracker.observe(&key(EventType::KeyDown, "s")), Some("Save"));
+        assert_eq!(tracker.observe(&key(EventType::KeyUp, "vk_162")), None);
+        assert_eq!(tracker.observe(&key(EventType::KeyUp, "s")), None);
+        assert_eq!(tracker.observe(&key(EventType::KeyDown, "s")), None);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::events::{EventType, MacroEvent};
+use std::ops::Range;
+
+// Overview of a recording: how heavy it is and how human it looks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MacroStats {
+    pub total_events: usize,
+    // Count per event type, in the order types first appear
+    pub per_type: Vec<(EventType, usize)>,
+    // Straight-line distance between consecutive mouse moves, in pixels
+    pub mouse_travel_px: f64,
+    // Presses (MouseDown or Click) per button number, lowest button first
+    pub clicks_per_button: Vec<(u64, usize)>,
+    // None with fewer than two events
+    pub average_interval_ms: Option<f64>,
+}
+
+pub fn compute_stats(events: &[MacroEvent]) -> MacroStats {
+    let mut stats = MacroStats {
+        total_events: events.len(),
+        ..MacroStats::default()
+    };
+    
+    let mut last_move: Option<(i64, i64)> = None;
+    for event in events {
+        match stats.per_type.iter_mut().find(|(event_type, _)| *event_type == event.event_type) {
+            Some((_, count)) => *count += 1,
+            None => stats.per_type.push((event.event_type.clone(), 1)),
+        }
+        
+        let position = event.data.get("x").and_then(|x| x.as_i64())
+            .zip(event.data.get("y").and_then(|y| y.as_i64()));
+        match (&event.event_type, position) {
+            (EventType::MouseMove, Some((x, y))) => {
+                if let Some((last_x, last_y)) = last_move {
+                    stats.mouse_travel_px += ((x - last_x) as f64).hypot((y - last_y) as f64);
+                }
+                last_move = Some((x, y));
+            }
+            (EventType::MouseDown | EventType::Click, _) => {
+                if let Some(button) = event.data.get("button").and_then(|b| b.as_u64()) {
+                    match stats.clicks_per_button.iter_mut().find(|(known, _)| *known == button) {
+                        Some((_, count)) => *count += 1,
+                        None => stats.clicks_per_button.push((button, 1)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    stats.clicks_per_button.sort_by_key(|(button, _)| *button);
+    
+    if let (Some(first), Some(last)) = (events.first(), events.last()) {
+        if events.len() > 1 {
+            stats.average_interval_ms = Some((last.timestamp - first.timestamp).max(0.0) * 1000.0 / (events.len() - 1) as f64);
+        }
+    }
+    stats
+}
+
+// Gaps past the last bucket are lumped together so one long idle doesn't create thousands of buckets
+pub const MAX_HISTOGRAM_BUCKETS: usize = 50;
+
+// Time between consecutive events, in milliseconds
+pub fn inter_event_gaps(events: &[MacroEvent]) -> Vec<u64> {
+    events.windows(2)
+        .map(|pair| ((pair[1].timestamp - pair[0].timestamp).max(0.0) * 1000.0).round() as u64)
+        .collect()
+}
+
+// Count inter-event gaps per `bucket_ms`-wide range, from 0 up to the largest gap.
+// The final bucket is open-ended (ends at u64::MAX) when gaps overflow MAX_HISTOGRAM_BUCKETS.
+pub fn timing_histogram(events: &[MacroEvent], bucket_ms: u64) -> Vec<(Range<u64>, usize)> {
+    let gaps = inter_event_gaps(events);
+    let bucket_ms = bucket_ms.max(1);
+    
+    let largest = match gaps.iter().max() {
+        Some(largest) => *largest,
+        None => return Vec::new(),
+    };
+    let bucket_count = ((largest / bucket_ms) as usize + 1).min(MAX_HISTOGRAM_BUCKETS);
+    
+    let mut histogram: Vec<(Range<u64>, usize)> = (0..bucket_count)
+        .map(|i| {
+            let start = i as u64 * bucket_ms;
+            (start..start + bucket_ms, 0)
+        })
+        .collect();
+    if let Some((last, _)) = histogram.last_mut() {
+        if largest >= last.end {
+            last.end = u64::MAX;
+        }
+    }
+    
+    for gap in gaps {
+        let bucket = ((gap / bucket_ms) as usize).min(bucket_count - 1);
+        histogram[bucket].1 += 1;
+    }
+    
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn events_at(timestamps: &[f64]) -> Vec<MacroEvent> {
+        timestamps.iter().map(|timestamp| MacroEvent::new(*timestamp, EventType::MouseMove)).collect()
+    }
+    
+    #[test]
+    fn gaps_are_counted_per_bucket() {
+        // Gaps of 10, 20, 40, 120 and 90ms
+        let events = events_at(&[0.0, 0.01, 0.03, 0.07, 0.19, 0.28]);
+        
+        assert_eq!(inter_event_gaps(&events), vec![10, 20, 40, 120, 90]);
+        assert_eq!(timing_histogram(&events, 50), vec![(0..50, 3), (50..100, 1), (100..150, 1)]);
+        assert_eq!(timing_histogram(&events_at(&[1.0]), 50), vec![]);
+    }
+    
+    #[test]
+    fn a_long_idle_lands_in_an_open_ended_last_bucket() {
+        let events = events_at(&[0.0, 0.001, 60.0]);
+        let histogram = timing_histogram(&events, 10);
+        
+        assert_eq!(histogram.len(), MAX_HISTOGRAM_BUCKETS);
+        assert_eq!(histogram[0], (0..10, 1));
+        assert_eq!(histogram[MAX_HISTOGRAM_BUCKETS - 1], (490..u64::MAX, 1));
+    }
+}
@@ -0,0 +1,108 @@
+use windows::core::PWSTR;
+use windows::Win32::{
+    Foundation::{CloseHandle, BOOL, HWND, LPARAM, POINT, RECT},
+    Graphics::Gdi::ClientToScreen,
+    System::Threading::{OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION},
+    UI::WindowsAndMessaging::*,
+};
+use crate::hooks::window_title;
+
+// What recording is limited to: one top-level window, or every window of a process
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordTarget {
+    Window(isize),
+    // Executable file name such as notepad.exe, compared case-insensitively
+    Process(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowInfo {
+    pub hwnd: isize,
+    pub title: String,
+    pub process: Option<String>,
+}
+
+impl WindowInfo {
+    pub fn label(&self) -> String {
+        match &self.process {
+            Some(process) => format!("{} ({})", self.title, process),
+            None => self.title.clone(),
+        }
+    }
+}
+
+// Visible, titled top-level windows in z-order
+pub fn visible_windows() -> Vec<WindowInfo> {
+    unsafe extern "system" fn collect(hwnd: HWND, data: LPARAM) -> BOOL {
+        let windows = &mut *(data.0 as *mut Vec<WindowInfo>);
+        if IsWindowVisible(hwnd).as_bool() {
+            let title = window_title(hwnd);
+            if !title.is_empty() {
+                windows.push(WindowInfo { hwnd: hwnd.0, title, process: process_name(hwnd) });
+            }
+        }
+        BOOL(1)
+    }
+    
+    let mut windows: Vec<WindowInfo> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(Some(collect), LPARAM(&mut windows as *mut _ as isize));
+    }
+    windows
+}
+
+// File name of the executable that owns the window
+pub fn process_name(hwnd: HWND) -> Option<String> {
+    let mut pid = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+        
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 260];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        path.rsplit(['\\', '/']).next().map(|name| name.to_string())
+    }
+}
+
+// Whether `hwnd` (usually the foreground window) belongs to the target
+pub fn window_matches(target: &RecordTarget, hwnd: HWND) -> bool {
+    match target {
+        RecordTarget::Window(target_hwnd) => {
+            // Dialogs and popups owned by the target count as the target
+            hwnd.0 == *target_hwnd || unsafe { GetAncestor(hwnd, GA_ROOTOWNER) }.0 == *target_hwnd
+        }
+        RecordTarget::Process(name) => process_name(hwnd)
+            .map(|process| process.eq_ignore_ascii_case(name))
+            .unwrap_or(false),
+    }
+}
+
+// The window's client area in screen coordinates
+pub fn client_rect(hwnd: HWND) -> Option<RECT> {
+    let mut rect = RECT::default();
+    unsafe {
+        GetClientRect(hwnd, &mut rect).ok()?;
+        let mut origin = POINT { x: rect.left, y: rect.top };
+        if !ClientToScreen(hwnd, &mut origin).as_bool() {
+            return None;
+        }
+        Some(RECT {
+            left: origin.x,
+            top: origin.y,
+            right: origin.x + rect.right - rect.left,
+            bottom: origin.y + rect.bottom - rect.top,
+        })
+    }
+}
+
+pub fn rect_contains(rect: &RECT, x: i32, y: i32) -> bool {
+    x >= rect.left && x < rect.right && y >= rect.top && y < rect.bottom
+}
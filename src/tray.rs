@@ -0,0 +1,297 @@
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use windows::core::{w, PCWSTR};
+use windows::Win32::{
+    Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM},
+    System::LibraryLoader::GetModuleHandleW,
+    UI::Shell::{Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW},
+    UI::WindowsAndMessaging::*,
+};
+
+const TRAY_ICON_ID: u32 = 1;
+const TRAY_CALLBACK_MESSAGE: u32 = WM_APP + 1;
+const TRAY_WINDOW_CLASS: PCWSTR = w!("MacroRecorderTray");
+
+const MENU_STATUS: usize = 1;
+const MENU_SHOW: usize = 2;
+const MENU_PLAY: usize = 3;
+const MENU_STOP: usize = 4;
+const MENU_QUIT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrayCommand {
+    Play,
+    Stop,
+    // Exit for real, through the app's usual exit path (the window is shown first)
+    Quit,
+}
+
+// Written straight against Shell_NotifyIconW instead of the tray-icon crate: that crate brings
+// muda and a second set of Windows bindings for what is one icon and a five-entry menu, and it
+// isn't among the dependencies this is built from. The windows crate already covers it.
+// There's one tray icon per process, so the window procedure reaches its state through statics.
+
+// Picked from the menu, waiting for the app to take them
+static COMMANDS: Mutex<Vec<TrayCommand>> = Mutex::new(Vec::new());
+// Shown greyed out at the top of the menu and as the icon's tooltip
+static STATUS: Mutex<String> = Mutex::new(String::new());
+// Title of the app's main window, which Show brings back
+static WINDOW_TITLE: Mutex<String> = Mutex::new(String::new());
+// Lets the app handle a pick right away even when it isn't repainting on its own
+static WAKE: Mutex<Option<Box<dyn Fn() + Send>>> = Mutex::new(None);
+
+// A notification-area icon with Show/Play/Stop/Quit entries, run by a hidden window on its
+// own message-loop thread. Dropping it removes the icon.
+pub struct TrayIcon {
+    hwnd: isize,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TrayIcon {
+    // `window_title` is the app's main window; `wake` is called, on the tray's thread, after
+    // every menu pick
+    pub fn create(status: &str, window_title: &str, wake: impl Fn() + Send + 'static) -> Result<Self, String> {
+        if let Ok(mut current) = STATUS.lock() {
+            *current = status.to_string();
+        }
+        if let Ok(mut current) = WINDOW_TITLE.lock() {
+            *current = window_title.to_string();
+        }
+        if let Ok(mut current) = WAKE.lock() {
+            *current = Some(Box::new(wake));
+        }
+        let (ready_tx, ready_rx) = mpsc::channel();
+        
+        let thread = thread::spawn(move || unsafe {
+            let hwnd = match create_tray_window() {
+                Ok(hwnd) => hwnd,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if !Shell_NotifyIconW(NIM_ADD, &icon_data(hwnd)).as_bool() {
+                let _ = DestroyWindow(hwnd);
+                let _ = ready_tx.send(Err("Failed to add the tray icon".to_string()));
+                return;
+            }
+            let _ = ready_tx.send(Ok(hwnd.0));
+            
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, HWND(0), 0, 0).as_bool() {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+            
+            Shell_NotifyIconW(NIM_DELETE, &icon_data(hwnd));
+        });
+        
+        match ready_rx.recv() {
+            Ok(Ok(hwnd)) => Ok(Self { hwnd, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err("Tray thread exited unexpectedly".to_string()),
+        }
+    }
+    
+    // Cheap when nothing changed, so it can be called every frame
+    pub fn set_status(&self, status: &str) {
+        match STATUS.lock() {
+            Ok(mut current) if *current != status => *current = status.to_string(),
+            _ => return,
+        }
+        unsafe {
+            Shell_NotifyIconW(NIM_MODIFY, &icon_data(HWND(self.hwnd)));
+        }
+    }
+    
+    // Menu picks since the last call, oldest first
+    pub fn take_commands(&self) -> Vec<TrayCommand> {
+        take_queued()
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostMessageW(HWND(self.hwnd), WM_CLOSE, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Ok(mut wake) = WAKE.lock() {
+            *wake = None;
+        }
+    }
+}
+
+// What a menu entry asks of the app; Show is handled on the tray's thread instead
+fn menu_command(id: usize) -> Option<TrayCommand> {
+    match id {
+        MENU_PLAY => Some(TrayCommand::Play),
+        MENU_STOP => Some(TrayCommand::Stop),
+        MENU_QUIT => Some(TrayCommand::Quit),
+        _ => None,
+    }
+}
+
+// Holds a pick for take_commands and wakes the app to take it
+fn queue_command(command: TrayCommand) {
+    if let Ok(mut commands) = COMMANDS.lock() {
+        commands.push(command);
+    }
+    if let Ok(wake) = WAKE.lock() {
+        if let Some(wake) = wake.as_ref() {
+            wake();
+        }
+    }
+}
+
+fn take_queued() -> Vec<TrayCommand> {
+    COMMANDS.lock().map(|mut commands| std::mem::take(&mut *commands)).unwrap_or_default()
+}
+
+fn to_wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+// Never shown; it only receives the icon's clicks and the menu's commands
+unsafe fn create_tray_window() -> Result<HWND, String> {
+    let instance: HINSTANCE = GetModuleHandleW(None).map_err(|e| e.to_string())?.into();
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(tray_window_proc),
+        hInstance: instance,
+        lpszClassName: TRAY_WINDOW_CLASS,
+        ..Default::default()
+    };
+    // Fails harmlessly when an earlier tray icon already registered the class
+    RegisterClassW(&class);
+    
+    let hwnd = CreateWindowExW(
+        WINDOW_EX_STYLE(0),
+        TRAY_WINDOW_CLASS,
+        w!("Macro Recorder"),
+        WS_OVERLAPPED,
+        0, 0, 0, 0,
+        None,
+        None,
+        instance,
+        None,
+    );
+    if hwnd.0 == 0 {
+        return Err("Failed to create the tray window".to_string());
+    }
+    Ok(hwnd)
+}
+
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    let mut data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: TRAY_ICON_ID,
+        uFlags: NIF_ICON | NIF_MESSAGE | NIF_TIP,
+        uCallbackMessage: TRAY_CALLBACK_MESSAGE,
+        hIcon: unsafe { LoadIconW(None, IDI_APPLICATION) }.unwrap_or_default(),
+        ..Default::default()
+    };
+    let status = STATUS.lock().map(|status| status.clone()).unwrap_or_default();
+    // The tooltip holds 127 characters plus the terminator
+    for (slot, unit) in data.szTip.iter_mut().zip(status.encode_utf16().take(127)) {
+        *slot = unit;
+    }
+    data
+}
+
+unsafe fn show_menu(hwnd: HWND) {
+    let menu = match CreatePopupMenu() {
+        Ok(menu) => menu,
+        Err(_) => return,
+    };
+    let status = to_wide(&STATUS.lock().map(|status| status.clone()).unwrap_or_default());
+    let _ = AppendMenuW(menu, MF_STRING | MF_GRAYED, MENU_STATUS, PCWSTR(status.as_ptr()));
+    let _ = AppendMenuW(menu, MF_SEPARATOR, 0, None);
+    let _ = AppendMenuW(menu, MF_STRING, MENU_SHOW, w!("Show window"));
+    let _ = AppendMenuW(menu, MF_STRING, MENU_PLAY, w!("Play"));
+    let _ = AppendMenuW(menu, MF_STRING, MENU_STOP, w!("Stop"));
+    let _ = AppendMenuW(menu, MF_SEPARATOR, 0, None);
+    let _ = AppendMenuW(menu, MF_STRING, MENU_QUIT, w!("Quit"));
+    
+    let mut cursor = POINT::default();
+    let _ = GetCursorPos(&mut cursor);
+    // Without this the menu stays open when the user clicks somewhere else
+    SetForegroundWindow(hwnd);
+    let _ = TrackPopupMenu(menu, TPM_RIGHTBUTTON, cursor.x, cursor.y, 0, hwnd, None);
+    let _ = DestroyMenu(menu);
+}
+
+// Done here rather than by the app: while its window is hidden the app may not get a frame in
+// which to react
+unsafe fn show_main_window() {
+    let title = WINDOW_TITLE.lock().map(|title| title.clone()).unwrap_or_default();
+    if let Some(hwnd) = crate::hooks::find_window(&title) {
+        ShowWindow(HWND(hwnd), SW_SHOW);
+        SetForegroundWindow(HWND(hwnd));
+    }
+}
+
+unsafe extern "system" fn tray_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        TRAY_CALLBACK_MESSAGE => {
+            // Without NIM_SETVERSION, lparam is the mouse message itself
+            if matches!(lparam.0 as u32, WM_LBUTTONUP | WM_RBUTTONUP) {
+                show_menu(hwnd);
+            }
+            LRESULT(0)
+        }
+        WM_COMMAND => {
+            let id = wparam.0 & 0xFFFF;
+            // Quit shows it too, so an unsaved-recording prompt can be answered
+            if matches!(id, MENU_SHOW | MENU_QUIT) {
+                show_main_window();
+            }
+            if let Some(command) = menu_command(id) {
+                queue_command(command);
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    
+    #[test]
+    fn menu_picks_queue_in_order_and_wake_the_app() {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = wakes.clone();
+        *WAKE.lock().unwrap() = Some(Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+        
+        for id in [MENU_STATUS, MENU_PLAY, MENU_SHOW, MENU_STOP, MENU_QUIT, 0] {
+            if let Some(command) = menu_command(id) {
+                queue_command(command);
+            }
+        }
+        
+        // The status line and Show never reach the app
+        assert_eq!(take_queued(), vec![TrayCommand::Play, TrayCommand::Stop, TrayCommand::Quit]);
+        assert_eq!(wakes.load(Ordering::SeqCst), 3);
+        // Taking them empties the queue
+        assert!(take_queued().is_empty());
+        
+        *WAKE.lock().unwrap() = None;
+        queue_command(TrayCommand::Stop);
+        assert_eq!(take_queued(), vec![TrayCommand::Stop]);
+    }
+}